@@ -5,7 +5,8 @@ use crate::Errors;
 use proc_macro2::{Span, TokenStream};
 use crate::relations::Relations;
 
-const ID_PARSE_ERROR : &'static str = "Could not parse id parameter. id must be a string containing either a field name.";
+const ID_PARSE_ERROR : &str = "Could not parse id parameter. id must be a string containing either a field name.";
+const TYPED_KEY_ERROR : &str = r#"typed_key must be the type wrapped by the generated key as a string litteral, e.g. `#[entity(typed_key = "u32")]`, and the id field must be declared with the generated `<StructName>Id` type."#;
 
 
 
@@ -16,6 +17,11 @@ pub struct EntityData {
     pub version : Option<u32>,
     pub id : Option<Ident>,
     pub id_type : Option<syn::Type>,
+    pub migrate_from : Option<syn::Path>,
+    pub key_as_bytes : Option<syn::Path>,
+    pub unique : Vec<Ident>,
+    pub timestamp_field : Option<Ident>,
+    pub typed_key : Option<syn::Type>,
     pub children : Relations,
     pub siblings : Relations,
     pub fields : Vec<(syn::Visibility,syn::Ident,syn::Type)>,
@@ -23,8 +29,10 @@ pub struct EntityData {
 
 impl EntityData {
     pub fn parse(span : &Span, attrs : &[Attribute], fields : &Fields, errors : &mut Errors) -> EntityData {
-        let mut entity_data = EntityData::default();
-        entity_data.crate_name = "reindeer".to_string();
+        let mut entity_data = EntityData {
+            crate_name : "reindeer".to_string(),
+            ..Default::default()
+        };
         entity_data.parse_fields( fields, errors);
         for attr in attrs {
             if attr.path.is_ident("entity") {
@@ -46,7 +54,12 @@ impl EntityData {
     fn parse_entity_args(&mut self, meta : &Meta, errors : &mut Errors) {
         match meta {
             Meta::Path(p) => {
-                errors.push(syn::Error::new_spanned(p, "Unrecognized argument. Accepted arguments are 'name', 'version' and 'id'"));
+                if p.is_ident("typed_key") {
+                    errors.push(syn::Error::new_spanned(p, TYPED_KEY_ERROR));
+                }
+                else {
+                    errors.push(syn::Error::new_spanned(p, "Unrecognized argument. Accepted arguments are 'name', 'version', 'id', 'migrate_from', 'key_as_bytes', 'unique', 'timestamp_field' and 'typed_key'"));
+                }
             },
             Meta::List(l) => {
                 for token in &l.nested {
@@ -55,7 +68,7 @@ impl EntityData {
                             self.parse_entity_args(m, errors);
                         },
                         syn::NestedMeta::Lit(l) => {
-                            errors.push(syn::Error::new_spanned(l, "Unrecognized argument. Accepted arguments are 'name', 'version' and 'id'"));
+                            errors.push(syn::Error::new_spanned(l, "Unrecognized argument. Accepted arguments are 'name', 'version', 'id', 'migrate_from', 'key_as_bytes', 'unique', 'timestamp_field' and 'typed_key'"));
                         },
                     }
                 }
@@ -79,7 +92,7 @@ impl EntityData {
                                     self.version = Some(int);
                                 },
                                 Err(_) => {
-                                    errors.push(syn::Error::new_spanned(&int, "Store version must be a positive integer."))
+                                    errors.push(syn::Error::new_spanned(int, "Store version must be a positive integer."))
                                 },
                             }
                         },
@@ -98,6 +111,81 @@ impl EntityData {
                         }
                     }
                 }
+                else if nv.path.is_ident("migrate_from") {
+                    match &nv.lit {
+                        syn::Lit::Str(str) => {
+                            match str.parse::<syn::Path>() {
+                                Ok(path) => {
+                                    self.migrate_from = Some(path);
+                                },
+                                Err(_) => {
+                                    errors.push(syn::Error::new_spanned(str, "migrate_from must be the path of a function."))
+                                },
+                            }
+                        },
+                        _ => {
+                            errors.push(syn::Error::new_spanned(&nv.lit, "migrate_from must be the path of a function as a string litteral."))
+                        }
+                    }
+                }
+                else if nv.path.is_ident("key_as_bytes") {
+                    match &nv.lit {
+                        syn::Lit::Str(str) => {
+                            match str.parse::<syn::Path>() {
+                                Ok(path) => {
+                                    self.key_as_bytes = Some(path);
+                                },
+                                Err(_) => {
+                                    errors.push(syn::Error::new_spanned(str, "key_as_bytes must be the path of a function."))
+                                },
+                            }
+                        },
+                        _ => {
+                            errors.push(syn::Error::new_spanned(&nv.lit, "key_as_bytes must be the path of a function as a string litteral."))
+                        }
+                    }
+                }
+                else if nv.path.is_ident("unique") {
+                    match &nv.lit {
+                        syn::Lit::Str(str) => {
+                            for field in str.value().split(',') {
+                                match syn::parse_str::<Ident>(field.trim()) {
+                                    Ok(ident) => self.unique.push(Ident::new(&ident.to_string(), str.span())),
+                                    Err(_) => errors.push(syn::Error::new_spanned(str, "unique must be a comma-separated list of field names.")),
+                                }
+                            }
+                        },
+                        _ => {
+                            errors.push(syn::Error::new_spanned(&nv.lit, "unique must be a comma-separated list of field names as a string litteral."))
+                        }
+                    }
+                }
+                else if nv.path.is_ident("timestamp_field") {
+                    match &nv.lit {
+                        syn::Lit::Str(str) => {
+                            match syn::parse_str::<Ident>(&str.value()) {
+                                Ok(ident) => self.timestamp_field = Some(Ident::new(&ident.to_string(), str.span())),
+                                Err(_) => errors.push(syn::Error::new_spanned(str, "timestamp_field must be a field name.")),
+                            }
+                        },
+                        _ => {
+                            errors.push(syn::Error::new_spanned(&nv.lit, "timestamp_field must be a field name as a string litteral."))
+                        }
+                    }
+                }
+                else if nv.path.is_ident("typed_key") {
+                    match &nv.lit {
+                        syn::Lit::Str(str) => {
+                            match str.parse::<syn::Type>() {
+                                Ok(ty) => self.typed_key = Some(ty),
+                                Err(_) => errors.push(syn::Error::new_spanned(str, TYPED_KEY_ERROR)),
+                            }
+                        },
+                        _ => {
+                            errors.push(syn::Error::new_spanned(&nv.lit, TYPED_KEY_ERROR))
+                        }
+                    }
+                }
                 else if nv.path.is_ident("crate") {
                     match &nv.lit {
                         syn::Lit::Str(str) => {
@@ -109,7 +197,7 @@ impl EntityData {
                     }
                 }
                 else {
-                    errors.push(syn::Error::new_spanned(&nv.path, "Unrecognized argument. Accepted arguments are 'name', 'version' and 'id'"))
+                    errors.push(syn::Error::new_spanned(&nv.path, "Unrecognized argument. Accepted arguments are 'name', 'version', 'id', 'migrate_from', 'key_as_bytes', 'unique', 'timestamp_field' and 'typed_key'"))
                 }
             },
         }
@@ -141,6 +229,12 @@ impl EntityData {
             Fields::Named(fields) => {
                 for field in fields.named.iter() {
                     let field = field.clone();
+                    if let Some(attr) = field.attrs.iter().find(|attr| attr.path.is_ident("entity_skip")) {
+                        if !is_skipped_by_serde(&field.attrs) {
+                            errors.push(syn::Error::new_spanned(attr, "Fields marked with #[entity_skip] must also be marked with #[serde(skip)], so that they are not persisted."));
+                        }
+                        continue;
+                    }
                     self.fields.push((field.vis,field.ident.unwrap(),field.ty));
                 }
             },
@@ -151,7 +245,7 @@ impl EntityData {
     fn check(&mut self, span : &Span, errors : &mut Errors){
         match &self.id {
             None => {
-                let id_field = self.fields.iter().find(|e| e.1.to_string() == "id");
+                let id_field = self.fields.iter().find(|e| e.1 == "id");
                 if let Some(id_field) = id_field {
                     self.id = Some(id_field.1.clone());
                     self.id_type = Some(id_field.2.clone());
@@ -164,11 +258,21 @@ impl EntityData {
                 self.check_id(&id.clone(), errors);
             }
         }
+        for field in &self.unique {
+            if !self.fields.iter().any(|e| e.1 == *field) {
+                errors.push(syn::Error::new(field.span(), format!("Cannot find unique field '{}' in current type",field)));
+            }
+        }
+        if let Some(field) = &self.timestamp_field {
+            if !self.fields.iter().any(|e| e.1 == *field) {
+                errors.push(syn::Error::new(field.span(), format!("Cannot find timestamp field '{}' in current type",field)));
+            }
+        }
 
 
     }
     fn check_id(&mut self, ident : &Ident, errors : &mut Errors) {
-        match self.fields.iter().find(|e| e.1.to_string() == ident.to_string()) {
+        match self.fields.iter().find(|e| e.1 == *ident) {
             Some(id) => {
                 self.id_type = Some(id.2.clone());
             }
@@ -194,4 +298,23 @@ impl EntityData {
         }
     }
 
-}
\ No newline at end of file
+}
+
+fn is_skipped_by_serde(attrs : &[Attribute]) -> bool {
+    let mut skip_serializing = false;
+    let mut skip_deserializing = false;
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("serde")) {
+        if let Ok(Meta::List(list)) = attr.parse_meta() {
+            for nested in &list.nested {
+                if let syn::NestedMeta::Meta(Meta::Path(path)) = nested {
+                    if path.is_ident("skip") {
+                        return true;
+                    }
+                    skip_serializing |= path.is_ident("skip_serializing");
+                    skip_deserializing |= path.is_ident("skip_deserializing");
+                }
+            }
+        }
+    }
+    skip_serializing && skip_deserializing
+}
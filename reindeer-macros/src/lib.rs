@@ -3,6 +3,7 @@
 //! To automatically derive Entity on a `struct`, you simply have to derive `Entity` (as Well as `serde`'s `Serialize` and `Deserialize` traits) like so:
 //! 
 //! ```rust
+//! # use reindeer::{Entity, Serialize, Deserialize};
 //! #[derive(Serialize,Deserialize,Entity)]
 //! struct User {
 //!     id : (u32,u32),
@@ -17,6 +18,7 @@
 //! To specify other values, use the helper attribute `entity` like so :
 //! 
 //! ```rust
+//! # use reindeer::{Entity, Serialize, Deserialize};
 //! #[derive(Serialize,Deserialize,Entity)]
 //! #[entity(name = "user", version = 1,id = "email")]
 //! struct User {
@@ -27,13 +29,14 @@
 //! }
 //! ```
 //! 
-//! To specify sibling entities and child entities, use the `sibling` and `child` helper attributes
+//! To specify sibling entities and child entities, use the `siblings` and `children` helper attributes
 //! respectively:
 //! 
 //! ```rust
+//! # use reindeer::{Entity, Serialize, Deserialize};
 //! #[derive(Serialize,Deserialize,Entity)]
 //! #[entity(name = "user", version = 1,id = "email")]
-//! #[sibling(("user_data", Cascade))]
+//! #[siblings(("user_data", Cascade))]
 //! #[children(("doc",Cascade),("shared_doc",BreakLink))]
 //! struct User {
 //!     email : String,
@@ -42,9 +45,9 @@
 //!     password_hash : String,
 //! }
 //! 
-//! //! #[derive(Serialize,Deserialize,Entity)]
+//! #[derive(Serialize,Deserialize,Entity)]
 //! #[entity(name = "user_data", version = 1,id = "email")]
-//! #[sibling(("user", Error))]
+//! #[siblings(("user", Error))]
 //! struct UserData {
 //!     email : String,
 //!     username : String,
@@ -53,7 +56,132 @@
 //! }
 //! ```
 //! 
-//! The second part of each relation is a `reindeer::DeletionBehaviour` enum value : `BreakLink`,`Cascade`, or `Error`.
+//! The second part of each relation is a `reindeer::DeletionBehaviour` enum value : `BreakLink`,`Cascade`, `Error`,
+//! or `SetNull(field_name)` where `field_name` is an `Option` field of the related entity.
+//! 
+//! To migrate existing data when the `version` of an entity increases, point the `migrate_from` argument to a
+//! `fn(old_version : u32, db : &Db) -> reindeer::Result<()>` function, which is called when the entity is registered in
+//! a database where it was registered with an older version, or where its store holds entities saved before versions
+//! were recorded (as version `0`). Deriving an entity with a `version` greater than `1` and no `migrate_from` emits a
+//! warning :
+//! 
+//! ```rust
+//! # use reindeer::{Entity, Serialize, Deserialize};
+//! # fn migrate_user(_old_version : u32, _db : &reindeer::Db) -> reindeer::Result<()> { Ok(()) }
+//! #[derive(Serialize,Deserialize,Entity)]
+//! #[entity(name = "user", version = 2, migrate_from = "migrate_user")]
+//! struct User {
+//!     id : u32,
+//!     email : Option<String>,
+//! }
+//! ```
+//! 
+//! The type of the id field must implement `reindeer::AsBytes`, which is already the case for `String`, integers and tuples
+//! of those. For other key types, such as newtypes, either implement it, or point the `key_as_bytes` argument to a
+//! `fn(&Key) -> Vec<u8>` function to have it implemented :
+//! 
+//! ```rust
+//! # use reindeer::{Entity, Serialize, Deserialize};
+//! # #[derive(Serialize, Deserialize, Clone)]
+//! # struct UserId(u32);
+//! #[derive(Serialize,Deserialize,Entity)]
+//! #[entity(name = "user", key_as_bytes = "user_id_bytes")]
+//! struct User {
+//!     id : UserId,
+//!     email : String,
+//! }
+//! 
+//! fn user_id_bytes(id : &UserId) -> Vec<u8> {
+//!     id.0.to_be_bytes().to_vec()
+//! }
+//! ```
+//! 
+//! To avoid mixing up the keys of different entity types, the `typed_key` argument generates a `<StructName>Id` newtype
+//! wrapping the given type, to be used as the type of the id field (it is serialized as the wrapped type) :
+//! 
+//! ```rust
+//! # use reindeer::{Entity, Serialize, Deserialize};
+//! # let db = reindeer::open_memory()?;
+//! #[derive(Serialize,Deserialize,Entity)]
+//! #[entity(name = "user", typed_key = "u32")]
+//! struct User {
+//!     id : UserId,
+//!     email : String,
+//! }
+//! 
+//! let user = User::get(&UserId(3), &db)?;
+//! # Ok::<(), reindeer::Error>(())
+//! ```
+//! 
+//! Fields that are only meant to live in memory, like computed caches, can be left out of the generated code (they cannot
+//! be the id, unique or set to `None` by relations) with `#[entity_skip]`. They must also be marked with `#[serde(skip)]`
+//! so that they are not persisted, and implement `Default` :
+//! 
+//! ```rust
+//! # use reindeer::{Entity, Serialize, Deserialize};
+//! #[derive(Serialize,Deserialize,Entity)]
+//! struct User {
+//!     id : u32,
+//!     email : String,
+//!     #[serde(skip)]
+//!     #[entity_skip]
+//!     cached_avatar : Option<Vec<u8>>,
+//! }
+//! ```
+//! 
+//! To prevent two entities of a store from having the same value for some fields, list them in the `unique` argument.
+//! `save`, `update` and every other write then fail with a `reindeer::ErrorKind::UniqueConstraintViolation` error instead of
+//! writing a duplicate value :
+//! 
+//! ```rust
+//! # use reindeer::{Entity, Serialize, Deserialize};
+//! #[derive(Serialize,Deserialize,Entity)]
+//! #[entity(name = "user", unique = "email,username")]
+//! struct User {
+//!     id : u32,
+//!     email : String,
+//!     username : String,
+//! }
+//! ```
+//! 
+//! Generic structs are supported, as long as their type parameters can be serialized : the derived implementation
+//! requires them to implement `Serialize` and `DeserializeOwned`. As its instantiations cannot share a store, a generic
+//! entity has no `name` argument : the store name of each instantiation is given by implementing `GenericStoreName` :
+//! 
+//! ```rust
+//! # use reindeer::{Entity, GenericStoreName, Serialize, Deserialize};
+//! #[derive(Serialize,Deserialize,Entity)]
+//! struct Container<T> {
+//!     id : u32,
+//!     value : T,
+//! }
+//! 
+//! impl GenericStoreName for Container<String> {
+//!     const STORE_NAME : &'static str = "string_container";
+//! }
+//! ```
+//! 
+//! A `u64` field can be used as a modification timestamp with the `timestamp_field` argument. It is then set to the
+//! current time, in milliseconds since the Unix epoch, by `Entity::touch` :
+//! 
+//! ```rust
+//! # use reindeer::{Entity, Serialize, Deserialize};
+//! # let db = reindeer::open_memory()?;
+//! #[derive(Serialize,Deserialize,Entity)]
+//! #[entity(name = "document", timestamp_field = "modified_at")]
+//! struct Document {
+//!     id : u32,
+//!     content : String,
+//!     modified_at : u64,
+//! }
+//! 
+//! # Document { id : 3, content : String::new(), modified_at : 0 }.save(&db)?;
+//! Document::touch(&3, &db)?;
+//! # Ok::<(), reindeer::Error>(())
+//! ```
+//! 
+//! ⚠ Two entities deriving `Entity` with the same store name and version would silently share the same `sled` tree :
+//! registering the second one fails with an `ErrorKind::StoreNameConflict` error naming both types.
 //! 
 
 mod entity_data;
@@ -63,7 +191,7 @@ use entity_data::EntityData;
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use syn::{parse_macro_input, DeriveInput, Visibility, spanned::Spanned};
-use quote::quote;
+use quote::{quote, quote_spanned};
 use syn::Ident;
 
 type Errors = Vec<syn::Error>;
@@ -74,6 +202,7 @@ type Errors = Vec<syn::Error>;
 /// To automatically derive Entity on a `struct`, you simply have to derive `Entity` (as Well as `serde`'s `Serialize` and `Deserialize` traits) like so:
 /// 
 /// ```rust
+/// # use reindeer::{Entity, Serialize, Deserialize};
 /// #[derive(Serialize,Deserialize,Entity)]
 /// struct User {
 ///     id : (u32,u32),
@@ -88,6 +217,7 @@ type Errors = Vec<syn::Error>;
 /// To specify other values, use the helper attribute `entity` like so :
 /// 
 /// ```rust
+/// # use reindeer::{Entity, Serialize, Deserialize};
 /// #[derive(Serialize,Deserialize,Entity)]
 /// #[entity(name = "user", version = 1,id = "email")]
 /// struct User {
@@ -98,13 +228,14 @@ type Errors = Vec<syn::Error>;
 /// }
 /// ```
 /// 
-/// To specify sibling entities and child entities, use the `sibling` and `child` helper attributes
+/// To specify sibling entities and child entities, use the `siblings` and `children` helper attributes
 /// respectively:
 /// 
 /// ```rust
+/// # use reindeer::{Entity, Serialize, Deserialize};
 /// #[derive(Serialize,Deserialize,Entity)]
 /// #[entity(name = "user", version = 1,id = "email")]
-/// #[sibling(("user_data", Cascade))]
+/// #[siblings(("user_data", Cascade))]
 /// #[children(("doc",Cascade),("shared_doc",BreakLink))]
 /// struct User {
 ///     email : String,
@@ -115,7 +246,7 @@ type Errors = Vec<syn::Error>;
 /// 
 /// #[derive(Serialize,Deserialize,Entity)]
 /// #[entity(name = "user_data", version = 1,id = "email")]
-/// #[sibling(("user", Error))]
+/// #[siblings(("user", Error))]
 /// struct UserData {
 ///     email : String,
 ///     username : String,
@@ -124,14 +255,160 @@ type Errors = Vec<syn::Error>;
 /// }
 /// ```
 /// 
-/// The second part of each relation is a `reindeer::DeletionBehaviour` enum value : `BreakLink`,`Cascade`, or `Error`.
+/// The second part of each relation is a `reindeer::DeletionBehaviour` enum value : `BreakLink`,`Cascade`, `Error`,
+/// or `SetNull(field_name)` where `field_name` is an `Option` field of the related entity.
+/// 
+/// To migrate existing data when the `version` of an entity increases, point the `migrate_from` argument to a
+/// `fn(old_version : u32, db : &Db) -> reindeer::Result<()>` function, which is called when the entity is registered in
+/// a database where it was registered with an older version, or where its store holds entities saved before versions
+/// were recorded (as version `0`). Deriving an entity with a `version` greater than `1` and no `migrate_from` emits a
+/// warning :
+/// 
+/// ```rust
+/// # use reindeer::{Entity, Serialize, Deserialize};
+/// # fn migrate_user(_old_version : u32, _db : &reindeer::Db) -> reindeer::Result<()> { Ok(()) }
+/// #[derive(Serialize,Deserialize,Entity)]
+/// #[entity(name = "user", version = 2, migrate_from = "migrate_user")]
+/// struct User {
+///     id : u32,
+///     email : Option<String>,
+/// }
+/// ```
+/// 
+/// The type of the id field must implement `reindeer::AsBytes`, which is already the case for `String`, integers and tuples
+/// of those. For other key types, such as newtypes, either implement it, or point the `key_as_bytes` argument to a
+/// `fn(&Key) -> Vec<u8>` function to have it implemented :
+/// 
+/// ```rust
+/// # use reindeer::{Entity, Serialize, Deserialize};
+/// # #[derive(Serialize, Deserialize, Clone)]
+/// # struct UserId(u32);
+/// #[derive(Serialize,Deserialize,Entity)]
+/// #[entity(name = "user", key_as_bytes = "user_id_bytes")]
+/// struct User {
+///     id : UserId,
+///     email : String,
+/// }
+/// 
+/// fn user_id_bytes(id : &UserId) -> Vec<u8> {
+///     id.0.to_be_bytes().to_vec()
+/// }
+/// ```
+/// 
+/// To avoid mixing up the keys of different entity types, the `typed_key` argument generates a `<StructName>Id` newtype
+/// wrapping the given type, to be used as the type of the id field (it is serialized as the wrapped type) :
+/// 
+/// ```rust
+/// # use reindeer::{Entity, Serialize, Deserialize};
+/// # let db = reindeer::open_memory()?;
+/// #[derive(Serialize,Deserialize,Entity)]
+/// #[entity(name = "user", typed_key = "u32")]
+/// struct User {
+///     id : UserId,
+///     email : String,
+/// }
+/// 
+/// let user = User::get(&UserId(3), &db)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+/// 
+/// Fields that are only meant to live in memory, like computed caches, can be left out of the generated code (they cannot
+/// be the id, unique or set to `None` by relations) with `#[entity_skip]`. They must also be marked with `#[serde(skip)]`
+/// so that they are not persisted, and implement `Default` :
+/// 
+/// ```rust
+/// # use reindeer::{Entity, Serialize, Deserialize};
+/// #[derive(Serialize,Deserialize,Entity)]
+/// struct User {
+///     id : u32,
+///     email : String,
+///     #[serde(skip)]
+///     #[entity_skip]
+///     cached_avatar : Option<Vec<u8>>,
+/// }
+/// ```
+/// 
+/// To prevent two entities of a store from having the same value for some fields, list them in the `unique` argument.
+/// `save`, `update` and every other write then fail with a `reindeer::ErrorKind::UniqueConstraintViolation` error instead of
+/// writing a duplicate value :
+/// 
+/// ```rust
+/// # use reindeer::{Entity, Serialize, Deserialize};
+/// #[derive(Serialize,Deserialize,Entity)]
+/// #[entity(name = "user", unique = "email,username")]
+/// struct User {
+///     id : u32,
+///     email : String,
+///     username : String,
+/// }
+/// ```
+/// 
+/// Generic structs are supported, as long as their type parameters can be serialized : the derived implementation
+/// requires them to implement `Serialize` and `DeserializeOwned`. As its instantiations cannot share a store, a generic
+/// entity has no `name` argument : the store name of each instantiation is given by implementing `GenericStoreName` :
+/// 
+/// ```rust
+/// # use reindeer::{Entity, GenericStoreName, Serialize, Deserialize};
+/// #[derive(Serialize,Deserialize,Entity)]
+/// struct Container<T> {
+///     id : u32,
+///     value : T,
+/// }
+/// 
+/// impl GenericStoreName for Container<String> {
+///     const STORE_NAME : &'static str = "string_container";
+/// }
+/// ```
+/// 
+/// A `u64` field can be used as a modification timestamp with the `timestamp_field` argument. It is then set to the
+/// current time, in milliseconds since the Unix epoch, by `Entity::touch` :
+/// 
+/// ```rust
+/// # use reindeer::{Entity, Serialize, Deserialize};
+/// # let db = reindeer::open_memory()?;
+/// #[derive(Serialize,Deserialize,Entity)]
+/// #[entity(name = "document", timestamp_field = "modified_at")]
+/// struct Document {
+///     id : u32,
+///     content : String,
+///     modified_at : u64,
+/// }
+/// 
+/// # Document { id : 3, content : String::new(), modified_at : 0 }.save(&db)?;
+/// Document::touch(&3, &db)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+/// 
+/// ⚠ Two entities deriving `Entity` with the same store name and version would share the same `sled` tree, so the
+/// program does not build, even if they are declared in different modules :
+/// 
+/// ```compile_fail
+/// # use reindeer::{Entity, Serialize, Deserialize};
+/// mod v1 {
+/// #   use super::*;
+///     #[derive(Serialize,Deserialize,Entity)]
+///     #[entity(name = "user")]
+///     pub struct User { pub id : u32 }
+/// }
+/// mod v2 {
+/// #   use super::*;
+///     // error: symbol `reindeer store "user" version 0 is derived by several entities` is already defined
+///     #[derive(Serialize,Deserialize,Entity)]
+///     #[entity(name = "user")]
+///     pub struct User { pub id : u32, pub name : String }
+/// }
+/// # fn main() {}
+/// ```
+/// 
+/// Entities implementing `Entity` manually are checked when registered instead : registering the second one fails with
+/// an `ErrorKind::StoreNameConflict` error naming both types.
 /// 
-#[proc_macro_derive(Entity, attributes(entity,children,siblings))]
+#[proc_macro_derive(Entity, attributes(entity,children,siblings,entity_skip))]
 pub fn derive_entity(item : TokenStream) -> TokenStream {
     let ast = parse_macro_input!(item as DeriveInput);
     let mut errors = Vec::new();
     let mut result = construct_token_stream(&ast, &mut errors);
-    if errors.len() > 0 {
+    if !errors.is_empty() {
         result.extend::<TokenStream>(errors.iter().map(|e| Into::<TokenStream>::into(e.to_compile_error())).collect());
     }
     result
@@ -146,7 +423,7 @@ fn construct_token_stream(input : &DeriveInput, errors : &mut Errors) -> TokenSt
             let attr_copy = entity_data.clone();
             result.extend([
                 generate_alias(&input.ident, entity_data.version.unwrap_or(0), &input.vis, &input.generics),
-                generate_impl( &input.ident, &attr_copy, &input.generics),
+                generate_impl( &input.ident, &attr_copy, &input.vis, &input.generics),
             ])
         },
         syn::Data::Enum(_) => errors.push(syn::Error::new_spanned(input, "Cannot derive Entity on an enum. Please implement Entity manually.")),
@@ -157,28 +434,55 @@ fn construct_token_stream(input : &DeriveInput, errors : &mut Errors) -> TokenSt
 
 fn generate_alias(name : &Ident,version : u32, vis : &Visibility, generics : &syn::Generics) -> TokenStream {
     let (_, ty_generics, _) = generics.split_for_impl();
-    let versionned_ident = Ident::new(&format!("{}_v{}",name.to_string(),version), Span::call_site());
+    let versionned_ident = Ident::new(&format!("{}_v{}",name,version), Span::call_site());
     quote ! {
         #vis type #versionned_ident #ty_generics = #name #ty_generics;
     }.into()
 }
 
-fn generate_impl(struct_name : &Ident,entity_data : &EntityData, generics : &syn::Generics) -> TokenStream {
-
-    if let (Some(store_name),Some(id_field),Some(key_type),crate_name) = (&entity_data.name,&entity_data.id,&entity_data.id_type,&entity_data.crate_name) {
-        let crate_name = Ident::new(crate_name,Span::call_site());
-        let children : Vec<proc_macro2::TokenStream> = entity_data.children.0.iter().map(|e| {
-            let (name,deletion) = (e.0.clone(),e.1.clone());
-            quote!{(#name,#crate_name::DeletionBehaviour::#deletion)}
-        }).collect();
-        let siblings: Vec<proc_macro2::TokenStream> = entity_data.siblings.0.iter().map(|e| {
-            let (name,deletion) = (e.0.clone(),e.1.clone());
-            quote!{(#name,#crate_name::DeletionBehaviour::#deletion)}
-        }).collect();
+fn generate_impl(struct_name : &Ident,entity_data : &EntityData, vis : &Visibility, generics : &syn::Generics) -> TokenStream {
+
+    let is_generic = generics.params.iter().any(|param| !matches!(param, syn::GenericParam::Lifetime(_)));
+    let crate_ident = Ident::new(&entity_data.crate_name,Span::call_site());
+    let store_name = match (&entity_data.name, is_generic) {
+        (Some(_), true) => Some(syn::Error::new(struct_name.span(), GENERIC_STORE_NAME_ERROR).to_compile_error()),
+        (Some(store_name), false) => Some(quote!{#store_name}),
+        (None, true) => Some(quote!{<Self as #crate_ident::GenericStoreName>::STORE_NAME}),
+        (None, false) => None,
+    };
+    if let (Some(store_name),Some(id_field),Some(key_type)) = (store_name,&entity_data.id,&entity_data.id_type) {
+        let crate_name = crate_ident;
+        let children : Vec<proc_macro2::TokenStream> = entity_data.children.0.iter().map(|e| generate_relation(e, &crate_name)).collect();
+        let siblings: Vec<proc_macro2::TokenStream> = entity_data.siblings.0.iter().map(|e| generate_relation(e, &crate_name)).collect();
+        let set_field_to_none = generate_set_field_to_none(&entity_data.fields, &crate_name);
+        let version = entity_data.version.unwrap_or(0);
+        let migrate_from = generate_migrate_from(&entity_data.migrate_from, &crate_name);
+        let missing_migration_warning = generate_missing_migration_warning(struct_name, version, &entity_data.migrate_from);
+        let key_as_bytes = generate_key_as_bytes(&entity_data.key_as_bytes, key_type, &crate_name);
+        let unique = generate_unique(&entity_data.unique, &crate_name);
+        let timestamp_field = generate_timestamp_field(&entity_data.timestamp_field, &crate_name);
+        let typed_key = match &entity_data.typed_key {
+            Some(wrapped_type) => {
+                let key_ident = Ident::new(&format!("{}Id", struct_name), struct_name.span());
+                if key_ident == quote!{#key_type}.to_string() {
+                    generate_typed_key(struct_name, &key_ident, wrapped_type, vis, &crate_name)
+                }
+                else {
+                    syn::Error::new_spanned(key_type, format!("With typed_key, the id field must be declared with the generated `{}` type.", key_ident)).to_compile_error()
+                }
+            },
+            None => proc_macro2::TokenStream::new(),
+        };
+        let mut generics = add_serde_bounds(generics, &crate_name);
+        if is_generic {
+            generics.make_where_clause().predicates.push(syn::parse_quote!(Self : #crate_name::GenericStoreName));
+        }
+        let store_name_marker = generate_store_name_marker(&entity_data.name, version);
         let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
         quote!{
             impl #impl_generics #crate_name::Entity for #struct_name #ty_generics #where_clause {
                 type Key = #key_type;
+                const SCHEMA_VERSION : u32 = #version;
                 fn store_name() -> &'static str {
                     #store_name
                 }
@@ -189,15 +493,190 @@ fn generate_impl(struct_name : &Ident,entity_data : &EntityData, generics : &syn
                     self.#id_field = key.clone();
                 }
                 fn get_child_stores() -> Vec<(&'static str, #crate_name::DeletionBehaviour)> {
-                    vec![#(#children)*]
+                    vec![#(#children),*]
                 }
                 fn get_sibling_stores() -> Vec<(&'static str, #crate_name::DeletionBehaviour)> {
-                    vec![#(#siblings)*]
+                    vec![#(#siblings),*]
                 }
+                #set_field_to_none
+                #migrate_from
+                #unique
+                #timestamp_field
             }
+
+            #missing_migration_warning
+            #store_name_marker
+            #key_as_bytes
+            #typed_key
         }.into()
     }
     else {
         TokenStream::new()
     }
-}
\ No newline at end of file
+}
+
+/// Exports a symbol named after the store name and version, so that two entities sharing them cannot be linked together.
+/// Generic entities get their store name from `GenericStoreName`, and are only checked when registered.
+fn generate_store_name_marker(store_name : &Option<String>, version : u32) -> proc_macro2::TokenStream {
+    match store_name {
+        Some(store_name) => {
+            let symbol = format!("reindeer store {:?} version {} is derived by several entities", store_name, version);
+            quote!{
+                const _ : () = {
+                    #[doc(hidden)]
+                    #[export_name = #symbol]
+                    static STORE_NAME_MARKER : u8 = 0;
+                };
+            }
+        },
+        None => proc_macro2::TokenStream::new(),
+    }
+}
+
+const GENERIC_STORE_NAME_ERROR : &str = "The instantiations of a generic entity cannot share a store name. Remove the 'name' argument and implement `GenericStoreName` for each instantiation instead.";
+
+/// Type parameters must be serializable for the struct to be.
+fn add_serde_bounds(generics : &syn::Generics, crate_name : &Ident) -> syn::Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!(#crate_name::__serde::Serialize));
+        param.bounds.push(syn::parse_quote!(#crate_name::__serde::de::DeserializeOwned));
+    }
+    generics
+}
+
+fn generate_migrate_from(migrate_from : &Option<syn::Path>, crate_name : &Ident) -> proc_macro2::TokenStream {
+    match migrate_from {
+        Some(path) => quote!{
+            fn migrate_from(old_version : u32, db : &#crate_name::Db) -> #crate_name::Result<()> {
+                #path(old_version, db)
+            }
+        },
+        None => proc_macro2::TokenStream::new(),
+    }
+}
+
+/// Stable proc macros cannot emit warnings : using a deprecated item makes the compiler emit one on their behalf.
+/// The usage is spanned on the struct name, as warnings are not reported for code spanned in the macro itself.
+fn generate_missing_migration_warning(struct_name : &Ident, version : u32, migrate_from : &Option<syn::Path>) -> proc_macro2::TokenStream {
+    if version <= 1 || migrate_from.is_some() {
+        return proc_macro2::TokenStream::new();
+    }
+    quote_spanned!{struct_name.span()=>
+        const _ : () = {
+            #[deprecated(note = "`version` is greater than 1 but no `migrate_from` function is given : entities saved with an older version will not be migrated")]
+            struct VersionWithoutMigrateFrom;
+            let _ = VersionWithoutMigrateFrom;
+        };
+    }
+}
+
+fn generate_unique(unique : &[Ident], crate_name : &Ident) -> proc_macro2::TokenStream {
+    if unique.is_empty() {
+        return proc_macro2::TokenStream::new();
+    }
+    let field_names : Vec<String> = unique.iter().map(|ident| ident.to_string()).collect();
+    quote!{
+        fn get_unique_fields() -> Vec<&'static str> {
+            vec![#(#field_names),*]
+        }
+        fn get_unique_values(&self) -> #crate_name::Result<Vec<(&'static str, Vec<u8>)>> {
+            Ok(vec![#((#field_names, #crate_name::__unique_value(&self.#unique)?)),*])
+        }
+    }
+}
+
+fn generate_timestamp_field(timestamp_field : &Option<Ident>, crate_name : &Ident) -> proc_macro2::TokenStream {
+    match timestamp_field {
+        Some(field) => quote!{
+            fn set_updated_at(&mut self, ts : u64) -> #crate_name::Result<()> {
+                self.#field = ts;
+                Ok(())
+            }
+        },
+        None => proc_macro2::TokenStream::new(),
+    }
+}
+
+fn generate_typed_key(struct_name : &Ident, key_ident : &Ident, key_type : &syn::Type, vis : &Visibility, crate_name : &Ident) -> proc_macro2::TokenStream {
+    let doc = format!("Typed key of [`{}`], generated by `#[entity(typed_key = \"...\")]`.", struct_name);
+    let serde_crate = format!("{}::__serde", crate_name);
+    quote!{
+        #[doc = #doc]
+        #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, #crate_name::Serialize, #crate_name::Deserialize)]
+        #[serde(crate = #serde_crate, transparent)]
+        #vis struct #key_ident(pub #key_type);
+
+        impl #crate_name::AsBytes for #key_ident {
+            fn as_bytes(&self) -> Vec<u8> {
+                #crate_name::AsBytes::as_bytes(&self.0)
+            }
+        }
+
+        impl #crate_name::FromBytes for #key_ident where #key_type : #crate_name::FromBytes {
+            fn from_bytes(bytes : &[u8]) -> #crate_name::Result<Self> {
+                Ok(#key_ident(<#key_type as #crate_name::FromBytes>::from_bytes(bytes)?))
+            }
+            fn fixed_size() -> Option<usize> {
+                <#key_type as #crate_name::FromBytes>::fixed_size()
+            }
+        }
+
+        impl From<#key_type> for #key_ident {
+            fn from(key : #key_type) -> Self {
+                #key_ident(key)
+            }
+        }
+    }
+}
+
+fn generate_key_as_bytes(key_as_bytes : &Option<syn::Path>, key_type : &syn::Type, crate_name : &Ident) -> proc_macro2::TokenStream {
+    match key_as_bytes {
+        Some(path) => quote!{
+            impl #crate_name::AsBytes for #key_type {
+                fn as_bytes(&self) -> Vec<u8> {
+                    #path(self)
+                }
+            }
+        },
+        None => proc_macro2::TokenStream::new(),
+    }
+}
+
+fn generate_relation(relation : &relations::Relation, crate_name : &Ident) -> proc_macro2::TokenStream {
+    let (name,deletion) = (&relation.0,&relation.1);
+    match &relation.2 {
+        Some(field) => {
+            let field = field.to_string();
+            quote!{(#name,#crate_name::DeletionBehaviour::#deletion(String::from(#field)))}
+        },
+        None => quote!{(#name,#crate_name::DeletionBehaviour::#deletion)},
+    }
+}
+
+fn generate_set_field_to_none(fields : &[(syn::Visibility,syn::Ident,syn::Type)], crate_name : &Ident) -> proc_macro2::TokenStream {
+    let optional_fields : Vec<&Ident> = fields.iter().filter(|(_,_,ty)| is_option(ty)).map(|(_,ident,_)| ident).collect();
+    if optional_fields.is_empty() {
+        return proc_macro2::TokenStream::new();
+    }
+    let field_names : Vec<String> = optional_fields.iter().map(|ident| ident.to_string()).collect();
+    quote!{
+        fn set_field_to_none(&mut self, field : &str) -> #crate_name::Result<()> {
+            match field {
+                #(#field_names => self.#optional_fields = None,)*
+                _ => return Err(#crate_name::Error::new(
+                    #crate_name::ErrorKind::IntegrityError,
+                    format!("Field {} cannot be set to None in entity {}", field, <Self as #crate_name::Entity>::store_name()),
+                )),
+            }
+            Ok(())
+        }
+    }
+}
+
+fn is_option(ty : &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => path.qself.is_none() && path.path.segments.last().map(|s| s.ident == "Option").unwrap_or(false),
+        _ => false,
+    }
+}
@@ -1,33 +1,34 @@
-use proc_macro2::{TokenTree, Ident};
-use quote::ToTokens;
+use proc_macro2::Ident;
 use syn::{parse::Parse, parenthesized, punctuated::Punctuated, Token, LitStr};
 
+const RELATION_SYNTAX_ERROR : &str = r#"A relation must respect the syntax ("store_name",Cascade)"#;
+const CUSTOM_BEHAVIOUR_ERROR : &str = r#"Custom deletion behaviours cannot be declared in attributes. Set them in the FamilyDescriptor of the entity instead"#;
+const BEHAVIOUR_ERROR : &str = r#"The second part of the relation must be either Cascade, BreakLink, Error or SetNull(field_name)"#;
+
 #[derive(Clone)]
-pub struct Relation(pub syn::LitStr,pub syn::Ident);
+pub struct Relation(pub syn::LitStr,pub syn::Ident,pub Option<syn::Ident>);
 
 impl Parse for Relation {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
         let content;
         parenthesized!(content in input);
-        let result = Punctuated::<TokenTree,Token![,]>::parse_separated_nonempty(&content)?;
-        if result.len() != 2 {
-            return Err(syn::Error::new(input.span(), format!(r#"A relation must respect the syntax ("store_name",Cascade) {}"#,result.len())))
-        }
-        let res1 = result[0].clone().into_token_stream().into();
-        let res2 = result[1].clone().into_token_stream().into();
-        match (syn::parse::<LitStr>(res1),syn::parse::<Ident>(res2)) {
-            (Ok(name),Ok(deletion)) => {
-                match &*deletion.to_string() {
-                    "Cascade" | "BreakLink" | "Error" => Ok(Self(name,deletion)),
-                    _ => Err(syn::Error::new_spanned(deletion, r#"The second part of the relation must be either Cascade, BreakLink or Error"#)),
-                }
+        let name = content.parse::<LitStr>().map_err(|e| syn::Error::new(e.span(), r#"The first part of the relation must be the store name as a string."#))?;
+        content.parse::<Token![,]>().map_err(|e| syn::Error::new(e.span(), RELATION_SYNTAX_ERROR))?;
+        let deletion = content.parse::<Ident>().map_err(|e| syn::Error::new(e.span(), BEHAVIOUR_ERROR))?;
+        let field = match &*deletion.to_string() {
+            "Cascade" | "BreakLink" | "Error" => None,
+            "SetNull" => {
+                let field_content;
+                parenthesized!(field_content in content);
+                Some(field_content.parse::<Ident>().map_err(|e| syn::Error::new(e.span(), r#"SetNull must be given the name of an optional field : SetNull(field_name)"#))?)
             },
-            (Err(e),Ok(_)) => Err(syn::Error::new(e.span(), r#"The first part of the relation must be the store name as a string."#)),
-            (Ok(_),Err(e)) => Err(syn::Error::new(e.span(), r#"The second part of the relation must be either Cascade, BreakLink or Error"#)),
-            _ => {
-                Err(syn::Error::new(input.span(), r#"A relation must respect the syntax ("store_name",Cascade)"#))
-            }
+            "Custom" => return Err(syn::Error::new_spanned(deletion, CUSTOM_BEHAVIOUR_ERROR)),
+            _ => return Err(syn::Error::new_spanned(deletion, BEHAVIOUR_ERROR)),
+        };
+        if !content.is_empty() {
+            return Err(syn::Error::new(content.span(), RELATION_SYNTAX_ERROR));
         }
+        Ok(Self(name,deletion,field))
     }
 }
 
@@ -0,0 +1,205 @@
+// Entities shared by the documentation examples of `reindeer`.
+//
+// Examples include this file in a hidden module and open a registered in-memory database with `doctest_db` :
+//
+// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+// # let db = doctest_db()?;
+
+pub use reindeer::{AsBytes, AutoIncrementEntity, DeletionBehaviour, Entity, Error, ErrorKind};
+use reindeer::{Db, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "my_struct", id = "key")]
+pub struct MyStruct {
+    pub key: u32,
+    pub prop1: String,
+    pub prop2: u32,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "my_struct_1", id = "key")]
+#[siblings(("my_struct_2", BreakLink))]
+#[children(("child_struct", Cascade))]
+pub struct MyStruct1 {
+    pub key: u32,
+    pub prop9: u32,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "my_struct_2", id = "key")]
+#[siblings(("my_struct_1", BreakLink))]
+pub struct MyStruct2 {
+    pub key: u32,
+    pub prop9: u32,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "child_struct", id = "key")]
+pub struct ChildStruct {
+    pub key: (u32, u32),
+    pub prop9: u32,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "user")]
+#[siblings(("profile", Cascade))]
+pub struct User {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "profile")]
+#[siblings(("user", BreakLink))]
+pub struct Profile {
+    pub id: u32,
+    pub bio: String,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "author")]
+pub struct Author {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "book")]
+pub struct Book {
+    pub id: u32,
+    pub title: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Rating {
+    pub stars: u8,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "post")]
+pub struct Post {
+    pub id: u32,
+    pub created_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "tag")]
+pub struct Tag {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "order")]
+#[children(("order_line", Cascade))]
+pub struct Order {
+    pub id: u32,
+    pub quantity: u32,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "order_line")]
+pub struct OrderLine {
+    pub id: (u32, u32),
+    pub product: String,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "archived_order_line")]
+pub struct ArchivedOrderLine {
+    pub id: (u32, u32),
+    pub product: String,
+}
+
+impl From<OrderLine> for ArchivedOrderLine {
+    fn from(line: OrderLine) -> Self {
+        ArchivedOrderLine { id: line.id, product: line.product }
+    }
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "invoice")]
+pub struct Invoice {
+    pub id: u32,
+    pub amount: u64,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "customer")]
+pub struct Customer {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "school")]
+#[children(("student", Cascade))]
+pub struct School {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "student")]
+pub struct Student {
+    pub id: (u32, u32),
+    pub name: String,
+    pub age: u32,
+    pub adult: bool,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "club")]
+pub struct Club {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "account")]
+pub struct Account {
+    pub id: u32,
+    pub balance: i64,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "transfer")]
+pub struct Transfer {
+    pub id: u32,
+    pub from: u32,
+    pub amount: i64,
+}
+
+#[derive(Serialize, Deserialize, Entity, Clone, Debug)]
+#[entity(name = "session")]
+pub struct Session {
+    pub id: String,
+    pub user_id: u32,
+}
+
+/// Opens an in-memory database in which every entity of this file is registered.
+pub fn doctest_db() -> reindeer::Result<Db> {
+    let db = reindeer::open_memory()?;
+    MyStruct::register(&db)?;
+    MyStruct1::register(&db)?;
+    MyStruct2::register(&db)?;
+    ChildStruct::register(&db)?;
+    User::register(&db)?;
+    Profile::register(&db)?;
+    Author::register(&db)?;
+    Book::register(&db)?;
+    Post::register(&db)?;
+    Tag::register(&db)?;
+    Order::register(&db)?;
+    OrderLine::register(&db)?;
+    ArchivedOrderLine::register(&db)?;
+    Invoice::register(&db)?;
+    Customer::register(&db)?;
+    School::register(&db)?;
+    Student::register(&db)?;
+    Club::register(&db)?;
+    Account::register(&db)?;
+    Transfer::register(&db)?;
+    Session::register(&db)?;
+    Ok(db)
+}
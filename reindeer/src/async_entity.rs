@@ -0,0 +1,100 @@
+//! # Async Module
+//! This module provides [`AsyncEntity`](trait.AsyncEntity.html), to use entities from an async runtime without blocking it.
+//!
+//! Only available with the `async` feature.
+
+use std::future::Future;
+
+use sled::Db;
+
+use crate::error::Result;
+use crate::Entity;
+
+async fn spawn_blocking<R, F>(f: F) -> Result<R>
+where
+    R: Send + 'static,
+    F: FnOnce() -> Result<R> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await?
+}
+
+/// Async counterparts of the main [`Entity`](trait.Entity.html) methods, implemented for every entity.
+///
+/// Each method runs its synchronous counterpart on Tokio's blocking thread pool with `spawn_blocking`,
+/// so that the async runtime is not blocked by database accesses. It must therefore be called from within a Tokio runtime.
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # let my_struct = MyStruct { key : 3, prop1 : String::from("Hello"), prop2 : 554 };
+/// use reindeer::AsyncEntity;
+///
+/// # tokio::runtime::Builder::new_current_thread().build()?.block_on(async {
+/// my_struct.save_async(&db).await?;
+/// let my_struct = MyStruct::get_async(&3, &db).await?;
+/// # Ok::<(), reindeer::Error>(())
+/// # })?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub trait AsyncEntity: Entity + Clone + Send + 'static
+where
+    Self::Key: Send + 'static,
+{
+    /// See [`Entity::get`](trait.Entity.html#method.get).
+    fn get_async(key: &Self::Key, db: &Db) -> impl Future<Output = Result<Option<Self>>> + Send {
+        let (key, db) = (key.clone(), db.clone());
+        spawn_blocking(move || Self::get(&key, &db))
+    }
+
+    /// See [`Entity::get_all`](trait.Entity.html#method.get_all).
+    fn get_all_async(db: &Db) -> impl Future<Output = Result<Vec<Self>>> + Send {
+        let db = db.clone();
+        spawn_blocking(move || Self::get_all(&db))
+    }
+
+    /// See [`Entity::get_with_filter`](trait.Entity.html#method.get_with_filter).
+    fn get_with_filter_async<F: Fn(&Self) -> bool + Send + 'static>(
+        f: F,
+        db: &Db,
+    ) -> impl Future<Output = Result<Vec<Self>>> + Send {
+        let db = db.clone();
+        spawn_blocking(move || Self::get_with_filter(f, &db))
+    }
+
+    /// See [`Entity::get_count`](trait.Entity.html#method.get_count).
+    fn get_count_async(db: &Db) -> impl Future<Output = Result<usize>> + Send {
+        let db = db.clone();
+        spawn_blocking(move || Self::get_count(&db))
+    }
+
+    /// See [`Entity::exists`](trait.Entity.html#method.exists).
+    fn exists_async(key: &Self::Key, db: &Db) -> impl Future<Output = Result<bool>> + Send {
+        let (key, db) = (key.clone(), db.clone());
+        spawn_blocking(move || Self::exists(&key, &db))
+    }
+
+    /// See [`Entity::save`](trait.Entity.html#method.save).
+    fn save_async(&self, db: &Db) -> impl Future<Output = Result<()>> + Send {
+        let (entity, db) = (self.clone(), db.clone());
+        spawn_blocking(move || entity.save(&db))
+    }
+
+    /// See [`Entity::update`](trait.Entity.html#method.update).
+    fn update_async<F: Fn(&mut Self) + Send + 'static>(
+        key: &Self::Key,
+        f: F,
+        db: &Db,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let (key, db) = (key.clone(), db.clone());
+        spawn_blocking(move || Self::update(&key, f, &db))
+    }
+
+    /// See [`Entity::remove`](trait.Entity.html#method.remove).
+    fn remove_async(key: &Self::Key, db: &Db) -> impl Future<Output = Result<()>> + Send {
+        let (key, db) = (key.clone(), db.clone());
+        spawn_blocking(move || Self::remove(&key, &db))
+    }
+}
+
+impl<T: Entity + Clone + Send + 'static> AsyncEntity for T where T::Key: Send + 'static {}
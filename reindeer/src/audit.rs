@@ -0,0 +1,78 @@
+//! # Audit Module
+//! This module provides the append-only audit trail written by
+//! [`Entity::save_with_audit`](trait.Entity.html#method.save_with_audit) and
+//! [`Entity::remove_with_audit`](trait.Entity.html#method.remove_with_audit).
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_derive::{Deserialize, Serialize};
+use sled::{Db, Tree};
+
+use crate::error::Result;
+
+pub(crate) const AUDIT_TREE_PREFIX: &str = "__$audit_";
+
+/// Kind of mutation recorded in an [`AuditEntry`](struct.AuditEntry.html).
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AuditOperation {
+    /// The entity was saved
+    Save,
+    /// The entity was removed
+    Remove,
+}
+
+/// A mutation of an entity, as returned by [`Entity::get_audit_log`](trait.Entity.html#method.get_audit_log).
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct AuditEntry {
+    /// Store name of the entity
+    pub entity_type: String,
+    /// Key of the entity, as bytes
+    pub key_bytes: Vec<u8>,
+    /// Who made the mutation
+    pub actor: String,
+    /// When the mutation was made, in milliseconds since the Unix epoch
+    pub timestamp: u64,
+    /// What the mutation was
+    pub operation: AuditOperation,
+}
+
+pub struct AuditLog;
+
+impl AuditLog {
+    fn get_tree(store: &str, db: &Db) -> Result<Tree> {
+        Ok(db.open_tree(format!("{}{}", AUDIT_TREE_PREFIX, store))?)
+    }
+
+    pub fn append(
+        store: &str,
+        key: &[u8],
+        actor: &str,
+        operation: AuditOperation,
+        db: &Db,
+    ) -> Result<()> {
+        let entry = AuditEntry {
+            entity_type: String::from(store),
+            key_bytes: key.to_vec(),
+            actor: String::from(actor),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            operation,
+        };
+        let entry_key = [key, &db.generate_id()?.to_be_bytes()].concat();
+        AuditLog::get_tree(store, db)?.insert(entry_key, bincode::serialize(&entry)?)?;
+        Ok(())
+    }
+
+    pub fn get(store: &str, key: &[u8], db: &Db) -> Result<Vec<AuditEntry>> {
+        let mut entries = Vec::new();
+        for elem in AuditLog::get_tree(store, db)?.scan_prefix(key) {
+            let entry = bincode::deserialize::<AuditEntry>(&elem?.1)?;
+            if entry.key_bytes == key {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+}
@@ -0,0 +1,432 @@
+//! # Database Module
+//! This module provides utilities working on the whole database rather than on a single entity store.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::error::Result;
+use crate::import_export::FullDatabaseDump;
+use crate::relation::{DeletionBehaviour, FamilyDescriptor, IntegrityIssue, Relation};
+use crate::{Error, ErrorKind};
+use sled::Db;
+
+const RELATIONS_FILE: &str = "relations";
+/// Number of times the lock of a database that was just closed is retried, 25ms apart
+const LOCK_ATTEMPTS: u32 = 20;
+
+/// Opens a temporary, in-memory `sled` database, that is deleted as soon as it is dropped.
+/// Entities still need to be registered in it as usual.
+///
+/// This is mostly useful for tests.
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// let db = reindeer::open_memory()?;
+/// MyStruct::register(&db)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn open_memory() -> Result<Db> {
+    open_with_config(sled::Config::new().temporary(true))
+}
+
+/// Opens a `sled` database to store Entities with the provided configuration, to tune its cache capacity,
+/// flush interval, mode, etc.
+///
+/// ### Exemple
+/// ```rust,no_run
+/// # use reindeer::Config;
+/// let db = reindeer::open_with_config(
+///     Config::new()
+///         .path("./my-db")
+///         .cache_capacity(64 * 1024 * 1024)
+///         .flush_every_ms(Some(500)),
+/// )?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn open_with_config(config: sled::Config) -> Result<Db> {
+    Ok(config.open()?)
+}
+
+/// Opens the database configured in `config`, retrying for a moment if its lock is still held : `sled` only releases
+/// the lock on its files once its background threads are done with them, which can be a bit after the last handle of a
+/// database is dropped.
+pub(crate) fn open_after_close(config: &sled::Config) -> Result<Db> {
+    let mut attempts = 0;
+    loop {
+        match config.open() {
+            Err(sled::Error::Io(e))
+                if attempts < LOCK_ATTEMPTS && e.to_string().contains("could not acquire lock") =>
+            {
+                attempts += 1;
+                std::thread::sleep(std::time::Duration::from_millis(25));
+            }
+            result => return Ok(result?),
+        }
+    }
+}
+
+/// Lists all the free relations involving the entity with key `key` in `store_name`, whatever the type of the related
+/// entities.
+///
+/// Relations are returned as `(source_store, target_store, target_key)` tuples : both the relations going from this
+/// entity and the ones pointing to it are returned. Entities of other stores sharing the same key are left out.
+///
+/// This is mostly a debugging and migration utility.
+///
+/// ⚠ This will iterate over every relation descriptor of the database.
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// let relations = reindeer::list_all_relations_for_key("user", &9u32.as_bytes(), &db)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn list_all_relations_for_key(
+    store_name: &str,
+    key: &[u8],
+    db: &Db,
+) -> Result<Vec<(String, String, Vec<u8>)>> {
+    Relation::list_all_for_entity_key(store_name, key, db)
+}
+
+/// Removes all the free relations of the entity with key `key` in `store_name`, whatever the type of the related
+/// entities, and returns the number of broken links.
+///
+/// This behaves as if every relation had a [`DeletionBehaviour::BreakLink`](enum.DeletionBehaviour.html#variant.BreakLink)
+/// behaviour : related entities are left untouched, and relation payloads are removed.
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// let count = reindeer::remove_all_relations_for_key("user", &9u32.as_bytes(), &db)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn remove_all_relations_for_key(store_name: &str, key: &[u8], db: &Db) -> Result<usize> {
+    Relation::remove_all_for_key(store_name, key, db)
+}
+
+/// Saves the family descriptors of several entity types at once, for instance before any other operation, so that
+/// integrity checks on deletion take all of them into account.
+///
+/// ⚠ This only registers the relations between stores : [`Entity::register`](trait.Entity.html#method.register)
+/// must still be called on entities with `SetNull` relations, soft-deletion, TTLs, unique fields or a schema version.
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// reindeer::register_all(&db, &[User::family_descriptor(), Book::family_descriptor()])?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn register_all(db: &Db, descriptors: &[FamilyDescriptor]) -> Result<()> {
+    for descriptor in descriptors {
+        descriptor.save(db)?;
+        Relation::register_custom(descriptor, db)?;
+    }
+    Ok(())
+}
+
+/// Returns the store names of all the entities registered in the database
+/// with [`Entity::register`](entity/trait.Entity.html#method.register).
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// for store in reindeer::get_all_registered_stores(&db)? {
+///     println!("{}", store);
+/// }
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn get_all_registered_stores(db: &Db) -> Result<Vec<String>> {
+    Ok(FamilyDescriptor::list_all(db)?
+        .into_iter()
+        .map(|descriptor| descriptor.tree_name)
+        .collect())
+}
+
+/// Checks the integrity of the whole database, and returns the list of issues found :
+///  - sibling or child stores that are declared but not registered
+///  - registered entities whose store does not exist
+///  - relation descriptors of entities that do not exist anymore
+///  - free relations pointing to entities that do not exist anymore
+///
+/// ⚠ This will iterate over every relation descriptor of the database.
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// for issue in reindeer::validate_all(&db)? {
+///     println!("{:?}", issue);
+/// }
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn validate_all(db: &Db) -> Result<Vec<IntegrityIssue>> {
+    FamilyDescriptor::validate_all(db)
+}
+
+/// Exports the schema of the database as a [DOT](https://graphviz.org/doc/info/lang.html) directed graph, that can be
+/// rendered with Graphviz.
+///
+/// Nodes are the registered entity stores, and edges are their sibling (dashed) and child (solid) relationships,
+/// labeled with their deletion behaviour (`cascade`, `error`, `break_link` or `set_null`).
+///
+/// ### Exemple
+/// ```rust,no_run
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// std::fs::write("schema.dot", reindeer::export_dot(&db)?)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn export_dot(db: &Db) -> Result<String> {
+    build_dot(false, db)
+}
+
+/// Same as [`export_dot`](fn.export_dot.html), but each node is also labeled with the number of entities in the store.
+/// Stores that do not exist in the database are labeled as `missing`, and are not created.
+pub fn export_dot_with_data(db: &Db) -> Result<String> {
+    build_dot(true, db)
+}
+
+fn build_dot(with_data: bool, db: &Db) -> Result<String> {
+    let mut nodes = String::new();
+    let mut edges = String::new();
+    let tree_names = db.tree_names();
+    for descriptor in FamilyDescriptor::list_all(db)? {
+        let store = escape_dot(&descriptor.tree_name);
+        if with_data {
+            let exists = tree_names
+                .iter()
+                .any(|name| name == descriptor.tree_name.as_bytes());
+            let count = if exists {
+                db.open_tree(&descriptor.tree_name)?.len().to_string()
+            } else {
+                String::from("missing")
+            };
+            nodes.push_str(&format!(
+                "    \"{0}\" [label=\"{0} ({1})\"];\n",
+                store, count
+            ));
+        } else {
+            nodes.push_str(&format!("    \"{}\";\n", store));
+        }
+        for (other, behaviour) in &descriptor.sibling_trees {
+            edges.push_str(&format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\", style=dashed];\n",
+                store,
+                escape_dot(other),
+                behaviour_label(behaviour)
+            ));
+        }
+        for (other, behaviour) in &descriptor.child_trees {
+            edges.push_str(&format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                store,
+                escape_dot(other),
+                behaviour_label(behaviour)
+            ));
+        }
+    }
+    Ok(format!("digraph reindeer {{\n{}{}}}\n", nodes, edges))
+}
+
+fn behaviour_label(behaviour: &DeletionBehaviour) -> String {
+    match behaviour {
+        DeletionBehaviour::Error => String::from("error"),
+        DeletionBehaviour::BreakLink => String::from("break_link"),
+        DeletionBehaviour::Cascade => String::from("cascade"),
+        DeletionBehaviour::SetNull(field) => format!("set_null({})", escape_dot(field)),
+        DeletionBehaviour::Custom(_) => String::from("custom"),
+    }
+}
+
+fn escape_dot(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Exports every registered entity store and all relation data of the database into the directory at `path`
+/// (which is created if needed) : one `<store_name>.json` file per store, and a `relations.json` file.
+///
+/// Entities are exported as raw serialized data, so that this does not require to know entity types.
+/// This can be used for saving purposes, alongside [`import_all`](fn.import_all.html).
+///
+/// Store names are checked before any file is written : this fails if one of them cannot be used as a file name
+/// (empty, `.`, `..`, or containing a path separator), or is `relations`.
+///
+/// ### Exemple
+/// ```rust,no_run
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # use std::path::Path;
+/// reindeer::export_all(Path::new("./backup"),&db)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn export_all(path: &Path, db: &Db) -> Result<()> {
+    let dump = FullDatabaseDump::capture(db)?;
+    for store in dump.stores.keys() {
+        check_file_name(store)?;
+    }
+    std::fs::create_dir_all(path)?;
+    for (store, entries) in &dump.stores {
+        let f = File::create(path.join(format!("{}.json", store)))?;
+        serde_json::to_writer(f, entries)?;
+    }
+    let f = File::create(path.join(format!("{}.json", RELATIONS_FILE)))?;
+    serde_json::to_writer(f, &dump.relations)?;
+    Ok(())
+}
+
+fn check_file_name(store: &str) -> Result<()> {
+    if store == RELATIONS_FILE {
+        return Err(Error::new(
+            ErrorKind::IOError,
+            format!("Store name {} is reserved for relation data", store),
+        ));
+    }
+    if store.is_empty()
+        || store == "."
+        || store == ".."
+        || store.contains(['/', '\\', '\0'])
+    {
+        return Err(Error::new(
+            ErrorKind::IOError,
+            format!("Store name {:?} cannot be used as a file name", store),
+        ));
+    }
+    Ok(())
+}
+
+/// Imports a whole database exported with [`export_all`](fn.export_all.html) from the directory at `path`.
+/// Any existing entities with matching keys will be overridden.
+///
+/// All files are read before anything is written, and everything is written in a single transaction :
+/// if anything fails, the database is left untouched.
+///
+/// ### Exemple
+/// ```rust,no_run
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # use std::path::Path;
+/// reindeer::import_all(Path::new("./backup"),&db)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn import_all(path: &Path, db: &Db) -> Result<()> {
+    let mut dump = FullDatabaseDump::default();
+    for entry in std::fs::read_dir(path)? {
+        let file_path = entry?.path();
+        if file_path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let name = match file_path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) => String::from(name),
+            None => continue,
+        };
+        let f = File::open(&file_path)?;
+        if name == RELATIONS_FILE {
+            dump.relations = serde_json::from_reader(f)?;
+        } else {
+            dump.stores.insert(name, serde_json::from_reader(f)?);
+        }
+    }
+    dump.restore(db)
+}
+
+/// Writes a backup of the whole database (entity stores, relations, family descriptors... every `sled` tree)
+/// to a new file at `dest_path`.
+///
+/// ⚠ Trees are read one after the other, and `sled` does not provide a snapshot of the whole database : writes made
+/// while the backup is being written may only be partially included in it. Stop writing to the database during the
+/// backup if it needs to be consistent.
+///
+/// Use [`restore`](fn.restore.html) to create a new database from the backup.
+///
+/// ### Exemple
+/// ```rust,no_run
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # use std::path::Path;
+/// reindeer::backup(&db, Path::new("./my-db.backup"))?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn backup(db: &Db, dest_path: &Path) -> Result<()> {
+    let mut w = BufWriter::new(File::create(dest_path)?);
+    let export = db.export();
+    bincode::serialize_into(&mut w, &(export.len() as u64))?;
+    for (collection_type, name, entries) in export {
+        bincode::serialize_into(&mut w, &(collection_type, name))?;
+        for entry in entries {
+            bincode::serialize_into(&mut w, &Some(entry))?;
+        }
+        bincode::serialize_into(&mut w, &None::<Vec<Vec<u8>>>)?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+/// Creates a new database at `dest_path` from a backup written with [`backup`](fn.backup.html), and returns it.
+/// Entities still need to be registered in it as usual.
+///
+/// If something already exists at `dest_path`, this will result in an error.
+///
+/// The database is first restored next to `dest_path` (in a directory with a `.restoring` suffix), and only moved to
+/// `dest_path` once the whole backup has been read : if the backup is invalid or truncated, nothing is left at
+/// `dest_path`.
+///
+/// ### Exemple
+/// ```rust,no_run
+/// # use std::path::Path;
+/// let db = reindeer::restore(Path::new("./my-db.backup"), Path::new("./my-restored-db"))?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn restore(backup_path: &Path, dest_path: &Path) -> Result<Db> {
+    if dest_path.exists() {
+        return Err(Error::new(
+            ErrorKind::IOError,
+            format!("{} already exists", dest_path.display()),
+        ));
+    }
+    let mut tmp_name = dest_path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".restoring");
+    let tmp_path = dest_path.with_file_name(tmp_name);
+    if tmp_path.exists() {
+        return Err(Error::new(
+            ErrorKind::IOError,
+            format!("{} already exists", tmp_path.display()),
+        ));
+    }
+    let result = restore_into(backup_path, &tmp_path);
+    if let Err(e) = result {
+        let _ = std::fs::remove_dir_all(&tmp_path);
+        return Err(e);
+    }
+    std::fs::rename(&tmp_path, dest_path)?;
+    open_after_close(&sled::Config::new().path(dest_path))
+}
+
+fn restore_into(backup_path: &Path, dest_path: &Path) -> Result<()> {
+    let mut r = BufReader::new(File::open(backup_path)?);
+    let db = sled::open(dest_path)?;
+    let count: u64 = bincode::deserialize_from(&mut r)?;
+    for _ in 0..count {
+        let (_, name): (Vec<u8>, Vec<u8>) = bincode::deserialize_from(&mut r)?;
+        let tree = db.open_tree(name)?;
+        while let Some(mut entry) = bincode::deserialize_from::<_, Option<Vec<Vec<u8>>>>(&mut r)? {
+            if entry.len() != 2 {
+                return Err(Error::new(
+                    ErrorKind::SerializationError,
+                    String::from("Invalid backup entry"),
+                ));
+            }
+            let value = entry.pop().unwrap_or_default();
+            let key = entry.pop().unwrap_or_default();
+            tree.insert(key, value)?;
+        }
+    }
+    db.flush()?;
+    Ok(())
+}
@@ -2,11 +2,29 @@
 //! This module provides the `Entity` trait as well as other utilities to manipulate entities and entity stores.
 //! For relation-related definitions, take a look a the [`relation` module](relation/index.html).
 
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::collections::HashSet;
 use std::{fs::File, mem::size_of};
 
-use crate::error::Result;
-use crate::import_export::JsonWrapper;
-use crate::relation::{DeletionBehaviour, EntityRelations, FamilyDescriptor, Relation};
+use crate::audit::{AuditEntry, AuditLog, AuditOperation, AUDIT_TREE_PREFIX};
+use crate::entity_ref::EntityRef;
+use crate::error::{entity_not_found, ErrorContext, Result};
+use crate::import_export::{move_tree, ConflictPolicy, ImportReport, JsonWrapper};
+use crate::migration::{check_store_name, check_store_version};
+#[cfg(feature = "messagepack")]
+use crate::migration::check_store_format;
+use crate::relation::{
+    DeletionBehaviour, EntityRelations, FamilyDescriptor, Relation, RelationCreateResult,
+};
+use crate::sibling_link::{SiblingLink, UNLINKED_TREE_PREFIX};
+use crate::soft_delete::{SoftDelete, DELETED_TREE_PREFIX};
+use crate::subscriber::EntitySubscriber;
+use crate::ttl::{Ttl, TTL_TREE_PREFIX};
+use crate::unique::{Unique, UNIQUE_TREE_PREFIX};
+use crate::version::{Version, VERSION_TREE_PREFIX};
+use crate::write::{EntityWriter, Removal};
 use crate::Error;
 use serde::{de::DeserializeOwned, Serialize};
 use sled::{Batch, Db, IVec, Tree};
@@ -34,13 +52,24 @@ use sled::{Batch, Db, IVec, Tree};
 /// }
 /// ```
 ///
-/// ```rust
+/// ```rust,no_run
+/// # use reindeer::{Entity, Serialize, Deserialize, open};
+/// # #[derive(Serialize, Deserialize, Entity)]
+/// # #[entity(name = "my-struct", id = "key")]
+/// # struct MyStruct { pub key : u32, pub prop1 : String }
 /// let db = open("./my-db")?;
 /// let my_struct = MyStruct { key : 2 , prop1 : String::from("Hello, World!")};
 /// my_struct.save(&db)?;
+/// # Ok::<(), reindeer::Error>(())
 /// ```
 /// ```rust
+/// # use reindeer::{Entity, Serialize, Deserialize};
+/// # #[derive(Serialize, Deserialize, Entity)]
+/// # #[entity(name = "my-struct", id = "key")]
+/// # struct MyStruct { pub key : u32, pub prop1 : String }
+/// # let db = reindeer::open_memory()?;
 /// let my_struct_0 = MyStruct::get(&2,&db)?;
+/// # Ok::<(), reindeer::Error>(())
 /// ```
 ///
 /// More information on how to use the trait is provided below.
@@ -57,16 +86,38 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///  - `i64`
     type Key: AsBytes + Clone;
 
+    /// Maximum size in bytes of a serialized instance of this entity.
+    ///
+    /// If set, [`save`](entity/trait.Entity.html#method.save) fails with an
+    /// [`ErrorKind::EntityTooLarge`](enum.ErrorKind.html#variant.EntityTooLarge) error instead of storing a bigger entity.
+    ///
+    /// ```rust
+    /// const MAX_SIZE_BYTES: Option<usize> = Some(4096);
+    /// ```
+    const MAX_SIZE_BYTES: Option<usize> = None;
+
+    /// Version of the schema of this entity, set by the derive macro from `#[entity(version = N)]`.
+    ///
+    /// When the entity is [registered](entity/trait.Entity.html#method.register) in a database where its store was
+    /// registered with a lower version, [`migrate_from`](entity/trait.Entity.html#method.migrate_from) is called.
+    const SCHEMA_VERSION: u32 = 0;
+
     /// The name of the store, as a string.
     /// It represents a keyspace in the database. It needs to be unique for the struct that implements it.
     ///
     /// A recommendation is to return the name of the struct in `snake_case`.
     /// ### Example
     /// ```rust
+    /// # use reindeer::{Entity, Serialize, Deserialize};
+    /// # #[derive(Serialize, Deserialize)]
+    /// # struct MyStruct { key : u32 }
     /// impl Entity for MyStruct {
+    /// #     type Key = u32;
     ///     fn store_name() -> &'static str {
     ///         "my_struct"
     ///     }
+    /// #     fn get_key(&self) -> &Self::Key { &self.key }
+    /// #     fn set_key(&mut self, key : &Self::Key) { self.key = key.clone(); }
     /// }
     /// ```
     fn store_name() -> &'static str;
@@ -75,10 +126,16 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///
     /// ### Example
     /// ```rust
+    /// # use reindeer::{Entity, Serialize, Deserialize};
+    /// # #[derive(Serialize, Deserialize)]
+    /// # struct MyStruct { key : u32 }
     /// impl Entity for MyStruct {
+    /// #     type Key = u32;
+    /// #     fn store_name() -> &'static str { "my_struct" }
     ///     fn get_key(&self) -> &Self::Key {
     ///         &self.key
     ///     }
+    /// #     fn set_key(&mut self, key : &Self::Key) { self.key = key.clone(); }
     /// }
     /// ```
     fn get_key(&self) -> &Self::Key;
@@ -91,7 +148,13 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///
     /// ### Example
     /// ```rust
+    /// # use reindeer::{Entity, Serialize, Deserialize};
+    /// # #[derive(Serialize, Deserialize)]
+    /// # struct MyStruct { key : u32 }
     /// impl Entity for MyStruct {
+    /// #     type Key = u32;
+    /// #     fn store_name() -> &'static str { "my_struct" }
+    /// #     fn get_key(&self) -> &Self::Key { &self.key }
     ///     fn set_key(&mut self, key : &Self::Key) {
     ///         self.key = key.clone();
     ///     }
@@ -111,7 +174,14 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///
     /// ### Example
     /// ```rust
+    /// # use reindeer::{DeletionBehaviour, Entity, Serialize, Deserialize};
+    /// # #[derive(Serialize, Deserialize)]
+    /// # struct MyStruct { key : u32 }
     /// impl Entity for MyStruct {
+    /// #     type Key = u32;
+    /// #     fn store_name() -> &'static str { "my_struct" }
+    /// #     fn get_key(&self) -> &Self::Key { &self.key }
+    /// #     fn set_key(&mut self, key : &Self::Key) { self.key = key.clone(); }
     ///     fn get_sibling_stores() -> Vec<(&'static str, DeletionBehaviour)> {
     ///         vec![
     ///             ("sibling_struct_1",DeletionBehaviour::Cascade),
@@ -138,7 +208,14 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///
     /// ### Example
     /// ```rust
+    /// # use reindeer::{DeletionBehaviour, Entity, Serialize, Deserialize};
+    /// # #[derive(Serialize, Deserialize)]
+    /// # struct MyStruct { key : u32 }
     /// impl Entity for MyStruct {
+    /// #     type Key = u32;
+    /// #     fn store_name() -> &'static str { "my_struct" }
+    /// #     fn get_key(&self) -> &Self::Key { &self.key }
+    /// #     fn set_key(&mut self, key : &Self::Key) { self.key = key.clone(); }
     ///     fn get_child_stores() -> Vec<(&'static str, DeletionBehaviour)> {
     ///         vec![
     ///             ("child_struct",DeletionBehaviour::Cascade),
@@ -150,49 +227,251 @@ pub trait Entity: Serialize + DeserializeOwned {
         Vec::new()
     }
 
+    /// A function that sets one of the `Option` fields of this entity instance to `None`, given its name.
+    /// This is used on behalf of the user when a related entity is removed with
+    /// [`DeletionBehaviour::SetNull`](relation/enum.DeletionBehaviour.html).
+    ///
+    /// The derive macro implements it for every `Option` field of the struct.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use reindeer::{Entity, Error, ErrorKind, Result, Serialize, Deserialize};
+    /// # #[derive(Serialize, Deserialize)]
+    /// # struct MyStruct { key : u32, parent_id : Option<u32> }
+    /// impl Entity for MyStruct {
+    /// #     type Key = u32;
+    /// #     fn store_name() -> &'static str { "my_struct" }
+    /// #     fn get_key(&self) -> &Self::Key { &self.key }
+    /// #     fn set_key(&mut self, key : &Self::Key) { self.key = key.clone(); }
+    ///     fn set_field_to_none(&mut self, field : &str) -> Result<()> {
+    ///         match field {
+    ///             "parent_id" => self.parent_id = None,
+    ///             _ => return Err(Error::new(ErrorKind::IntegrityError, format!("Cannot set {} to None", field))),
+    ///         }
+    ///         Ok(())
+    ///     }
+    /// }
+    /// ```
+    fn set_field_to_none(&mut self, field: &str) -> Result<()> {
+        Err(Error::new(
+            crate::ErrorKind::IntegrityError,
+            format!(
+                "Field {} cannot be set to None in entity {}",
+                field,
+                Self::store_name()
+            ),
+        ))
+    }
+
+    #[doc(hidden)]
+    fn set_field_to_none_from_u8_array(key: &[u8], field: &str, db: &Db) -> Result<()> {
+        if let Some(mut entity) = Self::get_from_u8_array(key, db)? {
+            entity.set_field_to_none(field)?;
+            entity.save(db)?;
+        }
+        Ok(())
+    }
+
+    /// A function that sets the modification timestamp of this entity instance, in milliseconds since the Unix epoch.
+    /// This is used by [`touch`](entity/trait.Entity.html#method.touch).
+    ///
+    /// The derive macro implements it for the `u64` field named with `#[entity(timestamp_field = "modified_at")]`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use reindeer::{Entity, Result, Serialize, Deserialize};
+    /// # #[derive(Serialize, Deserialize)]
+    /// # struct MyStruct { key : u32, modified_at : u64 }
+    /// impl Entity for MyStruct {
+    /// #     type Key = u32;
+    /// #     fn store_name() -> &'static str { "my_struct" }
+    /// #     fn get_key(&self) -> &Self::Key { &self.key }
+    /// #     fn set_key(&mut self, key : &Self::Key) { self.key = key.clone(); }
+    ///     fn set_updated_at(&mut self, ts : u64) -> Result<()> {
+    ///         self.modified_at = ts;
+    ///         Ok(())
+    ///     }
+    /// }
+    /// ```
+    fn set_updated_at(&mut self, _ts: u64) -> Result<()> {
+        Err(Error::new(
+            crate::ErrorKind::IntegrityError,
+            format!("Entity {} has no timestamp field", Self::store_name()),
+        ))
+    }
+
     /// Call this function once the database is opened on each Entity that you want to use.
     /// This is necessary to provide safe and type-agnostic deletion mechanisms.
     ///
     /// ⚠ If this function is not called, deleting an entity of that type will result in an error.
     ///
+    /// This also runs [`migrate_from`](entity/trait.Entity.html#method.migrate_from) if the store was last registered
+    /// with an older [`SCHEMA_VERSION`](entity/trait.Entity.html#associatedconstant.SCHEMA_VERSION).
+    ///
     /// ### Example
     ///
     /// ```rust
-    /// impl Entity for MyStruct { /* ... */}
+    /// # use reindeer::{Entity, Serialize, Deserialize};
+    /// #[derive(Serialize, Deserialize, Entity)]
+    /// #[entity(name = "my_struct")]
+    /// struct MyStruct { id : u32, /* ... */ }
     /// ```
     ///
     /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
     /// MyStruct::register(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn register(db: &Db) -> Result<()> {
-        let desc = FamilyDescriptor {
+        check_store_name::<Self>()?;
+        let mut descriptor = Self::family_descriptor();
+        Relation::keep_custom(&mut descriptor, db)?;
+        descriptor.save(db)?;
+        Relation::register_custom(&descriptor, db)?;
+        Relation::register_set_null::<Self>();
+        SoftDelete::register::<Self>(db)?;
+        Ttl::register::<Self>(db);
+        check_store_version::<Self>(db)?;
+        #[cfg(feature = "messagepack")]
+        check_store_format::<Self>(db)?;
+        Unique::register::<Self>(db)
+    }
+
+    /// Describes the sibling and child stores of this entity, without accessing the database.
+    ///
+    /// Descriptors of several entity types can be saved upfront with [`register_all`](fn.register_all.html).
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// reindeer::register_all(&db, &[MyStruct1::family_descriptor(), MyStruct2::family_descriptor()])?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn family_descriptor() -> FamilyDescriptor {
+        FamilyDescriptor {
             tree_name: String::from(Self::store_name()),
             child_trees: Self::get_child_stores()
-                .iter()
+                .into_iter()
                 .map(|e| (String::from(e.0), e.1))
                 .collect(),
             sibling_trees: Self::get_sibling_stores()
-                .iter()
+                .into_iter()
                 .map(|e| (String::from(e.0), e.1))
                 .collect(),
-        };
-        desc.save(db)?;
-        Ok(())
+        }
     }
 
-    #[doc(hidden)]
+    /// Escape hatch : returns the `sled` tree in which the entities of this store are saved, for operations not covered
+    /// by `reindeer`'s API.
+    ///
+    /// Keys are the [`AsBytes`](trait.AsBytes.html) representation of entity keys, and values are the serialized entities.
+    ///
+    /// ⚠ Writing to this tree bypasses every check made by `reindeer` : you are responsible for maintaining relation
+    /// integrity, unique indexes, TTLs and versions of the entities you write or remove.
+    ///
+    /// Fails with an [`ErrorKind::TreeOpenError`](enum.ErrorKind.html#variant.TreeOpenError) if the tree cannot be opened.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let tree = MyStruct::get_tree(&db)?;
+    /// let size_on_disk = tree.iter().values().map(|v| v.map(|v| v.len())).sum::<Result<usize, _>>()?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
     fn get_tree(db: &Db) -> Result<Tree> {
-        Ok(db.open_tree(Self::store_name())?)
+        db.open_tree(Self::store_name()).map_err(|e| {
+            Error::with_source(
+                crate::ErrorKind::TreeOpenError,
+                format!("Could not open tree {}", Self::store_name()),
+                e,
+            )
+            .with_context(ErrorContext::TreeOpen {
+                tree_name: String::from(Self::store_name()),
+            })
+        })
+    }
+
+    /// Names of the fields declared unique with `#[entity(unique = "field")]`.
+    ///
+    /// When this is not empty, every method writing an entity ([`save`](entity/trait.Entity.html#method.save), `update`,
+    /// `versioned_save`, transactional saves...) fails with an
+    /// [`ErrorKind::UniqueConstraintViolation`](enum.ErrorKind.html#variant.UniqueConstraintViolation) error if another
+    /// entity of the store has the same value for one of these fields.
+    ///
+    /// The indexes enforcing these constraints are rebuilt by [`register`](entity/trait.Entity.html#method.register),
+    /// which fails if two stored entities have the same value for one of these fields.
+    fn get_unique_fields() -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    #[doc(hidden)]
+    fn get_unique_values(&self) -> Result<Vec<(&'static str, Vec<u8>)>> {
+        Ok(Vec::new())
+    }
+
+    #[doc(hidden)]
+    fn serialization_error<E: std::error::Error + Send + Sync + 'static>(source: E) -> Error {
+        Error::with_source(
+            crate::ErrorKind::SerializationError,
+            format!("Could not serialize or deserialize {} : {}", Self::store_name(), source),
+            source,
+        )
+        .with_context(ErrorContext::Serialization {
+            entity_type: Self::store_name(),
+        })
+    }
+
+    #[doc(hidden)]
+    #[cfg(not(feature = "messagepack"))]
+    fn try_from_ivec(vec: IVec) -> Result<Self> {
+        bincode::deserialize::<Self>(vec.as_ref()).map_err(Self::serialization_error)
     }
 
     #[doc(hidden)]
+    #[cfg(feature = "messagepack")]
     fn try_from_ivec(vec: IVec) -> Result<Self> {
-        Ok(bincode::deserialize::<Self>(vec.as_ref())?)
+        rmp_serde::from_slice::<Self>(vec.as_ref()).map_err(Self::serialization_error)
+    }
+
+    #[doc(hidden)]
+    #[cfg(not(feature = "messagepack"))]
+    fn try_into_ivec(&self) -> Result<IVec> {
+        Ok(IVec::from(
+            bincode::serialize(self).map_err(Self::serialization_error)?,
+        ))
     }
 
     #[doc(hidden)]
+    #[cfg(feature = "messagepack")]
     fn try_into_ivec(&self) -> Result<IVec> {
-        Ok(IVec::from(bincode::serialize(self)?))
+        Ok(IVec::from(
+            rmp_serde::to_vec_named(self).map_err(Self::serialization_error)?,
+        ))
+    }
+
+    #[doc(hidden)]
+    fn try_into_ivec_with_limit(&self, limit: Option<usize>) -> Result<IVec> {
+        self.validate()?;
+        let value = self.try_into_ivec()?;
+        match limit {
+            Some(limit) if value.len() > limit => Err(Error::new(
+                crate::ErrorKind::EntityTooLarge {
+                    size: value.len(),
+                    limit,
+                },
+                format!(
+                    "Entity of {} bytes exceeds the limit of {} bytes in {}",
+                    value.len(),
+                    limit,
+                    Self::store_name()
+                ),
+            )),
+            _ => Ok(value),
+        }
     }
 
     /// Retrieves an entity instance given its key.
@@ -202,9 +481,12 @@ pub trait Entity: Serialize + DeserializeOwned {
     /// ### Example
     ///
     /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
     /// if let Some(my_struct_4) = MyStruct::get(&4,&db)? {
     ///     /* ... */
     /// }
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn get(key: &Self::Key, db: &Db) -> Result<Option<Self>> {
         Self::get_from_u8_array(&key.as_bytes(), db)
@@ -219,27 +501,244 @@ pub trait Entity: Serialize + DeserializeOwned {
     /// ### Example
     ///
     /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
     /// let entities = MyStruct::get_all(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn get_all(db: &Db) -> Result<Vec<Self>> {
+        trace_span!("get_all", Self::store_name());
         Self::get_tree(db)?
             .iter()
             .map(|elem| -> Result<Self> { Self::try_from_ivec(elem?.1) })
             .collect()
     }
 
+    /// Retrieves all entities of a given type, like [`get_all`](entity/trait.Entity.html#method.get_all), but
+    /// deserializes them in parallel on all CPU cores.
+    ///
+    /// Serialized entities are first read sequentially, so this is only faster for large stores of large entities.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let entities = MyStruct::get_all_parallel(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    #[cfg(feature = "rayon")]
+    fn get_all_parallel(db: &Db) -> Result<Vec<Self>>
+    where
+        Self: Send,
+    {
+        use rayon::prelude::*;
+        trace_span!("get_all_parallel", Self::store_name());
+        let values = Self::get_tree(db)?
+            .iter()
+            .values()
+            .collect::<std::result::Result<Vec<IVec>, sled::Error>>()?;
+        values.into_par_iter().map(Self::try_from_ivec).collect()
+    }
+
+    /// Retrieves all entities of a given type that can be deserialized, along with the key and the error of each
+    /// entity that could not be.
+    ///
+    /// This allows working on the healthy part of a store while reporting corrupted entries. Storage errors still
+    /// make the whole call fail.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let (entities, errors) = MyStruct::get_all_lenient(&db)?;
+    /// for (key, error) in errors {
+    ///     eprintln!("Corrupted entry {:?} : {}", key, error);
+    /// }
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    #[allow(clippy::type_complexity)]
+    fn get_all_lenient(db: &Db) -> Result<(Vec<Self>, Vec<(Vec<u8>, Error)>)> {
+        let mut entities = Vec::new();
+        let mut errors = Vec::new();
+        for elem in Self::get_tree(db)?.iter() {
+            let (key, value) = elem?;
+            match Self::try_from_ivec(value) {
+                Ok(entity) => entities.push(entity),
+                Err(e) => errors.push((key.to_vec(), e)),
+            }
+        }
+        Ok((entities, errors))
+    }
+
+    /// Retrieves all entities of a given type, except the ones marked as deleted with
+    /// [`soft_remove`](entity/trait.Entity.html#method.soft_remove).
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let entities = MyStruct::get_active(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_active(db: &Db) -> Result<Vec<Self>> {
+        let mut entities = Vec::new();
+        for elem in Self::get_tree(db)?.iter() {
+            let (key, value) = elem?;
+            if SoftDelete::get_deleted_at(Self::store_name(), &key, db)?.is_none() {
+                entities.push(Self::try_from_ivec(value)?);
+            }
+        }
+        Ok(entities)
+    }
+
     /// Returns the number of saved instances for this entity type.
     ///
     /// ### Example
     /// ```rust
-    /// let count = MyStruct::get_count()?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let count = MyStruct::get_count(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn get_count(db: &Db) -> Result<usize> {
         Ok(Self::get_tree(db)?.len())
     }
 
+    /// Returns the size in bytes this entity instance would take once serialized, without storing it.
+    /// If it cannot be serialized, `0` is returned.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # let my_struct = MyStruct { key : 3, prop1 : String::from("Hello"), prop2 : 554 };
+    /// println!("{} bytes", my_struct.estimate_serialized_size());
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    #[cfg(not(feature = "messagepack"))]
+    fn estimate_serialized_size(&self) -> usize {
+        bincode::serialized_size(self).unwrap_or(0) as usize
+    }
+
+    /// Returns the size in bytes this entity instance would take once serialized, without storing it.
+    /// If it cannot be serialized, `0` is returned.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # let my_struct = MyStruct { key : 3, prop1 : String::from("Hello"), prop2 : 554 };
+    /// println!("{} bytes", my_struct.estimate_serialized_size());
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    #[cfg(feature = "messagepack")]
+    fn estimate_serialized_size(&self) -> usize {
+        rmp_serde::to_vec_named(self).map(|v| v.len()).unwrap_or(0)
+    }
+
+    /// Estimates the disk space used by this store, as the sum of the sizes in bytes of every key and value in it.
+    ///
+    /// This is an estimate : `sled` does not report the size of a single tree, and the actual disk usage also
+    /// depends on its internal structures and compression. Entries are read one at a time, and are not deserialized.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let size = MyStruct::get_tree_size_bytes(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_tree_size_bytes(db: &Db) -> Result<u64> {
+        let mut size = 0;
+        for elem in Self::get_tree(db)?.iter() {
+            let (key, value) = elem?;
+            size += (key.len() + value.len()) as u64;
+        }
+        Ok(size)
+    }
+
+    /// Returns the size in bytes of the stored entity with the given key, without deserializing it.
+    ///
+    /// If the key does not exist, it returns None.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let size = MyStruct::get_stored_size(&3, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_stored_size(key: &Self::Key, db: &Db) -> Result<Option<usize>> {
+        Ok(Self::get_tree(db)?.get(key.as_bytes())?.map(|v| v.len()))
+    }
+
+    /// Estimates the number of saved instances matching a condition materialized
+    /// as a function returning a boolean, without evaluating it on every entity.
+    ///
+    /// The condition is evaluated on `sample_size` evenly-spaced entities of the store,
+    /// and the number of matches is extrapolated to the whole store.
+    /// The result is returned as a tuple `(estimate, standard_error)`.
+    ///
+    /// If `sample_size` is greater than or equal to the number of entities in the store, the count is exact
+    /// and the standard error is `0`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let (estimate, std_error) = MyStruct::estimate_count_with_filter(|m_struct| m_struct.prop2 > 20, 100, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn estimate_count_with_filter<F: Fn(&Self) -> bool>(
+        f: F,
+        sample_size: usize,
+        db: &Db,
+    ) -> Result<(f64, f64)> {
+        let tree = Self::get_tree(db)?;
+        let total = tree.len();
+        if total == 0 {
+            return Ok((0.0, 0.0));
+        }
+        let sample_size = sample_size.clamp(1, total);
+        let (mut matches, mut sampled) = (0, 0);
+        for (position, elem) in tree.iter().enumerate() {
+            if sampled == sample_size {
+                break;
+            }
+            // The i-th sampled entity is the one at position i * total / sample_size, to spread the sample across the
+            // whole store even when total is not a multiple of sample_size
+            if position == sampled * total / sample_size {
+                if f(&Self::try_from_ivec(elem?.1)?) {
+                    matches += 1;
+                }
+                sampled += 1;
+            }
+        }
+        if sampled == 0 {
+            return Ok((0.0, 0.0));
+        }
+        let (sample_size, total) = (sampled as f64, total as f64);
+        let proportion = matches as f64 / sample_size;
+        let finite_population_correction = if total > 1.0 {
+            ((total - sample_size) / (total - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        let standard_error = total
+            * (proportion * (1.0 - proportion) / sample_size).sqrt()
+            * finite_population_correction;
+        Ok((proportion * total, standard_error))
+    }
+
     #[doc(hidden)]
     fn get_from_u8_array(key: &[u8], db: &Db) -> Result<Option<Self>> {
+        trace_span!("get", Self::store_name(), key);
+        if Ttl::is_expired(Self::store_name(), key, db)? {
+            return Ok(None);
+        }
         Self::get_tree(db)?
             .get(key)?
             .map(|vec| -> Result<Self> { Self::try_from_ivec(vec) })
@@ -260,7 +759,10 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///
     /// ### Example
     /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
     /// let entities = MyStruct::get_in_range(10,30,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn get_in_range(start: impl AsBytes, end: impl AsBytes, db: &Db) -> Result<Vec<Self>> {
         Self::get_tree(db)?
@@ -273,7 +775,10 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///
     /// ### Example
     /// ```rust
-    /// let entities = MyStruct::get_from_start(10,20,None,&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let entities = MyStruct::get_from_start(10,20,None::<u32>,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     /// ## Child entities
     ///
@@ -281,7 +786,11 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///
     /// ### Example
     /// ```rust
-    /// let entities = MyStruct::get_from_start(10,20,Some(parent.get_key().to_owned()),&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # let parent = MyStruct1 { key : 9, prop9 : 44 };
+    /// let entities = ChildStruct::get_from_start(10,20,Some(parent.get_key().to_owned()),&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn get_from_start(
         start: usize,
@@ -313,7 +822,10 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///
     /// ### Example
     /// ```rust
-    /// let entities = MyStruct::get_from_end(10,20,None,&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let entities = MyStruct::get_from_end(10,20,None::<u32>,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     /// ## Child entities
     ///
@@ -321,7 +833,11 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///
     /// ### Example
     /// ```rust
-    /// let entities = MyStruct::get_from_end(10,20,Some(parent.get_key().to_owned()),&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # let parent = MyStruct1 { key : 9, prop9 : 44 };
+    /// let entities = ChildStruct::get_from_end(10,20,Some(parent.get_key().to_owned()),&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn get_from_end(
         start: usize,
@@ -358,7 +874,10 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///
     /// ### Example
     /// ```rust
-    /// let entities = MyStruct::get_with_filter(|m_struct| m_struct.prop > 20,&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let entities = MyStruct::get_with_filter(|m_struct| m_struct.prop2 > 20,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn get_with_filter<F: Fn(&Self) -> bool>(f: F, db: &Db) -> Result<Vec<Self>> {
         Self::get_tree(db)?
@@ -366,18 +885,130 @@ pub trait Entity: Serialize + DeserializeOwned {
             .map(|elem| -> Result<Self> { Self::try_from_ivec(elem?.1) })
             .filter(|e| match e {
                 Ok(v) => f(v),
-                Err(_) => false,
+                Err(_) => true,
+            })
+            .collect()
+    }
+
+    /// Gets all entities of a given store matching a condition, like [`get_with_filter`](entity/trait.Entity.html#method.get_with_filter),
+    /// but deserializes and filters them in parallel on all CPU cores.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let entities = MyStruct::get_with_filter_parallel(|m_struct| m_struct.prop2 > 20,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    #[cfg(feature = "rayon")]
+    fn get_with_filter_parallel<F: Fn(&Self) -> bool + Send + Sync>(
+        f: F,
+        db: &Db,
+    ) -> Result<Vec<Self>>
+    where
+        Self: Send,
+    {
+        use rayon::prelude::*;
+        let values = Self::get_tree(db)?
+            .iter()
+            .values()
+            .collect::<std::result::Result<Vec<IVec>, sled::Error>>()?;
+        values
+            .into_par_iter()
+            .filter_map(|value| match Self::try_from_ivec(value) {
+                Ok(v) if f(&v) => Some(Ok(v)),
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
             })
             .collect()
     }
 
+    /// Gets all entities of a given store matching a condition, reading them in chunks of `chunk_size` entries.
+    ///
+    /// Each chunk is deserialized and filtered in parallel on all CPU cores while the next one is read, so that
+    /// reads and deserialization overlap. Unlike [`get_with_filter_parallel`](entity/trait.Entity.html#method.get_with_filter_parallel),
+    /// at most two chunks of serialized entities are held in memory at once.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let entities = MyStruct::get_with_filter_chunked(|m_struct| m_struct.prop2 > 20, 10000, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    #[cfg(feature = "rayon")]
+    fn get_with_filter_chunked<F: Fn(&Self) -> bool + Send + Sync>(
+        f: F,
+        chunk_size: usize,
+        db: &Db,
+    ) -> Result<Vec<Self>>
+    where
+        Self: Send,
+    {
+        use rayon::prelude::*;
+        fn read_chunk(iter: &mut sled::Iter, chunk_size: usize) -> Result<Vec<IVec>> {
+            let mut chunk = Vec::with_capacity(chunk_size);
+            for elem in iter.take(chunk_size) {
+                chunk.push(elem?.1);
+            }
+            Ok(chunk)
+        }
+        let chunk_size = chunk_size.max(1);
+        let mut iter = Self::get_tree(db)?.iter();
+        let mut result = Vec::new();
+        let mut chunk = read_chunk(&mut iter, chunk_size)?;
+        while !chunk.is_empty() {
+            let (filtered, next_chunk) = rayon::join(
+                || {
+                    chunk
+                        .into_par_iter()
+                        .filter_map(|value| match Self::try_from_ivec(value) {
+                            Ok(v) if f(&v) => Some(Ok(v)),
+                            Ok(_) => None,
+                            Err(e) => Some(Err(e)),
+                        })
+                        .collect::<Result<Vec<Self>>>()
+                },
+                || read_chunk(&mut iter, chunk_size),
+            );
+            result.extend(filtered?);
+            chunk = next_chunk?;
+        }
+        Ok(result)
+    }
+
+    /// Returns the keys of all the entities of the store, without deserializing the entities themselves.
+    ///
+    /// This requires the key type to implement [`FromBytes`](entity/trait.FromBytes.html).
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let keys : Vec<u32> = MyStruct::get_typed_keys_all(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_typed_keys_all(db: &Db) -> Result<Vec<Self::Key>>
+    where
+        Self::Key: FromBytes,
+    {
+        Self::get_tree(db)?
+            .iter()
+            .keys()
+            .map(|key| Self::Key::from_bytes(&key?))
+            .collect()
+    }
+
     /// Gets several entites matching a collection of keys
     ///
     /// ⚠ This will call `get` as many times as the number of keys provided.
     ///
     /// ### Example
     /// ```rust
-    /// let entities = MyStruct::get_each(vec![4,8,9],&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let entities = MyStruct::get_each(&[4,8,9],&db);
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn get_each(keys: &[Self::Key], db: &Db) -> Vec<Self> {
         keys.iter()
@@ -389,7 +1020,27 @@ pub trait Entity: Serialize + DeserializeOwned {
             .collect()
     }
 
-    #[doc(hidden)]
+    /// Gets several entities matching a collection of keys, like [`get_each`](entity/trait.Entity.html#method.get_each),
+    /// but returns one element per key, in the same order : `None` if no entity has this key.
+    ///
+    /// Unlike `get_each`, this fails if an entity cannot be read or deserialized.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let entities = MyStruct::get_each_nullable(&[4,8,9],&db)?;
+    /// assert_eq!(entities.len(), 3);
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_each_nullable(keys: &[Self::Key], db: &Db) -> Result<Vec<Option<Self>>> {
+        keys.iter().map(|key| Self::get(key, db)).collect()
+    }
+
+    /// Gets several entities matching a collection of keys already converted to bytes, for instance the ones
+    /// returned by [`get_related_keys`](entity/trait.Entity.html#method.get_related_keys).
+    ///
+    /// See [`get_each`](entity/trait.Entity.html#method.get_each).
     fn get_each_u8(keys: &[Vec<u8>], db: &Db) -> Vec<Self> {
         keys.iter()
             .map(|key| Self::get_from_u8_array(key, db))
@@ -405,98 +1056,617 @@ pub trait Entity: Serialize + DeserializeOwned {
     /// ### Example
     ///
     /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
     /// let my_struct = MyStruct { key : 0, prop1 : String::from("Hello"), prop2 : 554};
     /// my_struct.save(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn save(&self, db: &Db) -> Result<()> {
-        Self::get_tree(db)?.insert(&self.get_key().as_bytes(), bincode::serialize(self)?)?;
-        Ok(())
+        trace_span!("save", Self::store_name(), self.get_key().as_bytes());
+        let value = self.try_into_ivec_with_limit(Self::MAX_SIZE_BYTES)?;
+        self.write_ivec(value, db)
     }
 
-    /// Updates an entity entry using the provided function
+    /// Saves an entity to the database like [`save`](entity/trait.Entity.html#method.save), unless its serialized
+    /// size exceeds `max_bytes`, in which case nothing is written and an
+    /// [`ErrorKind::EntityTooLarge`](enum.ErrorKind.html#variant.EntityTooLarge) error is returned.
     ///
     /// ### Example
-    /// This will get the `MyStruct` instance with key 3  and increment its `prop1` member
+    ///
     /// ```rust
-    /// MyStruct::update(&3,|my_struct| my_struct.prop1++,&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # let my_struct = MyStruct { key : 3, prop1 : String::from("Hello"), prop2 : 554 };
+    /// my_struct.save_with_limit(4096, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
-    fn update<F: Fn(&mut Self)>(key: &Self::Key, f: F, db: &Db) -> Result<()> {
-        Self::get_tree(db)?.fetch_and_update(&key.as_bytes(), |e| {
-            Result::ok(
-                e.map(|u8_arr| -> Result<IVec> {
-                    let mut value = Self::try_from_ivec(IVec::from(u8_arr))?;
-                    f(&mut value);
-                    value.try_into_ivec()
-                })
-                .transpose(),
-            )
-            .flatten()
-        })?;
-        Ok(())
+    fn save_with_limit(&self, max_bytes: usize, db: &Db) -> Result<()> {
+        let value = self.try_into_ivec_with_limit(Some(max_bytes))?;
+        self.write_ivec(value, db)
     }
 
-    /// Updates all entities that match a condition provided as a function
+    #[doc(hidden)]
+    fn write_ivec(&self, value: IVec, db: &Db) -> Result<()> {
+        EntityWriter::save::<Self>(&[(self.get_key().as_bytes(), value)], db)
+    }
+
+    /// Saves an entity to the database like [`save`](entity/trait.Entity.html#method.save), and appends an
+    /// [`AuditEntry`](struct.AuditEntry.html) recording that `actor` saved it to the audit log of this store.
     ///
     /// ### Example
-    /// This will get all the `MyStruct` instances with prop1 greater than 100
-    /// and change it to be 0 instead;
+    ///
     /// ```rust
-    /// MyStruct::filter_update(|my_struct| mu_struct.prop1 > 100,|my_struct| {my_struct.prop1 = 0;},&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # let my_struct = MyStruct { key : 3, prop1 : String::from("Hello"), prop2 : 554 };
+    /// my_struct.save_with_audit("alice", &db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
-    fn filter_update<F: Fn(&Self) -> bool, M: Fn(&mut Self)>(
-        filter: F,
-        modifier: M,
-        db: &Db,
-    ) -> Result<()> {
-        let mut res = Self::get_with_filter(filter, db)?;
-        for entity in &mut res {
-            modifier(entity);
-            entity.save(db)?;
-        }
-        Ok(())
+    fn save_with_audit(&self, actor: &str, db: &Db) -> Result<()> {
+        self.save(db)?;
+        AuditLog::append(
+            Self::store_name(),
+            &self.get_key().as_bytes(),
+            actor,
+            AuditOperation::Save,
+            db,
+        )
     }
 
-    /// Override this function by returning `true` to cause `pre_remove_hook` to be called before removing an entry.
+    /// Saves an entity to the database like [`save`](entity/trait.Entity.html#method.save), and makes it expire after `ttl`.
     ///
-    /// For this function to be useful, also override `pre_remove_hook` with your cleanup code.
-    fn use_pre_remove_hook() -> bool {
-        false
-    }
-
-    /// Override this function along with `use_pre_remove_hook`to be called before removing an entry.
-    /// Use this to clean up side effects before removing it.
-    /// If an entry cannot be removed (i.e. remaining constraints), this will not be called.
+    /// Once expired, the entity is not returned by [`get`](entity/trait.Entity.html#method.get) anymore, and is removed
+    /// by the next call to [`sweep_expired`](fn.sweep_expired.html) (see also [`run_expiry_sweeper`](fn.run_expiry_sweeper.html)).
+    /// Saving it again without a TTL makes it permanent.
     ///
-    /// ⚠ Child, sibling and related entries will automatically be removed *before* this one.
-    fn pre_remove_hook(&self, _db: &Db) -> Result<()> {
-        Ok(())
+    /// ⚠ Until they are swept, expired entities are still returned by methods reading the whole store, like `get_all`.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # use std::time::Duration;
+    /// # let session = Session { id : String::from("3f2a"), user_id : 3 };
+    /// session.save_with_ttl(Duration::from_secs(3600), &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn save_with_ttl(&self, ttl: Duration, db: &Db) -> Result<()> {
+        self.save(db)?;
+        Ttl::enable::<Self>();
+        Ttl::set_expiry(Self::store_name(), &self.get_key().as_bytes(), ttl, db)
     }
 
-    #[doc(hidden)]
-    fn pre_remove(key: &[u8], db: &Db) -> Result<()> {
-        let mut to_be_removed = EntityRelations::default();
-        Relation::can_be_deleted(Self::store_name(), key, &Vec::new(), &mut to_be_removed, db)?;
-        for (tree, keys) in &to_be_removed.related_entities {
-            let tree = db.open_tree(tree)?;
-            let mut batch = Batch::default();
-            keys.iter().for_each(|rd| batch.remove(rd.key.as_slice()));
-            tree.apply_batch(batch)?;
-        }
-        Relation::remove_entity_entry::<Self>(key, db)?;
-        Relation::remove_descriptor(Self::store_name(), key, db)?;
-        Ok(())
-    }
+    /// Saves an entity to the database only if its stored version is `expected_version`, and increments its version.
+    /// Entities that have never been saved with this method are at version `0`.
+    ///
+    /// This allows optimistic locking : read an entity and its version with
+    /// [`get_version`](entity/trait.Entity.html#method.get_version), modify it, and save it with the version that was read.
+    /// If it was saved by someone else in the meantime, nothing is written and an
+    /// [`ErrorKind::VersionConflict`](enum.ErrorKind.html#variant.VersionConflict) error is returned.
+    ///
+    /// Versions are kept when an entity is removed, so that a stale version can never match again.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct { key : 3, prop1 : String::from("Hello"), prop2 : 554 }.save(&db)?;
+    /// let version = MyStruct::get_version(&3, &db)?.unwrap_or(0);
+    /// let mut my_struct = MyStruct::get(&3, &db)?.unwrap();
+    /// my_struct.prop2 += 1;
+    /// my_struct.versioned_save(version, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn versioned_save(&self, expected_version: u64, db: &Db) -> Result<()> {
+        let value = self.try_into_ivec_with_limit(Self::MAX_SIZE_BYTES)?;
+        Version::save::<Self>(&self.get_key().as_bytes(), value, expected_version, db)
+    }
+
+    /// Updates an entity entry using the provided function, like [`update`](entity/trait.Entity.html#method.update), only if
+    /// its stored version is `expected_version`, and increments its version.
+    ///
+    /// Returns `true` if the entity was updated, and `false` if its version did not match, in which case nothing is written.
+    /// See [`versioned_save`](entity/trait.Entity.html#method.versioned_save) for how versions are tracked.
+    ///
+    /// Fails with an [`ErrorKind::EntityNotFound`](enum.ErrorKind.html#variant.EntityNotFound) error if the entity does not exist.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct { key : 3, prop1 : String::from("Hello"), prop2 : 554 }.save(&db)?;
+    /// let version = MyStruct::get_version(&3, &db)?.unwrap_or(0);
+    /// if !MyStruct::versioned_update(&3, version, |my_struct| my_struct.prop2 += 1, &db)? {
+    ///     println!("modified concurrently, retrying");
+    /// }
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn versioned_update<F: Fn(&mut Self)>(
+        key: &Self::Key,
+        expected_version: u64,
+        f: F,
+        db: &Db,
+    ) -> Result<bool> {
+        Version::update::<Self, _>(
+            &key.as_bytes(),
+            expected_version,
+            |value| {
+                let mut entity = Self::try_from_ivec(value)?;
+                f(&mut entity);
+                entity.try_into_ivec_with_limit(Self::MAX_SIZE_BYTES)
+            },
+            db,
+        )
+    }
+
+    /// Returns the version of an entity saved with [`versioned_save`](entity/trait.Entity.html#method.versioned_save),
+    /// or `None` if it has never been.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let version = MyStruct::get_version(&3, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_version(key: &Self::Key, db: &Db) -> Result<Option<u64>> {
+        Version::get(Self::store_name(), &key.as_bytes(), db)
+    }
+
+    /// Rewrites every entity of this store, reading it with the layout of `Old` and converting it with `f`,
+    /// and returns the number of migrated entities.
+    /// All entities are rewritten in a single batch : if one of them cannot be read as `Old`, or if a converted entity
+    /// is invalid, too large or breaks a unique constraint, nothing is written.
+    ///
+    /// This is meant to be used when the struct of an entity changes (new field, renamed field, changed type...),
+    /// as entities saved with the old layout cannot be read anymore. It must be run before anything else
+    /// reads the store, for instance in a [`MigrationStep`](trait.MigrationStep.html).
+    ///
+    /// ### Example
+    /// The old struct is kept around, with the same store name.
+    /// ```rust
+    /// # use reindeer::{Entity, Serialize, Deserialize};
+    /// # let db = reindeer::open_memory()?;
+    /// #[derive(Serialize, Deserialize, Entity)]
+    /// #[entity(name = "user", version = 1)]
+    /// struct UserV1 {
+    ///     id : u32,
+    ///     name : String,
+    /// }
+    ///
+    /// #[derive(Serialize, Deserialize, Entity)]
+    /// #[entity(name = "user", version = 2)]
+    /// struct UserV2 {
+    ///     id : u32,
+    ///     name : String,
+    ///     email : Option<String>,
+    /// }
+    ///
+    /// UserV2::migrate_field(|old : UserV1| UserV2 { id : old.id, name : old.name, email : None }, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn migrate_field<Old: Entity, F: Fn(Old) -> Self>(f: F, db: &Db) -> Result<usize> {
+        let tree = Self::get_tree(db)?;
+        let mut batch = Batch::default();
+        let mut entities = Vec::new();
+        for elem in tree.iter() {
+            let (key, value) = elem?;
+            let entity = f(Old::try_from_ivec(value)?);
+            let new_key = entity.get_key().as_bytes();
+            if new_key != key.as_ref() {
+                batch.remove(key);
+            }
+            batch.insert(
+                new_key.as_slice(),
+                entity.try_into_ivec_with_limit(Self::MAX_SIZE_BYTES)?,
+            );
+            entities.push((new_key, entity));
+        }
+        let indexes = Unique::build_indexes(&entities)?;
+        tree.apply_batch(batch)?;
+        Unique::write_indexes::<Self>(indexes, db)?;
+        Ok(entities.len())
+    }
+
+    /// Moves all the data of the store named `old_name` to the store of this entity, and returns the number of moved entities.
+    ///
+    /// This is meant to be used when the store name of an entity changes, as entities saved under the old name would
+    /// otherwise be silently abandoned : relations, family descriptors referencing the old name, and TTLs, deletion marks,
+    /// versions and audit logs are moved along, and the old trees are dropped.
+    /// Entities already saved under the new name with the same keys are overridden.
+    /// Side trees that do not exist under the old name are not created under the new one, so that a renamed store does
+    /// not get expiry dates or deletion marks enabled.
+    ///
+    /// ⚠ The rename is not atomic : each tree is moved on its own, and entries are only removed from the old trees once
+    /// they are written to the new ones. If it is interrupted, running it again finishes the move.
+    ///
+    /// Use [`RenameStore`](struct.RenameStore.html) to run it as a [`MigrationStep`](trait.MigrationStep.html).
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let count = Customer::rename_store("client", &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn rename_store(old_name: &str, db: &Db) -> Result<usize> {
+        if old_name == Self::store_name() {
+            return Ok(0);
+        }
+        let count = move_tree(old_name, Self::store_name(), db)?;
+        Relation::rename_tree(old_name, Self::store_name(), db)?;
+        for prefix in [
+            TTL_TREE_PREFIX,
+            DELETED_TREE_PREFIX,
+            VERSION_TREE_PREFIX,
+            AUDIT_TREE_PREFIX,
+            UNLINKED_TREE_PREFIX,
+        ] {
+            move_tree(
+                &format!("{}{}", prefix, old_name),
+                &format!("{}{}", prefix, Self::store_name()),
+                db,
+            )?;
+        }
+        for field in Self::get_unique_fields() {
+            move_tree(
+                &format!("{}{}_{}", UNIQUE_TREE_PREFIX, old_name, field),
+                &format!("{}{}_{}", UNIQUE_TREE_PREFIX, Self::store_name(), field),
+                db,
+            )?;
+        }
+        FamilyDescriptor::rename(old_name, Self::store_name(), db)?;
+        let siblings = Self::get_sibling_stores()
+            .into_iter()
+            .map(|(name, _)| String::from(name))
+            .collect::<Vec<String>>();
+        SiblingLink::rename_store(old_name, Self::store_name(), &siblings, db)?;
+        SoftDelete::register::<Self>(db)?;
+        Ttl::register::<Self>(db);
+        Unique::register::<Self>(db)?;
+        Ok(count)
+    }
+
+    /// Copies the entity of key `key` from the `source` database to the `dest` database, and returns `true` if it
+    /// existed in `source`.
+    ///
+    /// The entity is saved to `dest` with [`save`](entity/trait.Entity.html#method.save), so this entity must be
+    /// registered in `dest` and its unique constraints are checked there.
+    ///
+    /// ⚠ Relations, children and siblings are not copied : they have to be copied separately.
+    ///
+    /// ### Example
+    /// ```rust,no_run
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let tenant_a = reindeer::open("tenant_a")?;
+    /// let tenant_b = reindeer::open("tenant_b")?;
+    /// MyStruct::register(&tenant_b)?;
+    /// let copied = MyStruct::copy_to(&3, &tenant_a, &tenant_b)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn copy_to(key: &Self::Key, source: &Db, dest: &Db) -> Result<bool> {
+        match Self::get(key, source)? {
+            Some(entity) => {
+                entity.save(dest)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Copies all the entities of this store from the `source` database to the `dest` database, and returns the
+    /// number of copied entities.
+    ///
+    /// See [`copy_to`](entity/trait.Entity.html#method.copy_to) : relations, children and siblings are not copied either.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # let tenant_a = doctest_db()?;
+    /// # let tenant_b = doctest_db()?;
+    /// let count = MyStruct::copy_all_to(&tenant_a, &tenant_b)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn copy_all_to(source: &Db, dest: &Db) -> Result<usize> {
+        let mut count = 0;
+        for elem in Self::get_tree(source)?.iter() {
+            Self::try_from_ivec(elem?.1)?.save(dest)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Updates an entity entry using the provided function
+    ///
+    /// If the updated entity is invalid or too large, it is left unchanged and the error is returned.
+    ///
+    /// ### Example
+    /// This will get the `MyStruct` instance with key 3  and increment its `prop1` member
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct { key : 3, prop1 : String::from("Hello"), prop2 : 554 }.save(&db)?;
+    /// MyStruct::update(&3,|my_struct| my_struct.prop2 += 1,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn update<F: Fn(&mut Self)>(key: &Self::Key, f: F, db: &Db) -> Result<()> {
+        Self::try_update(
+            key,
+            |entity| {
+                f(entity);
+                Ok(())
+            },
+            db,
+        )
+    }
+
+    /// Saves `new` if no entity exists with key `key`. Otherwise, merges `new` into the existing entity with `merge_fn`,
+    /// which receives the existing entity and `new`, and saves the result.
+    ///
+    /// ⚠ This is not atomic : the entity may be changed concurrently between the read and the write.
+    ///
+    /// ### Example
+    /// This will save `my_struct`, or only update the `prop1` member of the existing entity with key 3
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # let my_struct = MyStruct { key : 3, prop1 : String::from("Hello"), prop2 : 554 };
+    /// MyStruct::save_or_update(&3, my_struct, |existing, new| existing.prop1 = new.prop1.clone(), &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn save_or_update<F: Fn(&mut Self, &Self)>(
+        key: &Self::Key,
+        new: Self,
+        merge_fn: F,
+        db: &Db,
+    ) -> Result<()> {
+        match Self::get(key, db)? {
+            Some(mut existing) => {
+                merge_fn(&mut existing, &new);
+                existing.save(db)
+            }
+            None => {
+                let mut new = new;
+                new.set_key(key);
+                new.save(db)
+            }
+        }
+    }
+
+    /// Atomically applies `field_fn` with `delta` to the entity with key `key`, to increment (or decrement) a numeric field
+    /// without a race between reading and saving it.
+    ///
+    /// `field_fn` may be called several times if the entity is changed concurrently. Fails with an
+    /// [`ErrorKind::EntityNotFound`](enum.ErrorKind.html#variant.EntityNotFound) error if the entity does not exist.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # let id = 3;
+    /// # Order { id, quantity : 1 }.save(&db)?;
+    /// Order::atomic_increment_field(&id, |o, d| o.quantity += d as u32, 5, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn atomic_increment_field<F: Fn(&mut Self, i64)>(
+        key: &Self::Key,
+        field_fn: F,
+        delta: i64,
+        db: &Db,
+    ) -> Result<()> {
+        let key = key.as_bytes();
+        let updated = EntityWriter::update::<Self, _>(
+            &key,
+            |entity| {
+                field_fn(entity, delta);
+                Ok(())
+            },
+            db,
+        )?;
+        if !updated {
+            return Err(entity_not_found(Self::store_name(), &key));
+        }
+        Ok(())
+    }
+
+    /// Atomically sets the modification timestamp of the entity of key `key` to the current time, in milliseconds since
+    /// the Unix epoch, using [`set_updated_at`](entity/trait.Entity.html#method.set_updated_at).
+    ///
+    /// The entity is deserialized, updated and serialized again within `sled`'s `fetch_and_update`, so that concurrent
+    /// writes are not lost. If the entity does not exist, an
+    /// [`ErrorKind::EntityNotFound`](enum.ErrorKind.html#variant.EntityNotFound) error is returned. If it has no
+    /// timestamp field or cannot be deserialized, it is left unchanged and the error is returned.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use reindeer::{Entity, Serialize, Deserialize};
+    /// # let db = reindeer::open_memory()?;
+    /// #[derive(Serialize, Deserialize, Entity)]
+    /// #[entity(name = "document", timestamp_field = "modified_at")]
+    /// struct Document {
+    ///     id : u32,
+    ///     content : String,
+    ///     modified_at : u64,
+    /// }
+    /// # Document { id : 3, content : String::new(), modified_at : 0 }.save(&db)?;
+    ///
+    /// Document::touch(&3, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn touch(key: &Self::Key, db: &Db) -> Result<()> {
+        let key = key.as_bytes();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        if !EntityWriter::update::<Self, _>(&key, |entity| entity.set_updated_at(now), db)? {
+            return Err(entity_not_found(Self::store_name(), &key));
+        }
+        Ok(())
+    }
+
+    /// Updates an entity entry using the provided fallible function.
+    ///
+    /// If `f` returns an error, the entity is left unchanged and the error is returned.
+    ///
+    /// ### Example
+    /// This will get the `MyStruct` instance with key 3 and increment its `prop1` member, unless it would exceed 100
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct { key : 3, prop1 : String::from("Hello"), prop2 : 554 }.save(&db)?;
+    /// MyStruct::try_update(&3,|my_struct| {
+    ///     if my_struct.prop2 >= 1000 {
+    ///         return Err(Error::new(ErrorKind::IntegrityError, String::from("prop2 is too big")));
+    ///     }
+    ///     my_struct.prop2 += 1;
+    ///     Ok(())
+    /// },&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn try_update<F: Fn(&mut Self) -> Result<()>>(key: &Self::Key, f: F, db: &Db) -> Result<()> {
+        EntityWriter::update::<Self, _>(&key.as_bytes(), f, db)?;
+        Ok(())
+    }
+
+    /// Updates all entities that match a condition provided as a function
+    ///
+    /// ### Example
+    /// This will get all the `MyStruct` instances with prop1 greater than 100
+    /// and change it to be 0 instead;
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// MyStruct::filter_update(|my_struct| my_struct.prop2 > 100,|my_struct| {my_struct.prop2 = 0;},&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn filter_update<F: Fn(&Self) -> bool, M: Fn(&mut Self)>(
+        filter: F,
+        modifier: M,
+        db: &Db,
+    ) -> Result<()> {
+        let mut res = Self::get_with_filter(filter, db)?;
+        for entity in &mut res {
+            modifier(entity);
+            entity.save(db)?;
+        }
+        Ok(())
+    }
+
+    /// Override this function by returning `true` to cause `pre_remove_hook` to be called before removing an entry.
+    ///
+    /// For this function to be useful, also override `pre_remove_hook` with your cleanup code.
+    fn use_pre_remove_hook() -> bool {
+        false
+    }
+
+    /// Override this function along with `use_pre_remove_hook`to be called before removing an entry.
+    /// Use this to clean up side effects before removing it.
+    /// If an entry cannot be removed (i.e. remaining constraints), this will not be called.
+    ///
+    /// ⚠ Child, sibling and related entries will automatically be removed *before* this one.
+    fn pre_remove_hook(&self, _db: &Db) -> Result<()> {
+        Ok(())
+    }
+
+    /// Override this function to check business rules before an entity is written.
+    /// It is called by every method writing an entity ([`save`](entity/trait.Entity.html#method.save), `update`,
+    /// `versioned_save`, migrations, transactional saves...), and nothing is written if it returns an error.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use reindeer::{Entity, Error, ErrorKind, Result, Serialize, Deserialize};
+    /// # #[derive(Serialize, Deserialize)]
+    /// # struct MyStruct { key : u32, prop1 : String }
+    /// impl Entity for MyStruct {
+    /// #     type Key = u32;
+    /// #     fn store_name() -> &'static str { "my_struct" }
+    /// #     fn get_key(&self) -> &Self::Key { &self.key }
+    /// #     fn set_key(&mut self, key : &Self::Key) { self.key = key.clone(); }
+    ///     fn validate(&self) -> Result<()> {
+    ///         if self.prop1.is_empty() {
+    ///             return Err(Error::new(ErrorKind::IntegrityError, String::from("prop1 cannot be empty")));
+    ///         }
+    ///         Ok(())
+    ///     }
+    /// }
+    /// ```
+    fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Override this function to release external resources (files, connections...) held by an entity before it is
+    /// removed with [`remove`](entity/trait.Entity.html#method.remove). It is called before any relation check, and
+    /// nothing is removed if it returns an error.
+    ///
+    /// ⚠ Unlike `pre_remove_hook`, this is only called on the removed entity itself, not on the related entities
+    /// removed along with it, nor on stored entries that cannot be deserialized.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use reindeer::{Db, Entity, Result, Serialize, Deserialize};
+    /// # #[derive(Serialize, Deserialize)]
+    /// # struct MyStruct { key : u32, file_path : String }
+    /// impl Entity for MyStruct {
+    /// #     type Key = u32;
+    /// #     fn store_name() -> &'static str { "my_struct" }
+    /// #     fn get_key(&self) -> &Self::Key { &self.key }
+    /// #     fn set_key(&mut self, key : &Self::Key) { self.key = key.clone(); }
+    ///     fn on_before_remove(&self, _db: &Db) -> Result<()> {
+    ///         std::fs::remove_file(&self.file_path)?;
+    ///         Ok(())
+    ///     }
+    /// }
+    /// ```
+    fn on_before_remove(&self, _db: &Db) -> Result<()> {
+        Ok(())
+    }
+
+    /// Override this function to migrate the store of this entity, when it is registered in a database where it was
+    /// last registered with `old_version`, lower than [`SCHEMA_VERSION`](entity/trait.Entity.html#associatedconstant.SCHEMA_VERSION).
+    /// If it returns an error, registration fails, and the migration is attempted again on the next registration.
+    ///
+    /// If the store holds entities but no version was recorded for it, as in databases created before store versions
+    /// were tracked, this is called with `old_version` set to `0`.
+    ///
+    /// With the derive macro, use `#[entity(version = 2, migrate_from = "my_migration_fn")]`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # use reindeer::{Db, Entity, Result, Serialize, Deserialize};
+    /// # #[derive(Serialize, Deserialize, Entity)]
+    /// # #[entity(name = "my_struct", version = 1)]
+    /// # struct MyStructV1 { id : u32 }
+    /// # #[derive(Serialize, Deserialize, Entity)]
+    /// # #[entity(name = "my_struct", version = 2, migrate_from = "migrate_from")]
+    /// # struct MyStruct { id : u32, email : Option<String> }
+    /// fn migrate_from(old_version: u32, db: &Db) -> Result<()> {
+    ///     if old_version < 2 {
+    ///         MyStruct::migrate_field(|old: MyStructV1| MyStruct { id : old.id, email : None }, db)?;
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    fn migrate_from(_old_version: u32, _db: &Db) -> Result<()> {
+        Ok(())
+    }
 
     #[doc(hidden)]
     fn can_be_removed(key: &[u8], db: &Db) -> Result<()> {
         Relation::can_be_deleted(
             Self::store_name(),
             key,
-            &Vec::new(),
+            &mut HashSet::new(),
             &mut EntityRelations::default(),
             db,
-        )?;
-        Ok(())
+        )
     }
 
     /// Removes an entity given its key.
@@ -505,31 +1675,90 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///
     /// ### Example
     /// ```rust
-    /// MyStruct::remove(&3, &db);
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// MyStruct::remove(&3, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn remove(key: &Self::Key, db: &Db) -> Result<()> {
         Self::remove_from_u8_array(&key.as_bytes(), db)
     }
 
+    /// Removes an entity given its key like [`remove`](entity/trait.Entity.html#method.remove), and appends an
+    /// [`AuditEntry`](struct.AuditEntry.html) recording that `actor` removed it to the audit log of this store.
+    ///
+    /// Entities removed as a consequence (`DeletionBehaviour::Cascade`) are not recorded.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// MyStruct::remove_with_audit(&3, "alice", &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn remove_with_audit(key: &Self::Key, actor: &str, db: &Db) -> Result<()> {
+        Self::remove(key, db)?;
+        AuditLog::append(
+            Self::store_name(),
+            &key.as_bytes(),
+            actor,
+            AuditOperation::Remove,
+            db,
+        )
+    }
+
+    /// Returns the audit log of an entity given its key, in chronological order : every save or removal made
+    /// with [`save_with_audit`](entity/trait.Entity.html#method.save_with_audit) or
+    /// [`remove_with_audit`](entity/trait.Entity.html#method.remove_with_audit).
+    ///
+    /// The audit log is kept after the entity is removed.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// for entry in MyStruct::get_audit_log(&3, &db)? {
+    ///     println!("{} : {:?} by {}", entry.timestamp, entry.operation, entry.actor);
+    /// }
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_audit_log(key: &Self::Key, db: &Db) -> Result<Vec<AuditEntry>> {
+        AuditLog::get(Self::store_name(), &key.as_bytes(), db)
+    }
+
     #[doc(hidden)]
     fn remove_from_u8_array(key: &[u8], db: &Db) -> Result<()> {
-        Self::pre_remove(key, db)?;
-        if Self::use_pre_remove_hook() {
-            Self::pre_remove_hook(
-                &Self::get_from_u8_array(key, db)?.ok_or(Error::new(
-                    crate::ErrorKind::IntegrityError,
-                    "Entry was not found".to_string(),
-                ))?,
-                db,
-            )?;
-        }
-        Self::get_tree(db)?.remove(key)?;
-        Ok(())
+        trace_span!("remove", Self::store_name(), key);
+        let mut removal = Removal::default();
+        removal.add::<Self>(key);
+        removal.commit(db)
     }
 
-    #[doc(hidden)]
+    /// Removes every entity with a key starting with `prefix`, like [`remove`](entity/trait.Entity.html#method.remove)
+    /// would for each of them : relation constraints are applied (`Cascade`, `SetNull`, `BreakLink`) and
+    /// [`on_before_remove`](entity/trait.Entity.html#method.on_before_remove) is called.
+    ///
+    /// ⚠ If any of these entities cannot be removed due to integrity checks (`DeletionBehaviour::Error` found in the
+    /// relation hierarchy), this results in an error and no entity is removed. Relations between these entities are
+    /// ignored, and all of them are removed in a single transaction.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// MyStruct::remove_prefixed(String::from("user_"), &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
     fn remove_prefixed(prefix: impl AsBytes, db: &Db) -> Result<()> {
-        Self::remove_prefixed_in_tree(Self::store_name(), &prefix.as_bytes(), db)
+        let keys = Self::get_tree(db)?
+            .scan_prefix(prefix.as_bytes())
+            .keys()
+            .collect::<std::result::Result<Vec<IVec>, sled::Error>>()?;
+        let mut removal = Removal::default();
+        for key in &keys {
+            removal.add::<Self>(key);
+        }
+        removal.commit(db)
     }
 
     #[doc(hidden)]
@@ -538,58 +1767,216 @@ pub trait Entity: Serialize + DeserializeOwned {
             Ok(u32::from_be_bytes([bytes[0],bytes[1],bytes[2],bytes[3]]))
         }
         else {
-            Err(Error::new(crate::error::ErrorKind::IOError, "Cannot convert byte slice smaller than 4 bytes to integer.".to_string()))
+            Err(Error::new(crate::error::ErrorKind::SerializationError, "Cannot convert byte slice smaller than 4 bytes to integer.".to_string()))
         }
     }
 
-    #[doc(hidden)]
-    fn remove_prefixed_in_tree(tree_name: &str, prefix: &[u8], db: &Db) -> Result<()> {
-        let tree = db.open_tree(tree_name)?;
-        let mut batch = Batch::default();
-        tree.scan_prefix(prefix).for_each(|elem| {
-            if let Ok((key, _)) = elem {
-                if Self::pre_remove(&key, db).is_ok() {
-                    batch.remove(key)
+    /// Removes every entry of a store, given a condition in the form of a function returning a boolean,
+    /// and returns the removed entities along with the ones that could not be removed and the reason why.
+    ///
+    /// Relation constraints of all matching entities are checked before any of them is removed, so that relations
+    /// between them never prevent their removal : entities that cannot be removed due to integrity checks
+    /// (`DeletionBehaviour::Error` found in the relation hierarchy) are not removed, and are returned with the
+    /// corresponding error. All the other ones are then removed in a single transaction : if it fails, none of them is
+    /// removed and the error is returned.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let (removed, blocked) = MyStruct::filter_remove(|m_struct| m_struct.prop2 > 20, &db)?;
+    /// for (m_struct, error) in blocked {
+    ///     eprintln!("{:?} could not be removed : {}", m_struct.get_key(), error);
+    /// }
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    #[allow(clippy::type_complexity)]
+    fn filter_remove<F: Fn(&Self) -> bool>(f: F, db: &Db) -> Result<(Vec<Self>, Vec<(Self, Error)>)> {
+        let mut removable = Self::get_with_filter(f, db)?;
+        let mut blocked = Vec::new();
+        // Blocked entities are not removed, so the remaining ones are checked again if any of them was related to them
+        loop {
+            let mut removed = removable
+                .iter()
+                .map(|entity| (String::from(Self::store_name()), entity.get_key().as_bytes()))
+                .collect::<HashSet<(String, Vec<u8>)>>();
+            let blocked_count = blocked.len();
+            let mut checked = Vec::new();
+            for entity in removable {
+                match Relation::can_be_deleted(
+                    Self::store_name(),
+                    &entity.get_key().as_bytes(),
+                    &mut removed,
+                    &mut EntityRelations::default(),
+                    db,
+                ) {
+                    Ok(()) => checked.push(entity),
+                    Err(e) => blocked.push((entity, e)),
                 }
             }
-        });
-        tree.apply_batch(batch)?;
-        Ok(())
+            removable = checked;
+            if blocked.len() == blocked_count {
+                break;
+            }
+        }
+        let mut removal = Removal::default();
+        for entity in &removable {
+            removal.add::<Self>(&entity.get_key().as_bytes());
+        }
+        removal.commit(db)?;
+        Ok((removable, blocked))
     }
 
-    /// Removes every entry of a store, given a condition in the form of a function returning a boolean
-    /// and returns the array of removed elements.
-    /// ⚠ If removal is impossible due to integrity checks
-    /// (`DeletionBehaviour::Error` found in the relation hierarchy), they won't be deleted and won't be
-    /// included in results.
+    /// Marks an entity as deleted, without removing it : it is not returned by
+    /// [`get_active`](entity/trait.Entity.html#method.get_active) anymore, but can still be retrieved with any other method,
+    /// and be brought back with [`restore`](entity/trait.Entity.html#method.restore).
+    ///
+    /// If the key does not exist, this will result in an error.
     ///
     /// ### Example
     /// ```rust
-    /// MyStruct::remove(&3, &db);
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct { key : 3, prop1 : String::from("Hello"), prop2 : 554 }.save(&db)?;
+    /// MyStruct::soft_remove(&3, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
-    fn filter_remove<F: Fn(&Self) -> bool>(f: F, db: &Db) -> Result<Vec<Self>> {
-        let mut res = Self::get_with_filter(f, db)?;
-        let mut to_remove_from_result = Vec::new();
-        for (index, entity) in res.iter().enumerate() {
-            if Self::remove(entity.get_key(), db).is_err() {
-                to_remove_from_result.push(index)
-            };
+    fn soft_remove(key: &Self::Key, db: &Db) -> Result<()> {
+        if !Self::exists(key, db)? {
+            return Err(entity_not_found(Self::store_name(), &key.as_bytes()));
         }
-        for index in to_remove_from_result {
-            res.remove(index);
-        }
-        Ok(res)
+        SoftDelete::mark(Self::store_name(), &key.as_bytes(), db)
+    }
+
+    /// Clears the deletion mark set with [`soft_remove`](entity/trait.Entity.html#method.soft_remove) on an entity.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct { key : 3, prop1 : String::from("Hello"), prop2 : 554 }.save(&db)?;
+    /// # MyStruct::soft_remove(&3, &db)?;
+    /// MyStruct::restore(&3, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn restore(key: &Self::Key, db: &Db) -> Result<()> {
+        SoftDelete::clear(Self::store_name(), &key.as_bytes(), db)
+    }
+
+    /// Returns the time (in seconds since the Unix epoch) at which an entity was marked as deleted with
+    /// [`soft_remove`](entity/trait.Entity.html#method.soft_remove), or `None` if it is not.
+    fn get_deleted_at(key: &Self::Key, db: &Db) -> Result<Option<u64>> {
+        SoftDelete::get_deleted_at(Self::store_name(), &key.as_bytes(), db)
     }
 
     /// Checks if an entity exists in a given store, without fetching it.
     /// ### Example
     /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
     /// if MyStruct::exists(&3, &db)? {
     ///     /* */
     /// }
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn exists(key: &Self::Key, db: &Db) -> Result<bool> {
-        Ok(Self::get_tree(db)?.contains_key(&key.as_bytes())?)
+        Ok(Self::get_tree(db)?.contains_key(key.as_bytes())?)
+    }
+
+    /// Checks if entities exist for all the given keys, without fetching them.
+    ///
+    /// Stops at the first missing key. Returns `true` if `keys` is empty.
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// if MyStruct::contains_all(&[3, 4, 5], &db)? {
+    ///     /* */
+    /// }
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn contains_all(keys: &[Self::Key], db: &Db) -> Result<bool> {
+        let tree = Self::get_tree(db)?;
+        for key in keys {
+            if !tree.contains_key(key.as_bytes())? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns the keys of `keys` for which no entity exists in the store, in the same order, without fetching any entity.
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let missing = MyStruct::get_missing(&[3, 4, 5], &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_missing(keys: &[Self::Key], db: &Db) -> Result<Vec<Self::Key>> {
+        let tree = Self::get_tree(db)?;
+        let mut missing = Vec::new();
+        for key in keys {
+            if !tree.contains_key(key.as_bytes())? {
+                missing.push(key.clone());
+            }
+        }
+        Ok(missing)
+    }
+
+    /// Checks if an entity exists for at least one of the given keys, without fetching it.
+    ///
+    /// Stops at the first existing key. Returns `false` if `keys` is empty.
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// if MyStruct::contains_any(&[3, 4, 5], &db)? {
+    ///     /* */
+    /// }
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn contains_any(keys: &[Self::Key], db: &Db) -> Result<bool> {
+        let tree = Self::get_tree(db)?;
+        for key in keys {
+            if tree.contains_key(key.as_bytes())? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Watches an entity given its key, and returns a subscriber yielding an
+    /// [`EntityEvent`](enum.EntityEvent.html) each time it is saved, updated or removed.
+    ///
+    /// Only changes made after this call are notified.
+    ///
+    /// ### Example
+    /// ```rust,no_run
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # use reindeer::EntityEvent;
+    /// for event in MyStruct::watch(&3, &db)? {
+    ///     match event? {
+    ///         EntityEvent::Set(my_struct) => println!("{:?}", my_struct),
+    ///         EntityEvent::Remove(_) => println!("removed"),
+    ///     }
+    /// }
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn watch(key: &Self::Key, db: &Db) -> Result<EntitySubscriber<Self>> {
+        let key = key.as_bytes();
+        let subscriber = Self::get_tree(db)?.watch_prefix(&key);
+        Ok(EntitySubscriber::new(subscriber, Some(key)))
+    }
+
+    /// Watches all the entities of this store with a key starting with `prefix`.
+    /// An empty `prefix` watches the whole store.
+    ///
+    /// See [`watch`](entity/trait.Entity.html#method.watch).
+    fn watch_all_prefix(prefix: impl AsBytes, db: &Db) -> Result<EntitySubscriber<Self>> {
+        let subscriber = Self::get_tree(db)?.watch_prefix(prefix.as_bytes());
+        Ok(EntitySubscriber::new(subscriber, None))
     }
 
     /// Exports the entire store for this entity as a JSON file.
@@ -604,82 +1991,606 @@ pub trait Entity: Serialize + DeserializeOwned {
         Ok(())
     }
 
-    /// Imports the entire store for this entity as a JSON file.
-    /// Any existing entities with matching keys will be overridden.
+    /// Exports the entities of this store matching a condition materialized as a function returning a boolean
+    /// as a JSON file, and returns the number of exported entities.
+    ///
+    /// The resulting file has the same format as the one produced by [`export_json`](entity/trait.Entity.html#method.export_json),
+    /// and can be imported with [`import_json`](entity/trait.Entity.html#method.import_json).
+    ///
+    /// ### Example
+    /// ```rust,no_run
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # use std::fs::File;
+    /// let count = MyStruct::export_json_filtered(|m_struct| m_struct.prop2 > 20, File::create("export.json")?, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn export_json_filtered<F: Fn(&Self) -> bool>(f: F, file: File, db: &Db) -> Result<usize> {
+        let entities = Self::get_with_filter(f, db)?;
+        let count = entities.len();
+        serde_json::to_writer(file, &JsonWrapper::from(entities, db)?)?;
+        Ok(count)
+    }
+
+    /// Imports the entire store for this entity as a JSON file.
+    /// Any existing entities with matching keys will be overridden.
+    ///
+    /// This can be used for restoring purposes.
+    ///
+    /// ⚠ If the structure of the JSON file does not match the Structs used in the app, this will fail with an error.
+    ///
+    /// Use [`import_json_reader`](entity/trait.Entity.html#method.import_json_reader) to get the number of imported entities.
+    fn import_json(f: File, db: &Db) -> Result<()> {
+        Self::import_json_reader(f, db)?;
+        Ok(())
+    }
+
+    /// Imports a JSON file produced by [`export_json`](entity/trait.Entity.html#method.export_json), handling entities
+    /// whose key already exists in the store according to `policy`, and returns a summary of the import.
+    ///
+    /// ### Example
+    /// ```rust,no_run
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # use std::fs::File;
+    /// # use reindeer::ConflictPolicy;
+    /// let report = MyStruct::import_json_with_policy(File::open("export.json")?, ConflictPolicy::Skip, &db)?;
+    /// println!("{} new entities, {} skipped", report.inserted, report.skipped);
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn import_json_with_policy(f: File, policy: ConflictPolicy, db: &Db) -> Result<ImportReport> {
+        let wrapper: JsonWrapper<Self> = serde_json::from_reader(BufReader::new(f))?;
+        wrapper.save_with_policy(policy, db)
+    }
+
+    /// Exports the entire store for this entity as JSON to any writer (a file, a socket, an in-memory buffer...).
+    ///
+    /// Entities are written one by one as they are read from the database, instead of being loaded all at once.
+    /// The output has the same format as the one produced by [`export_json`](entity/trait.Entity.html#method.export_json).
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let mut buffer = Vec::new();
+    /// MyStruct::export_json_writer(&mut buffer, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn export_json_writer<W: Write>(w: W, db: &Db) -> Result<()> {
+        let mut w = BufWriter::new(w);
+        w.write_all(b"[")?;
+        for (index, elem) in Self::get_tree(db)?.iter().enumerate() {
+            if index > 0 {
+                w.write_all(b",")?;
+            }
+            let entity = Self::try_from_ivec(elem?.1)?;
+            serde_json::to_writer(&mut w, &JsonWrapper::entry(entity, db)?)?;
+        }
+        w.write_all(b"]")?;
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Imports JSON produced by [`export_json`](entity/trait.Entity.html#method.export_json) or
+    /// [`export_json_writer`](entity/trait.Entity.html#method.export_json_writer) from any reader,
+    /// and returns the number of imported entities.
+    /// Any existing entities with matching keys will be overridden.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # let mut buffer = Vec::new();
+    /// # MyStruct::export_json_writer(&mut buffer, &db)?;
+    /// let count = MyStruct::import_json_reader(buffer.as_slice(), &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn import_json_reader<R: Read>(r: R, db: &Db) -> Result<usize> {
+        let wrapper: JsonWrapper<Self> = serde_json::from_reader(BufReader::new(r))?;
+        wrapper.save(db)
+    }
+
+    /// Exports the entire store for this entity as NDJSON (one JSON entity per line) to any writer,
+    /// and returns the number of exported entities.
+    ///
+    /// Unlike [`export_json_writer`](entity/trait.Entity.html#method.export_json_writer), only the entities are exported, without their relations,
+    /// so that the output can directly be consumed by other tools.
+    ///
+    /// ### Example
+    /// ```rust,no_run
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # use std::fs::File;
+    /// let count = MyStruct::export_ndjson(File::create("export.ndjson")?, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn export_ndjson<W: Write>(w: W, db: &Db) -> Result<usize> {
+        let mut w = BufWriter::new(w);
+        let mut count = 0;
+        for elem in Self::get_tree(db)?.iter() {
+            serde_json::to_writer(&mut w, &Self::try_from_ivec(elem?.1)?)?;
+            w.write_all(b"\n")?;
+            count += 1;
+        }
+        w.flush()?;
+        Ok(count)
+    }
+
+    /// Imports NDJSON (one JSON entity per line) from any reader, saves each entity and returns the number of imported entities.
+    /// Empty lines are ignored, and any existing entities with matching keys will be overridden.
+    ///
+    /// ### Example
+    /// ```rust,no_run
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # use std::fs::File;
+    /// let count = MyStruct::import_ndjson(File::open("export.ndjson")?, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn import_ndjson<R: Read>(r: R, db: &Db) -> Result<usize> {
+        let mut count = 0;
+        for line in BufReader::new(r).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            serde_json::from_str::<Self>(&line)?.save(db)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Exports the entire store for this entity as CSV to any writer, with field names as headers,
+    /// and returns the number of exported entities.
+    ///
+    /// Only available with the `csv` feature.
+    ///
+    /// ⚠ This only works for flat entities whose fields are all scalar types, and will fail with an error otherwise.
+    ///
+    /// ### Example
+    /// ```rust,no_run
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # use std::fs::File;
+    /// let count = MyStruct::export_csv(File::create("export.csv")?, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    #[cfg(feature = "csv")]
+    fn export_csv<W: Write>(w: W, db: &Db) -> Result<usize> {
+        let mut writer = csv::Writer::from_writer(w);
+        let mut count = 0;
+        for elem in Self::get_tree(db)?.iter() {
+            writer.serialize(Self::try_from_ivec(elem?.1)?)?;
+            count += 1;
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+
+    /// Imports CSV with field names as headers from any reader, saves each entity and returns the number of imported entities.
+    /// Any existing entities with matching keys will be overridden.
+    ///
+    /// Only available with the `csv` feature.
+    ///
+    /// ### Example
+    /// ```rust,no_run
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # use std::fs::File;
+    /// let count = MyStruct::import_csv(File::open("export.csv")?, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    #[cfg(feature = "csv")]
+    fn import_csv<R: Read>(r: R, db: &Db) -> Result<usize> {
+        let mut count = 0;
+        for entity in csv::Reader::from_reader(r).deserialize::<Self>() {
+            entity?.save(db)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Creates a free relation between this entity and another one.
+    ///
+    /// As this creates a two way binding, `DeletionBehaviour` in both ways must be provided :
+    ///  - `self_to_other` defines what happens to `other` if `self` gets removed from the database
+    ///  - `other_to_self` defines what happens to `self` if `other` gets removed from the database
+    ///  - `name` is an optionnal name for the relation.
+    ///
+    /// Creating a relation that already exists with the same name does nothing : the returned
+    /// [`RelationCreateResult`](struct.RelationCreateResult.html) tells if the link was actually created.
+    fn create_relation<E: Entity>(
+        &self,
+        other: &E,
+        self_to_other: DeletionBehaviour,
+        other_to_self: DeletionBehaviour,
+        name: Option<&str>,
+        db: &Db,
+    ) -> Result<RelationCreateResult> {
+        trace_span!(
+            "create_relation",
+            Self::store_name(),
+            self.get_key().as_bytes(),
+            E::store_name()
+        );
+        Relation::create(self, other, self_to_other, other_to_self, name, db)
+    }
+
+    /// Creates a free relation between this entity and another one, and attaches `payload` to it.
+    ///
+    /// The payload can be any `serde`-serializable value (a weight, a timestamp, a label...) and is shared by both
+    /// ends of the relation. If the relation already exists, its payload is replaced.
+    /// Both ends of the relation and their payload are written in a single transaction, as a
+    /// [`RelationWithPayload`](struct.RelationWithPayload.html), and the payload is removed along with the relation.
+    ///
+    /// See [`create_relation`](entity/trait.Entity.html#method.create_relation) for the meaning of the other parameters.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # User { id : 9, name : String::from("Nancy") }.save(&db)?;
+    /// # Book { id : 4, title : String::from("Dune") }.save(&db)?;
+    /// let user = User::get(&9,&db)?.unwrap();
+    /// let book = Book::get(&4,&db)?.unwrap();
+    /// user.create_relation_with_payload(&book,&Rating { stars : 4 },DeletionBehaviour::BreakLink,DeletionBehaviour::BreakLink,None,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn create_relation_with_payload<E: Entity, P: Serialize>(
+        &self,
+        other: &E,
+        payload: &P,
+        self_to_other: DeletionBehaviour,
+        other_to_self: DeletionBehaviour,
+        name: Option<&str>,
+        db: &Db,
+    ) -> Result<RelationCreateResult> {
+        Relation::create_with_payload(self, other, payload, self_to_other, other_to_self, name, db)
+    }
+
+    /// Creates free relations between this entity and several others at once.
+    ///
+    /// All links are written in a single operation : either all of them are created, or none is.
+    /// Links that already exist (with the same name) are skipped, unless `fail_on_duplicate` is `true`,
+    /// in which case this will result in an error and no link will be created.
+    ///
+    /// See [`create_relation`](entity/trait.Entity.html#method.create_relation) for the meaning of the other parameters.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # Post { id : 9, created_at : 0 }.save(&db)?;
+    /// # for id in [1, 4, 7] { Tag { id, name : String::new() }.save(&db)?; }
+    /// let post = Post::get(&9,&db)?.unwrap();
+    /// let tags = Tag::get_each(&[1,4,7],&db);
+    /// post.create_relation_batch(&tags,DeletionBehaviour::BreakLink,DeletionBehaviour::BreakLink,Some("tags"),false,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    fn create_relation_batch<E: Entity>(
+        &self,
+        others: &[E],
+        self_to_other: DeletionBehaviour,
+        other_to_self: DeletionBehaviour,
+        name: Option<&str>,
+        fail_on_duplicate: bool,
+        db: &Db,
+    ) -> Result<()> {
+        Relation::create_batch(
+            self,
+            others,
+            self_to_other,
+            other_to_self,
+            name,
+            fail_on_duplicate,
+            db,
+        )
+    }
+
+    /// Breaks an existing link between two entities.
+    ///
+    /// This will remove the relation in both ways.
+    fn remove_relation<E: Entity>(&self, other: &E, db: &Db) -> Result<()> {
+        trace_span!(
+            "remove_relation",
+            Self::store_name(),
+            self.get_key().as_bytes(),
+            E::store_name()
+        );
+        Relation::remove(self, other, db)
+    }
+
+    /// Breaks an existing link between two entities, and removes `other` from its store.
+    ///
+    /// `other` is removed with [`remove`](entity/trait.Entity.html#method.remove), so its own deletion behaviours apply :
+    /// its children and related entities are removed, unlinked or block the removal as usual.
+    ///
+    /// ⚠ If `other` cannot be removed, the error is returned but the link is not restored.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # let order = Order { id : 9, quantity : 1 };
+    /// # order.save(&db)?;
+    /// # let draft = Invoice { id : 12, amount : 0 };
+    /// # draft.save(&db)?;
+    /// # order.create_relation(&draft, DeletionBehaviour::BreakLink, DeletionBehaviour::BreakLink, None, &db)?;
+    /// let order = Order::get(&9,&db)?.unwrap();
+    /// let draft = Invoice::get(&12,&db)?.unwrap();
+    /// order.remove_relation_cascade(&draft,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn remove_relation_cascade<E: Entity>(&self, other: &E, db: &Db) -> Result<()> {
+        trace_span!(
+            "remove_relation_cascade",
+            Self::store_name(),
+            self.get_key().as_bytes(),
+            E::store_name()
+        );
+        Relation::remove(self, other, db)?;
+        E::remove(other.get_key(), db)
+    }
+
+    /// Breaks an existing link between two entities with a specific name
+    ///
+    /// This will remove the relation in both ways.
+    fn remove_relation_with_name<E: Entity>(&self, other: &E, name: &str, db: &Db) -> Result<()> {
+        Relation::remove_with_name(self, other, name, db)
+    }
+
+    #[doc(hidden)]
+    fn remove_relation_with_key<E: Entity>(&self, other: &[u8], db: &Db) -> Result<()> {
+        Relation::remove_by_keys::<Self, E>(&self.get_key().as_bytes(), other, db)
+    }
+
+    /// Gets all entities related to this one in another store.
+    ///
+    /// Related entities are sorted by key bytes, whatever the order the relations were created in.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let related_struct2s = m_struct_1.get_related::<MyStruct2>(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_related<E: Entity>(&self, db: &Db) -> Result<Vec<E>> {
+        trace_span!(
+            "get_related",
+            Self::store_name(),
+            self.get_key().as_bytes(),
+            E::store_name()
+        );
+        Relation::get::<Self, E>(self, db)
+    }
+
+    /// Gets the keys, as bytes, of all entities related to this one in another store, without reading them.
+    /// Keys are sorted by bytes.
+    ///
+    /// Entities can then be read only when needed with [`get_each_u8`](entity/trait.Entity.html#method.get_each_u8).
+    ///
+    /// ### Exemple
+    /// ```rust,no_run
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let keys = m_struct_1.get_related_keys::<MyStruct2>(&db)?;
+    /// let m_struct_2s = MyStruct2::get_each_u8(&keys[..10],&db);
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_related_keys<E: Entity>(&self, db: &Db) -> Result<Vec<Vec<u8>>> {
+        Relation::get_related_keys::<Self, E>(self, db)
+    }
+
+    /// Gets the keys of all entities related to this one in another store, without reading them.
+    ///
+    /// This requires the key type of `E` to implement [`FromBytes`](entity/trait.FromBytes.html).
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let ids : Vec<u32> = m_struct_1.get_related_ids_only::<MyStruct2>(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_related_ids_only<E: Entity>(&self, db: &Db) -> Result<Vec<E::Key>>
+    where
+        E::Key: FromBytes,
+    {
+        Relation::get_related_keys::<Self, E>(self, db)?
+            .iter()
+            .map(|key| E::Key::from_bytes(key))
+            .collect()
+    }
+
+    /// Gets lazy references to all entities related to this one in another store.
+    ///
+    /// Unlike [`get_related`](entity/trait.Entity.html#method.get_related), related entities are only read and
+    /// deserialized when their [`EntityRef`](struct.EntityRef.html) is accessed.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let m_struct_2_refs = m_struct_1.get_related_refs::<MyStruct2>(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_related_refs<E: Entity>(&self, db: &Db) -> Result<Vec<EntityRef<E>>> {
+        Relation::get_refs::<Self, E>(self, db)
+    }
+
+    /// Gets all entities related to this one in another store through a relation created with
+    /// [`create_relation_with_payload`](entity/trait.Entity.html#method.create_relation_with_payload),
+    /// along with the payload of each relation.
     ///
-    /// This can be used for restoring purposes.
+    /// Relations created without a payload are not returned.
     ///
-    /// ⚠ If the structure of the JSON file does not match the Structs used in the app, this will fail with an error.
-    fn import_json(f: File, db: &Db) -> Result<()> {
-        let wrapper: JsonWrapper<Self> = serde_json::from_reader(f)?;
-        wrapper.save(db)?;
-        Ok(())
+    /// ⚠ `P` must be the type that was used when creating the relations, or this will fail with an error.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # User { id : 9, name : String::from("Nancy") }.save(&db)?;
+    /// let user = User::get(&9,&db)?.unwrap();
+    /// let rated_books = user.get_related_with_payload::<Book,Rating>(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_related_with_payload<E: Entity, P: DeserializeOwned>(
+        &self,
+        db: &Db,
+    ) -> Result<Vec<(E, P)>> {
+        Relation::get_with_payload::<Self, E, P>(self, db)
     }
 
-    /// Creates a free relation between this entity and another one.
+    /// Gets all entities related to this one in another store matching a condition materialized
+    /// as a function returning a boolean.
     ///
-    /// As this creates a two way binding, `DeletionBehaviour` in both ways must be provided :
-    ///  - `self_to_other` defines what happens to `other` if `self` gets removed from the database
-    ///  - `other_to_self` defines what happens to `self` if `other` gets removed from the database
-    ///  - `name` is an optionnal name for the relation.
-    fn create_relation<E: Entity>(
+    /// Related entities that do not match the condition are discarded as soon as they are loaded.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let related_struct2s = m_struct_1.get_related_with_filter(|m_struct_2 : &MyStruct2| m_struct_2.prop9 > 20,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_related_with_filter<E: Entity, F: Fn(&E) -> bool>(
         &self,
-        other: &E,
-        self_to_other: DeletionBehaviour,
-        other_to_self: DeletionBehaviour,
-        name: Option<&str>,
+        f: F,
         db: &Db,
-    ) -> Result<()> {
-        Relation::create(self, other, self_to_other, other_to_self, name, db)
+    ) -> Result<Vec<E>> {
+        Relation::get_with_filter::<Self, E, F>(self, f, db)
     }
 
-    /// Breaks an existing link between two entities.
+    /// Gets a page of `page_size` entities related to this one in another store, `page` starting at `0`.
+    /// Only the entities of the requested page are loaded, and pages past the last one are empty.
     ///
-    /// This will remove the relation in both ways.
-    fn remove_relation<E: Entity>(&self, other: &E, db: &Db) -> Result<()> {
-        Relation::remove(self, other, db)
+    /// Related entities are paginated in key bytes order, so that pages are stable while the relations are not modified.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let second_page = m_struct_1.get_related_paginated::<MyStruct2>(1,20,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_related_paginated<E: Entity>(
+        &self,
+        page: usize,
+        page_size: usize,
+        db: &Db,
+    ) -> Result<Vec<E>> {
+        Relation::get_paginated::<Self, E>(self, page, page_size, db)
     }
 
-    /// Breaks an existing link between two entities with a specific name
+    /// Counts the entities related to this one in another store, without loading them.
     ///
-    /// This will remove the relation in both ways.
-    fn remove_relation_with_name<E: Entity>(&self, other: &E, name: &str, db: &Db) -> Result<()> {
-        Relation::remove_with_name(self, other, name, db)
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let count = m_struct_1.get_related_count::<MyStruct2>(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_related_count<E: Entity>(&self, db: &Db) -> Result<usize> {
+        Relation::count::<Self, E>(self, db)
     }
 
-    #[doc(hidden)]
-    fn remove_relation_with_key<E: Entity>(&self, other: &[u8], db: &Db) -> Result<()> {
-        Relation::remove_by_keys::<Self, E>(&self.get_key().as_bytes(), other, db)
+    /// Counts the entities of another store that have a link pointing to this one, without loading them.
+    ///
+    /// As free relations are two-way links, this is usually the same as
+    /// [`get_related_count`](entity/trait.Entity.html#method.get_related_count), but it is computed
+    /// from the other side of the relation and can be used to check its consistency.
+    ///
+    /// ⚠ This will iterate over every relation descriptor of the other store.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let count = m_struct_1.get_referrer_count::<MyStruct2>(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_referrer_count<E: Entity>(&self, db: &Db) -> Result<usize> {
+        Relation::count_referrers::<Self, E>(self, db)
     }
 
-    /// Gets all entities related to this one in another store.
+    /// Finds the free relations from this store to the store of `E` that point to an entity that does not exist anymore,
+    /// and returns them as `(self_key, other_key)` pairs of keys as bytes.
+    ///
+    /// ⚠ This will iterate over every relation descriptor of this store.
     ///
     /// ### Exemple
     /// ```rust
-    /// let m_struct_1 = MyStruct1::get(&9,&db)?;
-    /// let related_struct2s = m_struct_1.get_related::<MyStruct2>(&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let broken_links = MyStruct1::find_orphan_relations::<MyStruct2>(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
-    fn get_related<E: Entity>(&self, db: &Db) -> Result<Vec<E>> {
-        Relation::get::<Self, E>(self, db)
+    fn find_orphan_relations<E: Entity>(db: &Db) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        Relation::find_orphans::<Self, E>(db)
     }
 
-    /// Gets all the entities related to this one in another store with a given relation name
+    /// Removes the free relations from this store to the store of `E` that point to an entity that does not exist anymore
+    /// (see [`find_orphan_relations`](entity/trait.Entity.html#method.find_orphan_relations)), and returns the number of removed relations.
     ///
     /// ### Exemple
     /// ```rust
-    /// let m_struct_1 = MyStruct1::get(&9,&db)?;
-    /// let related_struct2s = m_struct_1.get_related_with_name::<MyStruct2>("collection",&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let repaired_count = MyStruct1::repair_orphan_relations::<MyStruct2>(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn repair_orphan_relations<E: Entity>(db: &Db) -> Result<usize> {
+        Relation::repair_orphans::<Self, E>(db)
+    }
+
+    /// Gets all the entities related to this one in another store with a given relation name, sorted by key bytes
     ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let related_struct2s = m_struct_1.get_related_with_name::<MyStruct2>("collection",&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
     fn get_related_with_name<E: Entity>(&self, name: &str, db: &Db) -> Result<Vec<E>> {
         Relation::get_with_name::<Self, E>(self, name, db)
     }
 
-    /// Gets the first entity related to this one in another store.
+    /// Gets the first entity related to this one in another store, in key bytes order like [`get_related`](entity/trait.Entity.html#method.get_related).
+    ///
+    /// Returns `None` if there is no such relation, and fails with an
+    /// [`ErrorKind::EntityNotFound`](enum.ErrorKind.html#variant.EntityNotFound) error if the related entity does not exist anymore.
     ///
     /// ### Exemple
     /// ```rust
-    /// let m_struct_1 = MyStruct1::get(&9,&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
     /// let m_struct_2 = m_struct_1.get_single_related::<MyStruct2>(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn get_single_related<E: Entity>(&self, db: &Db) -> Result<Option<E>> {
         Relation::get_one::<Self, E>(self, db)
@@ -687,18 +2598,26 @@ pub trait Entity: Serialize + DeserializeOwned {
 
     /// Gets the first entity related to this one in another store with a given relation name
     ///
+    /// See [`get_single_related`](entity/trait.Entity.html#method.get_single_related).
+    ///
     /// ### Exemple
     /// ```rust
-    /// let m_struct_1 = MyStruct1::get(&9,&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
     /// let m_struct_2 = m_struct_1.get_single_related_with_name::<MyStruct2>("main_book",&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn get_single_related_with_name<E: Entity>(&self, name: &str, db: &Db) -> Result<Option<E>> {
         Relation::get_one_with_name::<Self, E>(self, name, db)
     }
 
     /// Checks if a free relation exists between this entity and another.
+    ///
+    /// This only reads the relation descriptor of this entity, without loading any related entity.
     fn is_related_to<E: Entity>(&self, other: &E, db: &Db) -> Result<bool> {
-        Relation::are_related(self, other, db)
+        Relation::exists(self, other, db)
     }
 
     /// Checks if a named relation exists between this entity and another, with a specific relation name
@@ -717,44 +2636,182 @@ pub trait Entity: Serialize + DeserializeOwned {
     }
 
     /// Saves `sibling` in its own store after having changed its key to match `self`
-    /// This is a convenience method.
+    /// This is a convenience method. It also restores the link between `self` and `sibling` if it was broken with
+    /// [`remove_sibling_link`](entity/trait.Entity.html#method.remove_sibling_link).
     ///
     /// ⚠ Note that for sibling relations to be fully functionnal, [`get_sibling_trees`](entity/trait.Entity.html#method.get_sibling_trees) must be
     /// overriden
     ///
     /// ### Exemple
     /// ```rust
-    /// let m_struct_1 = MyStruct1::get(&9,&db)?;
-    /// let m_struct_2 = MyStruct2 { key : 0, prop9 : 32};
-    /// m_struct_1.save_sibling(m_struct_2,&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let mut m_struct_2 = MyStruct2 { key : 0, prop9 : 32};
+    /// m_struct_1.save_sibling(&mut m_struct_2,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn save_sibling<E: Entity<Key = Self::Key>>(&self, sibling: &mut E, db: &Db) -> Result<()> {
         sibling.set_key(self.get_key());
-        sibling.save(db)
+        sibling.save(db)?;
+        SiblingLink::relink(Self::store_name(), E::store_name(), &self.get_key().as_bytes(), db)
     }
 
     /// Gets an Entity in another store with the same key as `self`
     ///
+    /// Returns `None` if the link between them was broken with
+    /// [`remove_sibling_link`](entity/trait.Entity.html#method.remove_sibling_link).
+    ///
+    /// ⚠ `E` must be registered as a sibling of `Self` (or `Self` as a sibling of `E`), or this will result in an error.
+    ///
     /// ### Exemple
     /// ```rust
-    /// let m_struct_1 = MyStruct1::get(&9,&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
     /// let m_struct_2 = m_struct_1.get_sibling::<MyStruct2>(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn get_sibling<E: Entity<Key = Self::Key>>(&self, db: &Db) -> Result<Option<E>> {
+        FamilyDescriptor::check_siblings(Self::store_name(), E::store_name(), db)?;
+        let key = self.get_key().as_bytes();
+        if SiblingLink::is_unlinked(Self::store_name(), E::store_name(), &key, db)? {
+            return Ok(None);
+        }
         E::get(self.get_key(), db)
     }
 
+    /// Breaks the link between `self` and its sibling Entity in another store with the same key, leaving both of them
+    /// in their stores.
+    ///
+    /// They are then not considered as siblings anymore : [`get_sibling`](entity/trait.Entity.html#method.get_sibling)
+    /// returns `None`, and the deletion behaviour between their stores is not applied when one of them is removed.
+    /// The link is restored by [`save_sibling`](entity/trait.Entity.html#method.save_sibling), and forgotten when
+    /// either of them is removed.
+    ///
+    /// ⚠ `E` must be registered as a sibling of `Self` (or `Self` as a sibling of `E`), or this will result in an error.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// # MyStruct2 { key : 9, prop9 : 32 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// m_struct_1.remove_sibling_link::<MyStruct2>(&db)?;
+    /// assert!(m_struct_1.get_sibling::<MyStruct2>(&db)?.is_none());
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn remove_sibling_link<E: Entity<Key = Self::Key>>(&self, db: &Db) -> Result<()> {
+        FamilyDescriptor::check_siblings(Self::store_name(), E::store_name(), db)?;
+        let key = self.get_key().as_bytes();
+        if E::get_tree(db)?.contains_key(&key)? {
+            SiblingLink::unlink(Self::store_name(), E::store_name(), &key, db)?;
+        }
+        Ok(())
+    }
+
+    /// Removes `self` and its sibling Entity in another store with the same key.
+    ///
+    /// Deletion behaviours between the two siblings are ignored, but all other integrity checks are done for
+    /// both of them before anything is removed : if any fails, this will result in an error and none will be removed.
+    /// Both siblings are then removed in a single transaction, along with their relations.
+    ///
+    /// ⚠ `E` must be registered as a sibling of `Self` (or `Self` as a sibling of `E`), or this will result in an error.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// m_struct_1.remove_with_sibling::<MyStruct2>(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn remove_with_sibling<E: Entity<Key = Self::Key>>(&self, db: &Db) -> Result<()> {
+        FamilyDescriptor::check_siblings(Self::store_name(), E::store_name(), db)?;
+        let key = self.get_key().as_bytes();
+        let mut removal = Removal::default();
+        removal.add::<Self>(&key);
+        if E::get_tree(db)?.contains_key(&key)?
+            && !SiblingLink::is_unlinked(Self::store_name(), E::store_name(), &key, db)?
+        {
+            removal.add::<E>(&key);
+        }
+        removal.commit(db)
+    }
+
+    /// Returns the keys of all entities of this store that do not have a sibling in the store of `E`.
+    ///
+    /// This is an integrity check helper (for migration purposes, for instance) : nothing prevents an entity from being saved without
+    /// its sibling.
+    ///
+    /// ⚠ This will effectively iterate over every entity in the store.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let keys_without_profile = User::validate_sibling_constraint::<Profile>(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn validate_sibling_constraint<E: Entity<Key = Self::Key>>(db: &Db) -> Result<Vec<Self::Key>> {
+        let sibling_tree = E::get_tree(db)?;
+        let unlinked_tree = SiblingLink::get_existing_tree(Self::store_name(), db)?;
+        let mut result = Vec::new();
+        for elem in Self::get_tree(db)?.iter() {
+            let (key, value) = elem?;
+            if !sibling_tree.contains_key(&key)? || SiblingLink::is_unlinked_in(&unlinked_tree, E::store_name(), &key)? {
+                result.push(Self::try_from_ivec(value)?.get_key().clone());
+            }
+        }
+        Ok(result)
+    }
+
+    /// Counts the entities of this store that do not have a sibling in the store of `E`, without loading them.
+    ///
+    /// ⚠ This will effectively iterate over every key in the store.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let count = User::count_without_sibling::<Profile>(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn count_without_sibling<E: Entity<Key = Self::Key>>(db: &Db) -> Result<usize> {
+        let sibling_tree = E::get_tree(db)?;
+        let unlinked_tree = SiblingLink::get_existing_tree(Self::store_name(), db)?;
+        let mut count = 0;
+        for key in Self::get_tree(db)?.iter().keys() {
+            let key = key?;
+            if !sibling_tree.contains_key(&key)? || SiblingLink::is_unlinked_in(&unlinked_tree, E::store_name(), &key)? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
     /// Saves `child` in its own store after having changed its key to make it effectively a child of `self`
     /// `child` must be an Entity with a Key being the tuple `(Self::Key,_)` (`Self::Key` being the key type of the parent entity)
     ///
+    /// The link between a parent and its children is the key itself : saving the child and linking it to `self`
+    /// is a single write. If a child with the same key already exists, it is updated and stays linked to `self`.
+    ///
     /// ⚠ Note that for child relations to be fully functionnal, [`get_child_trees`](entity/trait.Entity.html#method.get_child_trees) must be
     /// overriden
     ///
     /// ### Exemple
     /// ```rust
-    /// let m_struct_1 = MyStruct1::get(&9,&db)?;
-    /// let m_struct_2 = MyStruct2 { key : (0,44), prop9 : 44};
-    /// m_struct1.save_child(m_struct2,&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let mut child = ChildStruct { key : (0,44), prop9 : 44};
+    /// m_struct_1.save_child(&mut child,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn save_child<E: Entity<Key = (Self::Key, T)>, T: Clone>(
         &self,
@@ -775,9 +2832,13 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///
     /// ### Exemple
     /// ```rust
-    /// let m_struct_1 = MyStruct1::get(&9,&db)?;
-    /// let m_struct_2 = MyStruct2 { key : (0,0), prop9 : 44};
-    /// m_struct1.save_next_child(m_struct2,&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let mut child = ChildStruct { key : (0,0), prop9 : 44};
+    /// m_struct_1.save_next_child(&mut child,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn save_next_child<E: Entity<Key = (Self::Key, u32)>>(
         &self,
@@ -786,7 +2847,7 @@ pub trait Entity: Serialize + DeserializeOwned {
     ) -> Result<E::Key> {
         
         let increment = match E::get_tree(db)?
-            .scan_prefix(&self.get_key().as_bytes())
+            .scan_prefix(self.get_key().as_bytes())
             .flatten()
             .last()
         {
@@ -812,12 +2873,17 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///
     /// ### Exemple
     /// ```rust
-    /// let m_struct_1 = MyStruct1::get(&9,&db)?;
-    /// let m_struct_2 = MyStruct2::get(&(7,2),&db)?;
-    /// m_struct1.adopt_as_next_child(m_struct2,&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// # ChildStruct { key : (7,2), prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let mut child = ChildStruct::get(&(7,2),&db)?.unwrap();
+    /// m_struct_1.adopt_as_next_child(&mut child,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
-    /// After this code, m_struct_2 now has key (9,2) instead of (7,2) and has changed
-    /// accordingly in the database.
+    /// After this code, `child` now has key (9,0) instead of (7,2) (9 being the key of `m_struct_1`, and 0 the next free
+    /// child key) and has changed accordingly in the database.
     fn adopt_as_next_child<E: Entity<Key = (Self::Key, u32)>>(
         &self,
         child: &mut E,
@@ -839,11 +2905,16 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///
     /// ### Exemple
     /// ```rust
-    /// let m_struct_1 = MyStruct1::get(&9,&db)?;
-    /// let m_struct_2 = MyStruct2::get(&(7,2),&db)?;
-    /// m_struct1.adopt_as_next_child(m_struct2,&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// # ChildStruct { key : (7,2), prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let mut child = ChildStruct::get(&(7,2),&db)?.unwrap();
+    /// m_struct_1.adopt_child(&mut child,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
-    /// After this code, m_struct_2 now has key (9,2) instead of (7,2) and has changed
+    /// After this code, `child` now has key (9,2) instead of (7,2) and has changed
     /// accordingly in the database.
     fn adopt_child<E: Entity<Key = (Self::Key, T)>, T: Clone + AsBytes>(
         &self,
@@ -872,14 +2943,138 @@ pub trait Entity: Serialize + DeserializeOwned {
     ///
     /// ### Exemple
     /// ```rust
-    /// let m_struct_1 = MyStruct1::get(&9,&db)?;
-    /// let children : Vec<MyStruct2> = _struct_1.get_children(&db)?;
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let children : Vec<ChildStruct> = m_struct_1.get_children(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     /// Note : Due to [turbofish restrictions](https://github.com/rust-lang/rust/issues/83701),
-    /// `_struct_1.get_children::<MyStruct2>(&db)?` cannot be used [before rust 1.63](https://github.com/rust-lang/rust/issues/83701#issuecomment-1190578601)
+    /// `m_struct_1.get_children::<ChildStruct>(&db)?` cannot be used [before rust 1.63](https://github.com/rust-lang/rust/issues/83701#issuecomment-1190578601)
     fn get_children<E: Entity<Key = (Self::Key, impl AsBytes)>>(&self, db: &Db) -> Result<Vec<E>> {
         E::get_with_prefix(self.get_key(), db)
     }
+
+    /// Removes all children Entities of `self` from another store, and returns the number of removed children.
+    /// `self` is left untouched.
+    ///
+    /// ⚠ If the removal of any of the children is impossible due to integrity checks
+    /// (`DeletionBehaviour::Error` found in the relation hierarchy), this will result in an error and no child will be removed.
+    /// Otherwise, all children are removed in a single transaction.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// let m_struct_1 = MyStruct1::get(&9,&db)?.unwrap();
+    /// let removed_count = m_struct_1.remove_children::<ChildStruct>(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn remove_children<E: Entity<Key = (Self::Key, impl AsBytes)>>(&self, db: &Db) -> Result<usize> {
+        let keys = E::get_tree(db)?
+            .scan_prefix(self.get_key().as_bytes())
+            .keys()
+            .collect::<sled::Result<Vec<IVec>>>()?;
+        let mut removal = Removal::default();
+        for key in &keys {
+            removal.add::<E>(key);
+        }
+        removal.commit(db)?;
+        Ok(keys.len())
+    }
+
+    /// Gets the parent Entity of `self` in another store.
+    /// `Self` must be an Entity with a Key being the tuple `(P::Key,_)` (`P::Key` being the key type of the parent entity)
+    ///
+    /// Returns `None` if the parent does not exist (anymore).
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # MyStruct1 { key : 9, prop9 : 44 }.save(&db)?;
+    /// # ChildStruct { key : (9,2), prop9 : 44 }.save(&db)?;
+    /// let child = ChildStruct::get(&(9,2),&db)?.unwrap();
+    /// let parent = child.get_parent::<MyStruct1,_>(&db)?; // MyStruct1 with key 9
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_parent<P: Entity, T>(&self, db: &Db) -> Result<Option<P>>
+    where
+        Self: Entity<Key = (P::Key, T)>,
+    {
+        P::get(&self.get_key().0, db)
+    }
+
+    /// Returns the number of entities of this store, the total size of their keys and serialized values, and the
+    /// first and last keys of the store, in a single iteration.
+    ///
+    /// ⚠ This will effectively iterate over every entry in the store.
+    ///
+    /// ### Exemple
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let stats = MyStruct::get_tree_stats(&db)?;
+    /// println!("{} entities, {} bytes", stats.count, stats.total_key_bytes + stats.total_value_bytes);
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    fn get_tree_stats(db: &Db) -> Result<TreeStats> {
+        let mut stats = TreeStats::default();
+        let mut last_key = None;
+        for elem in Self::get_tree(db)?.iter() {
+            let (key, value) = elem?;
+            stats.count += 1;
+            stats.total_key_bytes += key.len() as u64;
+            stats.total_value_bytes += value.len() as u64;
+            if stats.first_key.is_none() {
+                stats.first_key = Some(key.to_vec());
+            }
+            last_key = Some(key);
+        }
+        stats.last_key = last_key.map(|key| key.to_vec());
+        Ok(stats)
+    }
+}
+
+/// Summary of the contents of a store, as returned by [`get_tree_stats`](trait.Entity.html#method.get_tree_stats).
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct TreeStats {
+    /// Number of entities in the store
+    pub count: usize,
+    /// Total size of the keys, in bytes
+    pub total_key_bytes: u64,
+    /// Total size of the serialized entities, in bytes
+    pub total_value_bytes: u64,
+    /// Smallest key of the store, as bytes
+    pub first_key: Option<Vec<u8>>,
+    /// Greatest key of the store, as bytes
+    pub last_key: Option<Vec<u8>>,
+}
+
+/// Store name of an instantiation of a generic struct deriving `Entity`.
+///
+/// The instantiations of a generic entity cannot share a store, as they would read and overwrite each other's entities :
+/// the derived [`store_name`](trait.Entity.html#tymethod.store_name) of a generic struct is taken from this trait,
+/// which must be implemented for each instantiation that is used.
+///
+/// ### Example
+/// ```rust
+/// # use reindeer::{Entity, GenericStoreName, Serialize, Deserialize};
+/// #[derive(Serialize, Deserialize, Entity)]
+/// struct Container<T> {
+///     id: u32,
+///     value: T,
+/// }
+///
+/// impl GenericStoreName for Container<String> {
+///     const STORE_NAME: &'static str = "string_container";
+/// }
+/// ```
+pub trait GenericStoreName {
+    /// Name of the store of this instantiation
+    const STORE_NAME: &'static str;
 }
 
 /// `AutoIncrementEntity` is a trait aimed to automatically be
@@ -894,11 +3089,15 @@ pub trait AutoIncrementEntity: Entity<Key = u32> {
     /// Saves the entity to the database after having modified its key to an auto-incremented one.
     /// ### Example
     /// ```rust
-    /// let m_struct = MyStruct { key : 0, prop9 : 44};
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// let mut m_struct = MyStruct1 { key : 0, prop9 : 44};
     /// m_struct.save_next(&db)?; // will have key 0
-    /// let m_struct_2 = MyStruct { key : 0, prop9 :59};
+    /// let mut m_struct_2 = MyStruct1 { key : 0, prop9 :59};
     /// m_struct_2.save_next(&db)?; // creates a new entry with key 1, and so on
-    /// // m_struct2.key is now 1
+    /// // m_struct_2.key is now 1
+    /// # assert_eq!(m_struct_2.key, 1);
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     fn save_next(&mut self, db: &Db) -> Result<u32>;
 }
@@ -971,12 +3170,288 @@ impl AsBytes for &[u8] {
     }
 }
 
+impl AsBytes for Ipv4Addr {
+    fn as_bytes(&self) -> Vec<u8> {
+        self.octets().to_vec()
+    }
+}
+
+impl AsBytes for Ipv6Addr {
+    fn as_bytes(&self) -> Vec<u8> {
+        self.octets().to_vec()
+    }
+}
+
+/// Addresses are stored as their octets prefixed with `4` or `6`, so that the addresses of a network are contiguous :
+/// for instance, the entities keyed by an address of `192.168.0.0/16` can be retrieved with
+/// `MyStruct::get_with_prefix(&[4, 192, 168].as_slice(), &db)`.
+impl AsBytes for IpAddr {
+    fn as_bytes(&self) -> Vec<u8> {
+        match self {
+            IpAddr::V4(ip) => [&[4], ip.octets().as_slice()].concat(),
+            IpAddr::V6(ip) => [&[6], ip.octets().as_slice()].concat(),
+        }
+    }
+}
+
 impl<K1, K2> AsBytes for (K1, K2)
 where
     K1: AsBytes,
     K2: AsBytes,
 {
     fn as_bytes(&self) -> Vec<u8> {
-        vec![self.0.as_bytes(), self.1.as_bytes()].concat()
+        [self.0.as_bytes(), self.1.as_bytes()].concat()
+    }
+}
+
+/// Trait allowing values to be rebuilt from the binary representation returned by [`AsBytes`](trait.AsBytes.html),
+/// for instance to get typed keys back from raw key bytes.
+/// This trait is not meant to be implemented, but you can if you need to.
+pub trait FromBytes: Sized {
+    /// Rebuilds a value from its binary representation
+    fn from_bytes(bytes: &[u8]) -> Result<Self>;
+
+    /// Size in bytes of the binary representation of every value of this type, if it is fixed.
+    ///
+    /// This is needed to split the representation of a tuple between its members.
+    fn fixed_size() -> Option<usize> {
+        None
+    }
+}
+
+fn from_bytes_error(type_name: &str, bytes: &[u8]) -> Error {
+    Error::new(
+        crate::ErrorKind::SerializationError,
+        format!("Cannot convert {} bytes to {}", bytes.len(), type_name),
+    )
+}
+
+impl FromBytes for String {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        String::from_utf8(bytes.to_vec()).map_err(|_| from_bytes_error("String", bytes))
+    }
+}
+
+macro_rules! impl_from_bytes_for_integer {
+    ($($t:ty),*) => {
+        $(
+            impl FromBytes for $t {
+                fn from_bytes(bytes: &[u8]) -> Result<Self> {
+                    bytes
+                        .try_into()
+                        .map(<$t>::from_be_bytes)
+                        .map_err(|_| from_bytes_error(stringify!($t), bytes))
+                }
+
+                fn fixed_size() -> Option<usize> {
+                    Some(std::mem::size_of::<$t>())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_bytes_for_integer!(u32, u64, i32, i64);
+
+impl FromBytes for Vec<u8> {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(bytes.to_vec())
+    }
+}
+
+impl FromBytes for Ipv4Addr {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        <[u8; 4]>::try_from(bytes)
+            .map(Ipv4Addr::from)
+            .map_err(|_| from_bytes_error("Ipv4Addr", bytes))
+    }
+
+    fn fixed_size() -> Option<usize> {
+        Some(4)
+    }
+}
+
+impl FromBytes for Ipv6Addr {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        <[u8; 16]>::try_from(bytes)
+            .map(Ipv6Addr::from)
+            .map_err(|_| from_bytes_error("Ipv6Addr", bytes))
+    }
+
+    fn fixed_size() -> Option<usize> {
+        Some(16)
+    }
+}
+
+impl FromBytes for IpAddr {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        match bytes.split_first() {
+            Some((4, octets)) => Ok(IpAddr::V4(Ipv4Addr::from_bytes(octets)?)),
+            Some((6, octets)) => Ok(IpAddr::V6(Ipv6Addr::from_bytes(octets)?)),
+            _ => Err(from_bytes_error("IpAddr", bytes)),
+        }
+    }
+}
+
+impl<K1, K2> FromBytes for (K1, K2)
+where
+    K1: FromBytes,
+    K2: FromBytes,
+{
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let split = match (K1::fixed_size(), K2::fixed_size()) {
+            (Some(size), _) => Some(size),
+            (None, Some(size)) => bytes.len().checked_sub(size),
+            (None, None) => {
+                return Err(Error::new(
+                    crate::ErrorKind::SerializationError,
+                    String::from("Cannot split a tuple of two variable size values"),
+                ))
+            }
+        };
+        match split {
+            Some(split) if split <= bytes.len() => {
+                let (b1, b2) = bytes.split_at(split);
+                Ok((K1::from_bytes(b1)?, K2::from_bytes(b2)?))
+            }
+            _ => Err(from_bytes_error("tuple", bytes)),
+        }
+    }
+
+    fn fixed_size() -> Option<usize> {
+        Some(K1::fixed_size()? + K2::fixed_size()?)
+    }
+}
+
+/// Encodes a timestamp in nanoseconds so that the byte order matches the chronological order, including before 1970.
+#[cfg(feature = "chrono")]
+fn timestamp_nanos_as_bytes(nanos: i64) -> Vec<u8> {
+    ((nanos as u64) ^ (1 << 63)).to_be_bytes().to_vec()
+}
+
+#[cfg(feature = "chrono")]
+fn timestamp_nanos_from_bytes(type_name: &str, bytes: &[u8]) -> Result<i64> {
+    bytes
+        .try_into()
+        .map(|bytes| (u64::from_be_bytes(bytes) ^ (1 << 63)) as i64)
+        .map_err(|_| from_bytes_error(type_name, bytes))
+}
+
+/// Timestamps are stored with nanosecond precision, so only dates between 1677 and 2262 can be represented : dates
+/// outside of this range are clamped to its bounds.
+#[cfg(feature = "chrono")]
+impl AsBytes for chrono::DateTime<chrono::Utc> {
+    fn as_bytes(&self) -> Vec<u8> {
+        timestamp_nanos_as_bytes(self.timestamp_nanos_opt().unwrap_or(if self.timestamp() < 0 {
+            i64::MIN
+        } else {
+            i64::MAX
+        }))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl FromBytes for chrono::DateTime<chrono::Utc> {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(chrono::DateTime::from_timestamp_nanos(
+            timestamp_nanos_from_bytes("DateTime<Utc>", bytes)?,
+        ))
+    }
+
+    fn fixed_size() -> Option<usize> {
+        Some(8)
+    }
+}
+
+/// Stored as the UTC [`DateTime`](https://docs.rs/chrono/latest/chrono/struct.DateTime.html) with the same date and time.
+#[cfg(feature = "chrono")]
+impl AsBytes for chrono::NaiveDateTime {
+    fn as_bytes(&self) -> Vec<u8> {
+        self.and_utc().as_bytes()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl FromBytes for chrono::NaiveDateTime {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(chrono::DateTime::<chrono::Utc>::from_bytes(bytes)?.naive_utc())
+    }
+
+    fn fixed_size() -> Option<usize> {
+        Some(8)
+    }
+}
+
+/// Versions are ordered by SemVer precedence : major, minor and patch (as 8 bytes big-endian each), then pre-release
+/// identifiers, numeric ones being compared numerically, and a version without pre-release last. Build metadata comes
+/// last, so that versions only differing by it are kept apart.
+#[cfg(feature = "semver")]
+impl AsBytes for semver::Version {
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = [self.major, self.minor, self.patch]
+            .iter()
+            .flat_map(|part| part.to_be_bytes())
+            .collect::<Vec<u8>>();
+        if self.pre.is_empty() {
+            bytes.push(1);
+        } else {
+            bytes.push(0);
+            for identifier in self.pre.split('.') {
+                if identifier.bytes().all(|b| b.is_ascii_digit()) {
+                    bytes.push(1);
+                    bytes.push(identifier.len() as u8);
+                    bytes.extend_from_slice(identifier.as_bytes());
+                } else {
+                    bytes.push(2);
+                    bytes.extend_from_slice(identifier.as_bytes());
+                    bytes.push(0);
+                }
+            }
+            bytes.push(0);
+        }
+        bytes.extend_from_slice(self.build.as_bytes());
+        bytes
+    }
+}
+
+#[cfg(feature = "semver")]
+impl FromBytes for semver::Version {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let error = || from_bytes_error("Version", bytes);
+        if bytes.len() < 25 {
+            return Err(error());
+        }
+        let part = |i: usize| u64::from_bytes(&bytes[i * 8..(i + 1) * 8]);
+        let mut version = semver::Version::new(part(0)?, part(1)?, part(2)?);
+        let mut rest = &bytes[25..];
+        if bytes[24] == 0 {
+            let mut identifiers = Vec::new();
+            loop {
+                match rest.split_first() {
+                    Some((0, tail)) => {
+                        rest = tail;
+                        break;
+                    }
+                    Some((1, tail)) => {
+                        let (len, tail) = tail.split_first().ok_or_else(error)?;
+                        let len = *len as usize;
+                        if tail.len() < len {
+                            return Err(error());
+                        }
+                        identifiers.push(String::from_bytes(&tail[..len])?);
+                        rest = &tail[len..];
+                    }
+                    Some((2, tail)) => {
+                        let end = tail.iter().position(|b| *b == 0).ok_or_else(error)?;
+                        identifiers.push(String::from_bytes(&tail[..end])?);
+                        rest = &tail[end + 1..];
+                    }
+                    _ => return Err(error()),
+                }
+            }
+            version.pre = semver::Prerelease::new(&identifiers.join(".")).map_err(|_| error())?;
+        }
+        version.build = semver::BuildMetadata::new(&String::from_bytes(rest)?).map_err(|_| error())?;
+        Ok(version)
     }
 }
@@ -0,0 +1,137 @@
+//! # Entity Collection Module
+//! This module provides [`EntityCollection`](struct.EntityCollection.html), an in-memory copy of an entity store
+//! that is kept synchronized with the database.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, TryLockError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use sled::Db;
+
+use crate::entity::{AsBytes, Entity};
+use crate::error::Result;
+use crate::subscriber::EntityEvent;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// An in-memory copy of all the entities of a store, indexed by key bytes.
+///
+/// All entities are loaded when the collection is created. A background thread then watches the store and applies
+/// every change made to it, through the collection or not, to the in-memory map. Changes made by other means are
+/// therefore visible after a short delay, while changes made with [`save_and_sync`](struct.EntityCollection.html#method.save_and_sync)
+/// and [`remove_and_sync`](struct.EntityCollection.html#method.remove_and_sync) are visible immediately.
+///
+/// The background thread is stopped when the collection is dropped.
+///
+/// ### Example
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # use reindeer::EntityCollection;
+/// let users = EntityCollection::<User>::new(&db)?;
+/// users.save_and_sync(User { id: 3, name: String::from("Nancy") })?;
+/// assert!(users.entities().contains_key(&3u32.as_bytes()));
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub struct EntityCollection<T: Entity> {
+    entities: Arc<RwLock<HashMap<Vec<u8>, T>>>,
+    db: Db,
+    stop: Arc<AtomicBool>,
+    watcher: Option<JoinHandle<()>>,
+}
+
+impl<T: Entity + Send + Sync + 'static> EntityCollection<T> {
+    /// Loads all the entities of `T` and starts watching its store.
+    pub fn new(db: &Db) -> Result<EntityCollection<T>> {
+        // Watching before loading so that no change is missed in between
+        let mut subscriber = T::watch_all_prefix(Vec::<u8>::new(), db)?;
+        let entities = Arc::new(RwLock::new(
+            T::get_all(db)?
+                .into_iter()
+                .map(|e| (e.get_key().as_bytes(), e))
+                .collect::<HashMap<Vec<u8>, T>>(),
+        ));
+        let stop = Arc::new(AtomicBool::new(false));
+        let watcher = {
+            let entities = entities.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut pending = Vec::new();
+                while !stop.load(Ordering::Relaxed) {
+                    if let Some(Ok(event)) = subscriber.next_timeout(POLL_INTERVAL) {
+                        pending.push(event);
+                    }
+                    if pending.is_empty() {
+                        continue;
+                    }
+                    // Events keep being received while the map is locked, so that writers are never blocked by a full channel
+                    let mut entities = match entities.try_write() {
+                        Ok(entities) => entities,
+                        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+                        Err(TryLockError::WouldBlock) => continue,
+                    };
+                    for event in pending.drain(..) {
+                        match event {
+                            EntityEvent::Set(e) => {
+                                entities.insert(e.get_key().as_bytes(), e);
+                            }
+                            EntityEvent::Remove(key) => {
+                                entities.remove(&key);
+                            }
+                        }
+                    }
+                }
+            })
+        };
+        Ok(EntityCollection {
+            entities,
+            db: db.clone(),
+            stop,
+            watcher: Some(watcher),
+        })
+    }
+
+    /// Read access to the in-memory entities, indexed by key bytes.
+    ///
+    /// ⚠ Changes cannot be applied to the collection while the returned guard is held.
+    pub fn entities(&self) -> RwLockReadGuard<'_, HashMap<Vec<u8>, T>> {
+        self.entities.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Saves `entity` to the database and to the in-memory map.
+    ///
+    /// If saving to the database fails, the in-memory map is left untouched.
+    pub fn save_and_sync(&self, entity: T) -> Result<()> {
+        let mut entities = self
+            .entities
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        entity.save(&self.db)?;
+        entities.insert(entity.get_key().as_bytes(), entity);
+        Ok(())
+    }
+
+    /// Removes the entity of key `key` from the database and from the in-memory map.
+    ///
+    /// If removing from the database fails, the in-memory map is left untouched.
+    pub fn remove_and_sync(&self, key: &T::Key) -> Result<()> {
+        let mut entities = self
+            .entities
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        T::remove(key, &self.db)?;
+        entities.remove(&key.as_bytes());
+        Ok(())
+    }
+}
+
+impl<T: Entity> Drop for EntityCollection<T> {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(watcher) = self.watcher.take() {
+            let _ = watcher.join();
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! # Entity Ref Module
+//! This module provides [`EntityRef`](struct.EntityRef.html), a reference to an entity that is only read from the
+//! database and deserialized the first time it is accessed.
+
+use std::cell::OnceCell;
+use std::fmt;
+use std::ops::Deref;
+
+use sled::Db;
+
+use crate::entity::{AsBytes, Entity};
+use crate::error::Result;
+
+/// A lazily loaded reference to an entity of type `T`.
+///
+/// An `EntityRef` only holds the key of the entity (as bytes). The entity is read and deserialized the first time
+/// it is accessed, either with [`load`](struct.EntityRef.html#method.load) or through `Deref`, and then cached.
+///
+/// `EntityRef`s of related entities can be obtained with
+/// [`Entity::get_related_refs`](trait.Entity.html#method.get_related_refs).
+///
+/// ### Example
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # let author = Author { id : 1, name : String::from("Frank Herbert") };
+/// let book_refs = author.get_related_refs::<Book>(&db)?;
+/// for book in &book_refs {
+///     if let Some(book) = book.load()? {
+///         println!("{}", book.title);
+///     }
+/// }
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub struct EntityRef<T: Entity> {
+    key: Vec<u8>,
+    db: Db,
+    value: OnceCell<Option<T>>,
+}
+
+impl<T: Entity> EntityRef<T> {
+    /// Creates a reference to the entity of key `key`, without reading it.
+    pub fn new(key: &T::Key, db: &Db) -> EntityRef<T> {
+        EntityRef::from_key_bytes(key.as_bytes(), db)
+    }
+
+    /// Creates a reference to the entity whose key is `key` once converted to bytes, without reading it.
+    pub fn from_key_bytes(key: Vec<u8>, db: &Db) -> EntityRef<T> {
+        EntityRef {
+            key,
+            db: db.clone(),
+            value: OnceCell::new(),
+        }
+    }
+
+    /// Key of the referenced entity, as bytes
+    pub fn key_bytes(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// Returns `true` if the entity has already been read from the database.
+    pub fn is_loaded(&self) -> bool {
+        self.value.get().is_some()
+    }
+
+    /// Reads and deserializes the entity on the first call, and returns the cached value afterwards.
+    ///
+    /// Returns `None` if the entity does not exist. Errors are not cached : the next call will try to read the entity again.
+    pub fn load(&self) -> Result<Option<&T>> {
+        if let Some(value) = self.value.get() {
+            return Ok(value.as_ref());
+        }
+        let value = T::get_from_u8_array(&self.key, &self.db)?;
+        Ok(self.value.get_or_init(|| value).as_ref())
+    }
+
+    /// Consumes this reference and returns the entity, reading it if it was not loaded yet.
+    pub fn into_inner(self) -> Result<Option<T>> {
+        match self.value.into_inner() {
+            Some(value) => Ok(value),
+            None => T::get_from_u8_array(&self.key, &self.db),
+        }
+    }
+}
+
+/// ⚠ Dereferencing an `EntityRef` panics if the entity cannot be read or deserialized.
+/// Use [`load`](struct.EntityRef.html#method.load) to handle those errors.
+impl<T: Entity> Deref for EntityRef<T> {
+    type Target = Option<T>;
+
+    fn deref(&self) -> &Option<T> {
+        if let Err(e) = self.load() {
+            panic!("Could not load entity of {} : {}", T::store_name(), e);
+        }
+        self.value.get().unwrap_or(&None)
+    }
+}
+
+impl<T: Entity> fmt::Debug for EntityRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EntityRef")
+            .field("store", &T::store_name())
+            .field("key", &self.key)
+            .field("loaded", &self.is_loaded())
+            .finish()
+    }
+}
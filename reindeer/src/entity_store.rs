@@ -0,0 +1,276 @@
+//! # Entity Store Module
+//! This module provides [`EntityStore`](struct.EntityStore.html), a handle on the store of an entity type.
+
+use std::marker::PhantomData;
+#[cfg(feature = "cache")]
+use std::num::NonZeroUsize;
+#[cfg(any(feature = "cache", feature = "metrics"))]
+use std::sync::Arc;
+#[cfg(feature = "cache")]
+use std::sync::{Mutex, PoisonError};
+#[cfg(feature = "metrics")]
+use std::time::Instant;
+
+#[cfg(feature = "cache")]
+use lru::LruCache;
+
+use sled::Db;
+
+#[cfg(feature = "cache")]
+use crate::entity::AsBytes;
+use crate::error::Result;
+#[cfg(feature = "metrics")]
+use crate::metrics::{Metrics, NoOpMetrics};
+use crate::Entity;
+
+#[derive(Clone, Copy)]
+enum Operation {
+    Get,
+    Save,
+    Remove,
+}
+
+/// A handle on the store of `T`, holding a clone of the database, to call the main [`Entity`](trait.Entity.html)
+/// methods as instance methods instead of passing `&Db` around.
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # use reindeer::EntityStore;
+/// # let my_struct = MyStruct { key : 3, prop1 : String::from("Hello"), prop2 : 554 };
+/// let store = EntityStore::<MyStruct>::new(&db);
+/// store.save(&my_struct)?;
+/// let my_struct = store.get(&3)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub struct EntityStore<T> {
+    db: Db,
+    #[cfg(feature = "metrics")]
+    metrics: Arc<dyn Metrics>,
+    entity_type: PhantomData<T>,
+}
+
+impl<T> Clone for EntityStore<T> {
+    fn clone(&self) -> Self {
+        EntityStore {
+            db: self.db.clone(),
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics.clone(),
+            entity_type: PhantomData,
+        }
+    }
+}
+
+impl<T: Entity> EntityStore<T> {
+    /// Creates a handle on the store of `T` in `db`.
+    pub fn new(db: &Db) -> Self {
+        EntityStore {
+            db: db.clone(),
+            #[cfg(feature = "metrics")]
+            metrics: Arc::new(NoOpMetrics),
+            entity_type: PhantomData,
+        }
+    }
+
+    /// Reports the `get`, `save` and `remove` operations made through this store (and its clones) and their
+    /// latencies to `metrics`.
+    ///
+    /// Only available with the `metrics` feature.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    #[cfg(feature = "metrics")]
+    fn record<R>(&self, operation: Operation, f: impl FnOnce() -> R) -> R {
+        let start = Instant::now();
+        let result = f();
+        let latency_ns = start.elapsed().as_nanos() as u64;
+        let entity_type = T::store_name();
+        let name = match operation {
+            Operation::Get => {
+                self.metrics.record_get(entity_type);
+                "get"
+            }
+            Operation::Save => {
+                self.metrics.record_save(entity_type);
+                "save"
+            }
+            Operation::Remove => {
+                self.metrics.record_remove(entity_type);
+                "remove"
+            }
+        };
+        self.metrics.record_latency_ns(name, entity_type, latency_ns);
+        result
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    fn record<R>(&self, _operation: Operation, f: impl FnOnce() -> R) -> R {
+        f()
+    }
+
+    /// Returns the database this store belongs to.
+    pub fn db(&self) -> &Db {
+        &self.db
+    }
+
+    /// Wraps this store with an LRU read cache holding up to `capacity` entities (at least 1).
+    ///
+    /// Only available with the `cache` feature.
+    #[cfg(feature = "cache")]
+    pub fn with_cache(&self, capacity: usize) -> CachedEntityStore<T> {
+        CachedEntityStore {
+            store: self.clone(),
+            cache: Arc::new(Mutex::new(LruCache::new(
+                NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN),
+            ))),
+        }
+    }
+
+    /// See [`Entity::register`](trait.Entity.html#method.register).
+    pub fn register(&self) -> Result<()> {
+        T::register(&self.db)
+    }
+
+    /// See [`Entity::get`](trait.Entity.html#method.get).
+    pub fn get(&self, key: &T::Key) -> Result<Option<T>> {
+        self.record(Operation::Get, || T::get(key, &self.db))
+    }
+
+    /// See [`Entity::get_all`](trait.Entity.html#method.get_all).
+    pub fn get_all(&self) -> Result<Vec<T>> {
+        T::get_all(&self.db)
+    }
+
+    /// See [`Entity::get_with_filter`](trait.Entity.html#method.get_with_filter).
+    pub fn get_with_filter<F: Fn(&T) -> bool>(&self, f: F) -> Result<Vec<T>> {
+        T::get_with_filter(f, &self.db)
+    }
+
+    /// See [`Entity::get_count`](trait.Entity.html#method.get_count).
+    pub fn get_count(&self) -> Result<usize> {
+        T::get_count(&self.db)
+    }
+
+    /// See [`Entity::exists`](trait.Entity.html#method.exists).
+    pub fn exists(&self, key: &T::Key) -> Result<bool> {
+        T::exists(key, &self.db)
+    }
+
+    /// See [`Entity::save`](trait.Entity.html#method.save).
+    pub fn save(&self, entity: &T) -> Result<()> {
+        self.record(Operation::Save, || entity.save(&self.db))
+    }
+
+    /// See [`Entity::update`](trait.Entity.html#method.update).
+    pub fn update<F: Fn(&mut T)>(&self, key: &T::Key, f: F) -> Result<()> {
+        self.record(Operation::Save, || T::update(key, f, &self.db))
+    }
+
+    /// See [`Entity::remove`](trait.Entity.html#method.remove).
+    pub fn remove(&self, key: &T::Key) -> Result<()> {
+        self.record(Operation::Remove, || T::remove(key, &self.db))
+    }
+}
+
+/// An [`EntityStore`](struct.EntityStore.html) keeping the last entities it read in an LRU cache,
+/// as returned by [`EntityStore::with_cache`](struct.EntityStore.html#method.with_cache).
+///
+/// The cache is shared by all clones of this handle. Saving, updating or removing an entity through it invalidates
+/// the matching cache entry.
+///
+/// ⚠ Changes made without this handle (including removals cascading from other stores) are not seen until the
+/// cache entry is evicted : use [`clear_cache`](struct.CachedEntityStore.html#method.clear_cache) if needed.
+///
+/// Only available with the `cache` feature.
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # use reindeer::EntityStore;
+/// let store = EntityStore::<MyStruct>::new(&db).with_cache(1000);
+/// let my_struct = store.get(&3)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+#[cfg(feature = "cache")]
+pub struct CachedEntityStore<T> {
+    store: EntityStore<T>,
+    cache: Arc<Mutex<LruCache<Vec<u8>, T>>>,
+}
+
+#[cfg(feature = "cache")]
+impl<T> Clone for CachedEntityStore<T> {
+    fn clone(&self) -> Self {
+        CachedEntityStore {
+            store: self.store.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "cache")]
+impl<T: Entity + Clone> CachedEntityStore<T> {
+    /// Evicts `key` from the cache. Writes call this once they are done, whether they succeeded or not.
+    fn invalidate(&self, key: &[u8]) {
+        self.cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop(key);
+    }
+
+    /// Returns the underlying store, to call methods that bypass the cache.
+    pub fn store(&self) -> &EntityStore<T> {
+        &self.store
+    }
+
+    /// Retrieves an entity instance given its key, from the cache if possible.
+    ///
+    /// See [`Entity::get`](trait.Entity.html#method.get).
+    pub fn get(&self, key: &T::Key) -> Result<Option<T>> {
+        let key_bytes = key.as_bytes();
+        // The cache stays locked while reading the store, so that a write finishing in the meantime evicts the
+        // value read here instead of running before it is cached
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(entity) = cache.get(&key_bytes) {
+            return Ok(Some(entity.clone()));
+        }
+        let entity = self.store.get(key)?;
+        if let Some(entity) = &entity {
+            cache.put(key_bytes, entity.clone());
+        }
+        Ok(entity)
+    }
+
+    /// See [`Entity::save`](trait.Entity.html#method.save).
+    pub fn save(&self, entity: &T) -> Result<()> {
+        let result = self.store.save(entity);
+        self.invalidate(&entity.get_key().as_bytes());
+        result
+    }
+
+    /// See [`Entity::update`](trait.Entity.html#method.update).
+    pub fn update<F: Fn(&mut T)>(&self, key: &T::Key, f: F) -> Result<()> {
+        let result = self.store.update(key, f);
+        self.invalidate(&key.as_bytes());
+        result
+    }
+
+    /// See [`Entity::remove`](trait.Entity.html#method.remove).
+    pub fn remove(&self, key: &T::Key) -> Result<()> {
+        let result = self.store.remove(key);
+        self.invalidate(&key.as_bytes());
+        result
+    }
+
+    /// Removes every entry of the cache.
+    pub fn clear_cache(&self) {
+        self.cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+}
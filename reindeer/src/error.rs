@@ -1,5 +1,7 @@
 use std::fmt;
 
+use crate::relation::DeletionBehaviour;
+
 /// Error kind enum for Reindeer-related errors.
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy)]
@@ -12,10 +14,77 @@ pub enum ErrorKind {
     IOError,
     /// An integrity constraint has been violated while trying to remove an entity from the database
     IntegrityError,
-    /// An entity was not found
+    /// A database or a resource other than an entity was not found
     NotFound,
     /// An entity was used without being registered firts in the database
     UnregisteredEntity,
+    /// A serialized entity is bigger than the allowed size
+    EntityTooLarge {
+        /// Size of the serialized entity, in bytes
+        size: usize,
+        /// Maximum allowed size, in bytes
+        limit: usize,
+    },
+    /// An entity was saved with [`versioned_save`](trait.Entity.html#method.versioned_save)
+    /// while its stored version was not the expected one
+    VersionConflict {
+        /// Version the entity was expected to have
+        expected: u64,
+        /// Version the entity actually has
+        current: u64,
+    },
+    /// An entity could not be saved because another entity of the store has the same value for a field declared
+    /// unique with `#[entity(unique = "field")]`. The field and value are available through
+    /// [`Error::context`](struct.Error.html#method.context)
+    UniqueConstraintViolation,
+    /// An entity that was expected to exist was not found, for instance the target of a relation that has been
+    /// removed concurrently. The store and key are available through [`Error::context`](struct.Error.html#method.context)
+    EntityNotFound,
+    /// A `sled` tree could not be opened. The tree name is available through [`Error::context`](struct.Error.html#method.context)
+    TreeOpenError,
+    /// An entity type was registered with the same store name and schema version as another entity type
+    StoreNameConflict,
+}
+
+/// Structured details about an error, available through [`Error::context`](struct.Error.html#method.context)
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub enum ErrorContext {
+    /// An entity of type `entity_type` could not be serialized or deserialized.
+    /// The underlying error is available through [`source`](https://doc.rust-lang.org/std/error/trait.Error.html#method.source)
+    Serialization {
+        /// Store name of the entity type
+        entity_type: &'static str,
+    },
+    /// An entity could not be removed because a related entity exists with a
+    /// [`DeletionBehaviour::Error`](enum.DeletionBehaviour.html#variant.Error) constraint
+    RelationConstraintViolation {
+        /// Store of the entity being removed
+        source_store: String,
+        /// Store of the related entity preventing the removal
+        target_store: String,
+        /// Deletion behaviour of the relation
+        behaviour: DeletionBehaviour,
+    },
+    /// A unique field constraint was violated
+    UniqueConstraintViolation {
+        /// Name of the unique field
+        field: &'static str,
+        /// Value of the field, as serialized with `bincode`
+        value: Vec<u8>,
+    },
+    /// An entity was not found
+    EntityNotFound {
+        /// Store name of the entity
+        store: &'static str,
+        /// Key of the entity, as bytes
+        key: Vec<u8>,
+    },
+    /// A tree could not be opened
+    TreeOpen {
+        /// Name of the tree
+        tree_name: String,
+    },
 }
 
 /// Error type for `reindeer`
@@ -23,6 +92,8 @@ pub enum ErrorKind {
 pub struct Error {
     error_kind: ErrorKind,
     message: String,
+    source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    context: Option<Box<ErrorContext>>,
 }
 
 impl Error {
@@ -31,11 +102,35 @@ impl Error {
         Error {
             error_kind,
             message,
+            source: None,
+            context: None,
+        }
+    }
+    /// Creates a new error from an error kind, a message and the underlying error that caused it
+    pub fn with_source<E: std::error::Error + Send + Sync + 'static>(
+        error_kind: ErrorKind,
+        message: String,
+        source: E,
+    ) -> Error {
+        Error {
+            error_kind,
+            message,
+            source: Some(Box::new(source)),
+            context: None,
         }
     }
+    /// Adds structured details to this error
+    pub fn with_context(mut self, context: ErrorContext) -> Error {
+        self.context = Some(Box::new(context));
+        self
+    }
     pub fn kind(&self) -> ErrorKind {
         self.error_kind
     }
+    /// Structured details about this error, if any
+    pub fn context(&self) -> Option<&ErrorContext> {
+        self.context.as_deref()
+    }
 }
 
 impl fmt::Display for Error {
@@ -48,31 +143,89 @@ impl fmt::Display for Error {
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| source.as_ref() as &(dyn std::error::Error + 'static))
+    }
+}
+
+pub(crate) fn entity_not_found(store: &'static str, key: &[u8]) -> Error {
+    Error::new(
+        ErrorKind::EntityNotFound,
+        format!("Entity was not found in {}", store),
+    )
+    .with_context(ErrorContext::EntityNotFound {
+        store,
+        key: key.to_vec(),
+    })
+}
 
 /// Type definition to simplify the use of Result everywhere in the library
 pub type Result<T> = std::result::Result<T, Error>;
 
 impl From<std::io::Error> for Error {
     fn from(source: std::io::Error) -> Self {
-        Error::new(ErrorKind::IOError, source.to_string())
+        Error::with_source(ErrorKind::IOError, source.to_string(), source)
     }
 }
 
 impl From<sled::Error> for Error {
     fn from(source: sled::Error) -> Self {
-        Error::new(ErrorKind::SledError, source.to_string())
+        Error::with_source(ErrorKind::SledError, source.to_string(), source)
     }
 }
 
 impl From<bincode::Error> for Error {
     fn from(source: bincode::Error) -> Self {
-        Error::new(ErrorKind::SerializationError, source.to_string())
+        Error::with_source(ErrorKind::SerializationError, source.to_string(), source)
     }
 }
 
 impl From<serde_json::Error> for Error {
     fn from(source: serde_json::Error) -> Self {
-        Error::new(ErrorKind::SerializationError, source.to_string())
+        Error::with_source(ErrorKind::SerializationError, source.to_string(), source)
+    }
+}
+
+impl From<sled::transaction::TransactionError> for Error {
+    fn from(source: sled::transaction::TransactionError) -> Self {
+        Error::with_source(ErrorKind::SledError, source.to_string(), source)
+    }
+}
+
+#[cfg(feature = "csv")]
+impl From<csv::Error> for Error {
+    fn from(source: csv::Error) -> Self {
+        Error::with_source(ErrorKind::SerializationError, source.to_string(), source)
+    }
+}
+
+#[cfg(feature = "messagepack")]
+impl From<rmp_serde::encode::Error> for Error {
+    fn from(source: rmp_serde::encode::Error) -> Self {
+        Error::with_source(ErrorKind::SerializationError, source.to_string(), source)
+    }
+}
+
+#[cfg(feature = "messagepack")]
+impl From<rmp_serde::decode::Error> for Error {
+    fn from(source: rmp_serde::decode::Error) -> Self {
+        Error::with_source(ErrorKind::SerializationError, source.to_string(), source)
+    }
+}
+
+#[cfg(feature = "async")]
+impl From<tokio::task::JoinError> for Error {
+    fn from(source: tokio::task::JoinError) -> Self {
+        Error::with_source(ErrorKind::IOError, source.to_string(), source)
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl From<prometheus::Error> for Error {
+    fn from(source: prometheus::Error) -> Self {
+        Error::with_source(ErrorKind::IOError, source.to_string(), source)
     }
 }
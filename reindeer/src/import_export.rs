@@ -1,8 +1,11 @@
-use crate::{AsBytes, Result};
+use std::collections::BTreeMap;
+
+use crate::{AsBytes, Error, ErrorKind, Result};
 use serde_derive::{Deserialize, Serialize};
-use sled::Db;
+use sled::transaction::ConflictableTransactionResult;
+use sled::{Batch, Db, Transactional, Tree};
 
-use crate::relation::Relation;
+use crate::relation::{FamilyDescriptor, Relation};
 use crate::{relation::EntityRelations, Entity};
 
 #[derive(Serialize, Deserialize)]
@@ -12,28 +15,172 @@ impl<T: Entity> JsonWrapper<T> {
     pub fn from(source_vec: Vec<T>, db: &Db) -> Result<Self> {
         let entries: Result<Vec<(T, Option<EntityRelations>)>> = source_vec
             .into_iter()
-            .map(|source| {
-                let relations = Relation::get_descriptor_with_key_and_tree_name(
-                    T::store_name(),
-                    &source.get_key().as_bytes(),
-                    db,
-                )?;
-                if relations.related_entities.len() > 0 {
-                    Ok((source, Some(relations)))
-                } else {
-                    Ok((source, None))
-                }
-            })
+            .map(|source| Self::entry(source, db))
             .collect();
         Ok(Self(entries?))
     }
-    pub fn save(self, db: &Db) -> Result<()> {
+    pub fn entry(source: T, db: &Db) -> Result<(T, Option<EntityRelations>)> {
+        let relations = Relation::get_descriptor_with_key_and_tree_name(
+            T::store_name(),
+            &source.get_key().as_bytes(),
+            db,
+        )?;
+        if !relations.related_entities.is_empty() {
+            Ok((source, Some(relations)))
+        } else {
+            Ok((source, None))
+        }
+    }
+    pub fn save(self, db: &Db) -> Result<usize> {
+        let report = self.save_with_policy(ConflictPolicy::Overwrite, db)?;
+        Ok(report.inserted + report.overwritten)
+    }
+    pub fn save_with_policy(self, policy: ConflictPolicy, db: &Db) -> Result<ImportReport> {
+        let tree = T::get_tree(db)?;
+        if policy == ConflictPolicy::Error {
+            for (entity, _) in &self.0 {
+                if tree.contains_key(entity.get_key().as_bytes())? {
+                    return Err(Error::new(
+                        ErrorKind::IntegrityError,
+                        format!("Entity already exists in {}", T::store_name()),
+                    ));
+                }
+            }
+        }
+        let mut report = ImportReport::default();
         for (entity, relations) in self.0 {
+            if tree.contains_key(entity.get_key().as_bytes())? {
+                if policy == ConflictPolicy::Skip {
+                    report.skipped += 1;
+                    continue;
+                }
+                report.overwritten += 1;
+            } else {
+                report.inserted += 1;
+            }
             entity.save(db)?;
             if let Some(relations) = relations {
                 Relation::save_descriptor(&entity, &relations, db)?;
             }
         }
+        Ok(report)
+    }
+}
+
+/// Defines what happens when importing an entity whose key already exists in the store.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ConflictPolicy {
+    /// The existing entity is left unchanged
+    Skip,
+    /// The existing entity is replaced by the imported one
+    Overwrite,
+    /// The import fails with an error before anything is imported
+    Error,
+}
+
+/// Summary of an import, as returned by [`import_json_with_policy`](trait.Entity.html#method.import_json_with_policy).
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct ImportReport {
+    /// Number of imported entities whose key did not exist in the store
+    pub inserted: usize,
+    /// Number of existing entities replaced by imported ones
+    pub overwritten: usize,
+    /// Number of imported entities left out because their key already existed in the store
+    pub skipped: usize,
+}
+
+pub type RawTree = Vec<(Vec<u8>, Vec<u8>)>;
+
+pub fn dump_tree(tree: &Tree) -> Result<RawTree> {
+    let mut entries = Vec::new();
+    for elem in tree.iter() {
+        let (key, value) = elem?;
+        entries.push((key.to_vec(), value.to_vec()));
+    }
+    Ok(entries)
+}
+
+/// Moves all the entries of the tree `from` to the tree `to`, and drops `from`.
+/// Nothing is done if `from` does not exist, so that no empty tree is created in its place, nor for `to`.
+pub fn move_tree(from: &str, to: &str, db: &Db) -> Result<usize> {
+    if !db.tree_names().iter().any(|name| name == from.as_bytes()) {
+        return Ok(0);
+    }
+    let source = db.open_tree(from)?;
+    let entries = dump_tree(&source)?;
+    db.open_tree(to)?.apply_batch(to_batch(&entries))?;
+    db.drop_tree(from)?;
+    Ok(entries.len())
+}
+
+pub fn to_batch(entries: &RawTree) -> Batch {
+    let mut batch = Batch::default();
+    for (key, value) in entries {
+        batch.insert(key.as_slice(), value.as_slice());
+    }
+    batch
+}
+
+/// Dump of a whole database : every registered entity store, and all relation data.
+///
+/// Entities are kept as raw serialized data, so that a database can be captured, serialized (with `serde`)
+/// and restored elsewhere without knowing entity types.
+///
+/// ### Exemple
+/// ```rust,no_run
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # use std::fs::File;
+/// # use reindeer::FullDatabaseDump;
+/// # let other_db = doctest_db()?;
+/// let dump = FullDatabaseDump::capture(&db)?;
+/// serde_json::to_writer(File::create("dump.json")?, &dump)?;
+/// // ...
+/// let dump : FullDatabaseDump = serde_json::from_reader(File::open("dump.json")?)?;
+/// dump.restore(&other_db)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+#[derive(Serialize, Deserialize, Default)]
+pub struct FullDatabaseDump {
+    pub(crate) stores: BTreeMap<String, RawTree>,
+    pub(crate) relations: BTreeMap<String, RawTree>,
+}
+
+impl FullDatabaseDump {
+    /// Captures the content of every registered entity store and all relation data of the database.
+    pub fn capture(db: &Db) -> Result<Self> {
+        let mut dump = FullDatabaseDump::default();
+        for descriptor in FamilyDescriptor::list_all(db)? {
+            let entries = dump_tree(&db.open_tree(&descriptor.tree_name)?)?;
+            dump.stores.insert(descriptor.tree_name, entries);
+        }
+        for tree_name in db.tree_names() {
+            let tree_name = String::from_utf8_lossy(&tree_name).into_owned();
+            if tree_name.starts_with("__$") {
+                let entries = dump_tree(&db.open_tree(&tree_name)?)?;
+                dump.relations.insert(tree_name, entries);
+            }
+        }
+        Ok(dump)
+    }
+
+    /// Writes the content of the dump to the database, in a single transaction.
+    /// Any existing entities with matching keys will be overridden.
+    pub fn restore(&self, db: &Db) -> Result<()> {
+        let mut trees = Vec::new();
+        let mut batches = Vec::new();
+        for (tree_name, entries) in self.stores.iter().chain(&self.relations) {
+            trees.push(db.open_tree(tree_name)?);
+            batches.push(to_batch(entries));
+        }
+        trees.as_slice().transaction(
+            |trees| -> ConflictableTransactionResult<(), sled::Error> {
+                for (tree, batch) in trees.iter().zip(&batches) {
+                    tree.apply_batch(batch)?;
+                }
+                Ok(())
+            },
+        )?;
         Ok(())
     }
 }
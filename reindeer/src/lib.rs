@@ -16,6 +16,7 @@
 //!  - the [`Entity::get`](entity/trait.Entity.html#method.get) method to get any entity from the database using its unique key
 //!  - the [`Entity::get_all`](entity/trait.Entity.html#method.get_all) method to get all entities from the database using its unique key
 //!  - the [`Entity::get_with_filter`](entity/trait.Entity.html#method.get_with_filter) method to get all entities that match a condition (O(n))
+//!  - the [`EntityQuery`](struct.EntityQuery.html) struct to compose conditions, limits and offsets on a store (O(n))
 //!  - ... And [much more](entity/trait.Entity.html)!
 //!
 //! If the [`Key`](entity/trait.Entity.html#associatedtype.Key) associated type is `u32`, then your entity can be auto-incremented
@@ -24,7 +25,7 @@
 //!
 //! Three types of relationships can be achieved :
 //!  - Sibling relationship : two or more `Entity` structs that share the same key type for which each entity has 0 or 1 counterpart
-//! in their sibling Entity stores (one-to-zero-or-one)
+//!    in their sibling Entity stores (one-to-zero-or-one)
 //!  - Parent-Child relationship : An entity has a collection of matching entities in another Entity Store (one-to-many)
 //!  - Free relationship : Any two entities can be linked together as a two-way link. (many-to-many)
 //!
@@ -32,24 +33,103 @@
 //!  - `DeletionBehaviour::Cascade` : related entities are also removed if this one is removed
 //!  - `DeletionBehaviour::Error` : Trying to remove this entity as related entities still exist will cause an error and abort
 //!  - `DeletionBehaviour::BreakLink` : Remove this entity and the links with its related entites, leaving the other ones untouched
+//!  - `DeletionBehaviour::SetNull(field)` : Remove this entity and the links with its related entites, and set their `field` to `None`
+//!  - `DeletionBehaviour::Custom(f)` : Remove this entity, and call `f` with the key of each related sibling or child entity
+//!
+//! Optional features can be enabled in `Cargo.toml` :
+//!  - `csv` : CSV export and import of flat entities with [`Entity::export_csv`](entity/trait.Entity.html#method.export_csv)
+//!    and [`Entity::import_csv`](entity/trait.Entity.html#method.import_csv)
+//!  - `messagepack` : entities are stored as (self-describing) MessagePack instead of `bincode`. Existing stores can be
+//!    converted with [`migrate_serialization_format`](fn.migrate_serialization_format.html)
+//!  - `async` : async counterparts of the main `Entity` methods, running on Tokio's blocking thread pool,
+//!    with the [`AsyncEntity`](trait.AsyncEntity.html) trait
+//!  - `cache` : an LRU read cache on top of an entity store, with [`EntityStore::with_cache`](struct.EntityStore.html#method.with_cache)
+//!  - `tracing` : `tracing` spans on entity operations (`save`, `get`, `get_all`, `remove` and free relation operations),
+//!    with `entity_type` and `key` (as hex bytes) fields
+//!  - `metrics` : operation counts and latencies of an [`EntityStore`](struct.EntityStore.html), reported to a
+//!    [`Metrics`](trait.Metrics.html) implementation such as [`PrometheusMetrics`](struct.PrometheusMetrics.html)
+//!  - `chrono` : [`AsBytes`](trait.AsBytes.html) and [`FromBytes`](trait.FromBytes.html) for `chrono::NaiveDateTime` and
+//!    `chrono::DateTime<Utc>`, ordered chronologically, to use timestamps as keys and query time ranges
+//!  - `semver` : [`AsBytes`](trait.AsBytes.html) and [`FromBytes`](trait.FromBytes.html) for `semver::Version`, ordered by
+//!    SemVer precedence, to use versions as keys and query version ranges
+//!  - `rayon` : [`Entity::get_all_parallel`](entity/trait.Entity.html#method.get_all_parallel),
+//!    [`Entity::get_with_filter_parallel`](entity/trait.Entity.html#method.get_with_filter_parallel) and
+//!    [`Entity::get_with_filter_chunked`](entity/trait.Entity.html#method.get_with_filter_chunked), deserializing a whole
+//!    store on all CPU cores
+
+#[macro_use]
+mod trace;
 
+#[cfg(feature = "async")]
+mod async_entity;
+mod audit;
+mod database;
 mod entity;
+mod entity_collection;
+mod entity_ref;
+mod entity_store;
 mod error;
 mod import_export;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod migration;
 mod query_builder;
+mod read_only;
 mod relation;
+mod save_batch;
+mod sibling_link;
+mod soft_delete;
+mod subscriber;
+mod transaction;
+mod ttl;
+mod unique;
+mod version;
+mod write;
+#[cfg(feature = "async")]
+pub use async_entity::AsyncEntity;
+pub use audit::{AuditEntry, AuditOperation};
+pub use database::{
+    backup, export_all, export_dot, export_dot_with_data, get_all_registered_stores, import_all,
+    list_all_relations_for_key, open_memory, open_with_config, register_all,
+    remove_all_relations_for_key, restore, validate_all,
+};
 pub use entity::AutoIncrementEntity;
-pub use entity::{AsBytes, Entity};
+pub use entity::{AsBytes, Entity, FromBytes, GenericStoreName, TreeStats};
+pub use entity_collection::EntityCollection;
+pub use entity_ref::EntityRef;
+#[cfg(feature = "cache")]
+pub use entity_store::CachedEntityStore;
+pub use entity_store::EntityStore;
+pub use import_export::{ConflictPolicy, FullDatabaseDump, ImportReport};
+#[cfg(feature = "metrics")]
+pub use metrics::{Metrics, NoOpMetrics, PrometheusMetrics};
+pub use migration::{get_schema_version, get_store_version, migrate, MigrationStep, RenameStore};
+#[cfg(feature = "messagepack")]
+pub use migration::migrate_serialization_format;
 pub use reindeer_macros::Entity;
 
 pub use query_builder::*;
-pub use relation::DeletionBehaviour;
+pub use read_only::{open_read_only, ReadOnlyDb};
+pub use relation::{
+    DeletionBehaviour, FamilyDescriptor, IntegrityIssue, RelationCreateResult, RelationWithPayload,
+};
+pub use save_batch::SaveBatch;
 pub use serde_derive::{Deserialize, Serialize};
+#[doc(hidden)]
+pub use serde as __serde;
+#[doc(hidden)]
+pub use unique::unique_value as __unique_value;
+pub use subscriber::{EntityEvent, EntitySubscriber};
+pub use transaction::{atomic_save, transaction, AtomicSaveBuilder, TransactionalDb};
+pub use ttl::{run_expiry_sweeper, sweep_expired, ExpirySweeper};
 
-pub use error::{Error, ErrorKind, Result};
+pub use error::{Error, ErrorContext, ErrorKind, Result};
 /// Opens a `sled` database to store Entities. The resulting Db object can be copied accross threads. This is a re-export of `sled::open`.
 ///
 pub use sled::open;
+/// `sled` database configuration, to be used with [`open_with_config`](fn.open_with_config.html). This is a re-export of `sled::Config`.
+///
+pub use sled::Config;
 /// `sled` database struct. It can be copied and used accross threads and is a central item to store entities. This is a re-export of `sled::Db`.
 ///
 pub use sled::Db;
@@ -0,0 +1,116 @@
+//! # Metrics Module
+//! This module provides the [`Metrics`](trait.Metrics.html) trait, to record entity operations made through an
+//! [`EntityStore`](struct.EntityStore.html).
+//!
+//! Only available with the `metrics` feature.
+
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry};
+
+/// Receives the operations made through an [`EntityStore`](struct.EntityStore.html) given to
+/// [`EntityStore::with_metrics`](struct.EntityStore.html#method.with_metrics).
+///
+/// ### Exemple
+/// ```rust
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use reindeer::Metrics;
+/// struct SaveCounter(AtomicUsize);
+///
+/// impl Metrics for SaveCounter {
+///     fn record_save(&self, _entity_type: &str) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+///     fn record_get(&self, _entity_type: &str) {}
+///     fn record_remove(&self, _entity_type: &str) {}
+///     fn record_latency_ns(&self, _operation: &str, _entity_type: &str, _latency_ns: u64) {}
+/// }
+/// ```
+pub trait Metrics: Send + Sync {
+    /// Called each time an entity of the store named `entity_type` is saved
+    fn record_save(&self, entity_type: &str);
+    /// Called each time an entity of the store named `entity_type` is retrieved by key
+    fn record_get(&self, entity_type: &str);
+    /// Called each time an entity of the store named `entity_type` is removed
+    fn record_remove(&self, entity_type: &str);
+    /// Called after each operation (`"save"`, `"get"` or `"remove"`), with its duration in nanoseconds
+    fn record_latency_ns(&self, operation: &str, entity_type: &str, latency_ns: u64);
+}
+
+/// [`Metrics`](trait.Metrics.html) implementation that records nothing. This is the default for an
+/// [`EntityStore`](struct.EntityStore.html).
+pub struct NoOpMetrics;
+
+impl Metrics for NoOpMetrics {
+    fn record_save(&self, _entity_type: &str) {}
+    fn record_get(&self, _entity_type: &str) {}
+    fn record_remove(&self, _entity_type: &str) {}
+    fn record_latency_ns(&self, _operation: &str, _entity_type: &str, _latency_ns: u64) {}
+}
+
+/// [`Metrics`](trait.Metrics.html) implementation exposing Prometheus metrics :
+///  - `reindeer_operations_total`, a counter of operations with `operation` and `entity_type` labels
+///  - `reindeer_operation_duration_seconds`, a histogram of operation durations with the same labels
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # use std::sync::Arc;
+/// # use reindeer::{EntityStore, PrometheusMetrics};
+/// let registry = prometheus::Registry::new();
+/// let store = EntityStore::<MyStruct>::new(&db)
+///     .with_metrics(Arc::new(PrometheusMetrics::register(&registry)?));
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub struct PrometheusMetrics {
+    operations: IntCounterVec,
+    durations: HistogramVec,
+}
+
+impl PrometheusMetrics {
+    /// Creates the metrics and registers them in `registry`.
+    pub fn register(registry: &Registry) -> prometheus::Result<Self> {
+        let operations = IntCounterVec::new(
+            Opts::new("reindeer_operations_total", "Number of entity operations"),
+            &["operation", "entity_type"],
+        )?;
+        let durations = HistogramVec::new(
+            HistogramOpts::new(
+                "reindeer_operation_duration_seconds",
+                "Duration of entity operations",
+            ),
+            &["operation", "entity_type"],
+        )?;
+        registry.register(Box::new(operations.clone()))?;
+        registry.register(Box::new(durations.clone()))?;
+        Ok(PrometheusMetrics {
+            operations,
+            durations,
+        })
+    }
+}
+
+impl Metrics for PrometheusMetrics {
+    fn record_save(&self, entity_type: &str) {
+        self.operations
+            .with_label_values(&["save", entity_type])
+            .inc();
+    }
+
+    fn record_get(&self, entity_type: &str) {
+        self.operations
+            .with_label_values(&["get", entity_type])
+            .inc();
+    }
+
+    fn record_remove(&self, entity_type: &str) {
+        self.operations
+            .with_label_values(&["remove", entity_type])
+            .inc();
+    }
+
+    fn record_latency_ns(&self, operation: &str, entity_type: &str, latency_ns: u64) {
+        self.durations
+            .with_label_values(&[operation, entity_type])
+            .observe(latency_ns as f64 / 1e9);
+    }
+}
@@ -0,0 +1,292 @@
+//! # Migration Module
+//! This module provides [`migrate`](fn.migrate.html), to run versioned schema migrations on a database.
+
+use std::collections::hash_map::{Entry, RandomState};
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::marker::PhantomData;
+use std::sync::{OnceLock, PoisonError, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sled::{Db, Tree};
+
+use crate::error::Result;
+#[cfg(feature = "messagepack")]
+use crate::write::EntityWriter;
+use crate::{Entity, Error, ErrorKind, FromBytes};
+
+const META_TREE: &str = "__$meta";
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+const STORE_VERSION_KEY_PREFIX: &str = "store_version_";
+const DATABASE_ID_KEY: &str = "database_id";
+#[cfg(feature = "messagepack")]
+const STORE_FORMAT_KEY_PREFIX: &str = "store_format_";
+/// Serialization format version recorded for the stores serialized as MessagePack
+#[cfg(feature = "messagepack")]
+const MESSAGEPACK_FORMAT: u32 = 1;
+
+/// A versioned migration step, to be run by [`migrate`](fn.migrate.html).
+///
+/// ### Exemple
+/// ```rust
+/// # use reindeer::{Db, MigrationStep, Result};
+/// # use reindeer::{Entity, Serialize, Deserialize};
+/// # #[derive(Serialize, Deserialize, Entity)]
+/// # #[entity(name = "user", version = 1)]
+/// # struct UserV1 { id : u32, name : String }
+/// # #[derive(Serialize, Deserialize, Entity)]
+/// # #[entity(name = "user", version = 2)]
+/// # struct UserV2 { id : u32, name : String, email : Option<String> }
+/// struct AddEmailToUsers;
+///
+/// impl MigrationStep for AddEmailToUsers {
+///     fn version(&self) -> u32 {
+///         2
+///     }
+///     fn run(&self, db: &Db) -> Result<()> {
+///         UserV2::migrate_field(|old: UserV1| UserV2 { id : old.id, name : old.name, email : None }, db)?;
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait MigrationStep {
+    /// The schema version the database is at once this step has been run. Versions must be unique and greater than `0`.
+    fn version(&self) -> u32;
+
+    /// Runs the migration.
+    fn run(&self, db: &Db) -> Result<()>;
+}
+
+fn get_meta_tree(db: &Db) -> Result<Tree> {
+    Ok(db.open_tree(META_TREE)?)
+}
+
+fn get_version(key: &str, db: &Db) -> Result<Option<u32>> {
+    get_meta_tree(db)?
+        .get(key)?
+        .map(|value| u32::from_bytes(&value))
+        .transpose()
+}
+
+/// Returns the identifier of the database, if it was already generated by `get_database_id`.
+pub(crate) fn find_database_id(db: &Db) -> Result<Option<u64>> {
+    get_meta_tree(db)?
+        .get(DATABASE_ID_KEY)?
+        .map(|id| u64::from_bytes(&id))
+        .transpose()
+}
+
+/// Returns a random identifier of the database, generated the first time it is needed, to tell apart the databases
+/// opened by the process.
+pub(crate) fn get_database_id(db: &Db) -> Result<u64> {
+    if let Some(id) = find_database_id(db)? {
+        return Ok(id);
+    }
+    let meta = get_meta_tree(db)?;
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0),
+    );
+    let id = hasher.finish();
+    match meta.compare_and_swap(
+        DATABASE_ID_KEY,
+        None as Option<&[u8]>,
+        Some(&id.to_be_bytes()),
+    )? {
+        Ok(()) => Ok(id),
+        Err(e) => u64::from_bytes(&e.current.unwrap_or_default()),
+    }
+}
+
+/// Returns the schema version of the database, as stored by [`migrate`](fn.migrate.html), or `0` if no migration was ever run.
+pub fn get_schema_version(db: &Db) -> Result<u32> {
+    Ok(get_version(SCHEMA_VERSION_KEY, db)?.unwrap_or(0))
+}
+
+/// Returns the [`SCHEMA_VERSION`](trait.Entity.html#associatedconstant.SCHEMA_VERSION) the store named `store_name`
+/// was last registered with, or `None` if it was never registered.
+pub fn get_store_version(store_name: &str, db: &Db) -> Result<Option<u32>> {
+    get_version(&format!("{}{}", STORE_VERSION_KEY_PREFIX, store_name), db)
+}
+
+/// Type registered for each store name and schema version in this process
+fn store_types() -> &'static RwLock<HashMap<(&'static str, u32), &'static str>> {
+    static TYPES: OnceLock<RwLock<HashMap<(&'static str, u32), &'static str>>> = OnceLock::new();
+    TYPES.get_or_init(Default::default)
+}
+
+/// Fails if another entity type with the same store name and schema version has already been registered, as both would
+/// silently share the same `sled` tree.
+pub(crate) fn check_store_name<E: Entity>() -> Result<()> {
+    let type_name = std::any::type_name::<E>();
+    let mut types = store_types()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner);
+    match types.entry((E::store_name(), E::SCHEMA_VERSION)) {
+        Entry::Occupied(entry) if *entry.get() != type_name => Err(Error::new(
+            ErrorKind::StoreNameConflict,
+            format!(
+                "{} and {} have the same store name {} and version {}",
+                entry.get(),
+                type_name,
+                E::store_name(),
+                E::SCHEMA_VERSION
+            ),
+        )),
+        Entry::Occupied(_) => Ok(()),
+        Entry::Vacant(entry) => {
+            entry.insert(type_name);
+            Ok(())
+        }
+    }
+}
+
+pub(crate) fn check_store_version<E: Entity>(db: &Db) -> Result<()> {
+    match get_store_version(E::store_name(), db)? {
+        Some(version) if version >= E::SCHEMA_VERSION => return Ok(()),
+        Some(version) => E::migrate_from(version, db)?,
+        // Entities saved before store versions were recorded
+        None if E::SCHEMA_VERSION > 0 && !E::get_tree(db)?.is_empty() => E::migrate_from(0, db)?,
+        None => {}
+    }
+    get_meta_tree(db)?.insert(
+        format!("{}{}", STORE_VERSION_KEY_PREFIX, E::store_name()),
+        &E::SCHEMA_VERSION.to_be_bytes(),
+    )?;
+    Ok(())
+}
+
+/// Records that the store of `E` is serialized as MessagePack if it is empty, so that it is never migrated by
+/// [`migrate_serialization_format`](fn.migrate_serialization_format.html).
+#[cfg(feature = "messagepack")]
+pub(crate) fn check_store_format<E: Entity>(db: &Db) -> Result<()> {
+    let key = format!("{}{}", STORE_FORMAT_KEY_PREFIX, E::store_name());
+    if get_version(&key, db)?.is_none() && E::get_tree(db)?.is_empty() {
+        get_meta_tree(db)?.insert(key, &MESSAGEPACK_FORMAT.to_be_bytes())?;
+    }
+    Ok(())
+}
+
+/// Rewrites every entity of the store of `T` from `bincode` (the default format) to MessagePack, and returns the
+/// number of migrated entities.
+///
+/// The format of the store is then recorded in the database, and later calls do nothing and return `0`. Stores that
+/// were empty when `T` was first registered with the `messagepack` feature enabled are recorded as MessagePack from the
+/// start. All entities are converted in a single transaction : if any of them cannot be read as `bincode`, this results
+/// in an error and nothing is written.
+///
+/// This is meant to be used once on existing databases after enabling the `messagepack` feature, which is the only case
+/// where it is available, before any entity of `T` is saved.
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// reindeer::migrate_serialization_format::<MyStruct>(&db)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+#[cfg(feature = "messagepack")]
+pub fn migrate_serialization_format<T: Entity>(db: &Db) -> Result<usize> {
+    let format_key = format!("{}{}", STORE_FORMAT_KEY_PREFIX, T::store_name());
+    if get_version(&format_key, db)? == Some(MESSAGEPACK_FORMAT) {
+        return Ok(0);
+    }
+    let tree = T::get_tree(db)?;
+    let mut entries = Vec::new();
+    for elem in tree.iter() {
+        let (key, value) = elem?;
+        let entity = bincode::deserialize::<T>(&value)?;
+        entries.push((key, entity.try_into_ivec_with_limit(T::MAX_SIZE_BYTES)?));
+    }
+    EntityWriter::transaction(&[tree, get_meta_tree(db)?], |trees| {
+        for (key, value) in &entries {
+            trees[0].insert(key, value.clone())?;
+        }
+        trees[1].insert(format_key.as_bytes(), &MESSAGEPACK_FORMAT.to_be_bytes())?;
+        Ok(())
+    })?;
+    Ok(entries.len())
+}
+
+/// Runs, in version order, every step of `steps` whose version is greater than the schema version of the database.
+///
+/// The schema version is updated after each successful step : if a step fails, the error is returned, and the database
+/// is left at the version of the last successful step, so that calling `migrate` again resumes from the failed step.
+///
+/// This should be called once the database is opened, before any other access to the migrated stores.
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # use reindeer::{Db, MigrationStep};
+/// # struct AddEmailToUsers;
+/// # impl MigrationStep for AddEmailToUsers {
+/// #     fn version(&self) -> u32 { 2 }
+/// #     fn run(&self, _db: &Db) -> reindeer::Result<()> { Ok(()) }
+/// # }
+/// # struct RenameCustomers;
+/// # impl MigrationStep for RenameCustomers {
+/// #     fn version(&self) -> u32 { 3 }
+/// #     fn run(&self, db: &Db) -> reindeer::Result<()> { Customer::rename_store("client", db).map(|_| ()) }
+/// # }
+/// reindeer::migrate(&[Box::new(AddEmailToUsers), Box::new(RenameCustomers)], &db)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn migrate(steps: &[Box<dyn MigrationStep>], db: &Db) -> Result<()> {
+    let current_version = get_schema_version(db)?;
+    let mut steps: Vec<&dyn MigrationStep> = steps
+        .iter()
+        .map(|step| step.as_ref())
+        .filter(|step| step.version() > current_version)
+        .collect();
+    steps.sort_by_key(|step| step.version());
+    let meta_tree = get_meta_tree(db)?;
+    for step in steps {
+        step.run(db)?;
+        meta_tree.insert(SCHEMA_VERSION_KEY, &step.version().to_be_bytes())?;
+    }
+    Ok(())
+}
+
+/// A [`MigrationStep`](trait.MigrationStep.html) moving the data of a store to the store of `E` when its name changes,
+/// with [`Entity::rename_store`](trait.Entity.html#method.rename_store).
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # use reindeer::RenameStore;
+/// reindeer::migrate(&[Box::new(RenameStore::<Customer>::new(3, "client"))], &db)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub struct RenameStore<E> {
+    version: u32,
+    old_name: String,
+    entity_type: PhantomData<E>,
+}
+
+impl<E: Entity> RenameStore<E> {
+    /// Creates a migration step at schema version `version`, moving the store named `old_name` to the store of `E`.
+    pub fn new(version: u32, old_name: &str) -> Self {
+        RenameStore {
+            version,
+            old_name: String::from(old_name),
+            entity_type: PhantomData,
+        }
+    }
+}
+
+impl<E: Entity> MigrationStep for RenameStore<E> {
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn run(&self, db: &Db) -> Result<()> {
+        E::rename_store(&self.old_name, db)?;
+        Ok(())
+    }
+}
@@ -1,5 +1,8 @@
+use std::cmp::Ordering;
+
 use sled::Db;
 
+use crate::write::{EntityWriter, Removal};
 use crate::{relation::Relation, AsBytes, Entity, Result};
 
 /// `QueryBuilder` is a convenient way to build query to target several conditions without the need to immediately
@@ -11,12 +14,19 @@ use crate::{relation::Relation, AsBytes, Entity, Result};
 /// 
 /// For instance, this will list all students older than 18 belonging to a given school and members of a given club.
 /// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # use reindeer::QueryBuilder;
+/// # let school_id = 1u32;
+/// # let club_id = 4u32;
 /// let students = QueryBuilder::new()
 ///     .with_parent(&school_id)
 ///     .with_named_relation_to::<Club>(&club_id, "member")
-///     .get_with_filter(|s : &Student| s.age > 18,&data.db)?;
+///     .get_with_filter(|s : &Student| s.age > 18,&db)?;
+/// # Ok::<(), reindeer::Error>(())
 /// ```
 /// 
+#[derive(Default)]
 pub struct QueryBuilder<'a> {
     ids: Vec<Vec<u8>>,
     parent: Option<Vec<u8>>,
@@ -91,9 +101,14 @@ impl<'a> QueryBuilder<'a> {
 
     /// Executes the query, filters it given a filter function, and returns the result as a Vec of the chosen entity.
     /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # use reindeer::QueryBuilder;
+    /// # let school_id = 1u32;
     /// let students = QueryBuilder::new()
     ///     .with_parent(&school_id)
-    ///     .get_with_filter(|s : &Student| s.age > 18,&data.db)?;
+    ///     .get_with_filter(|s : &Student| s.age > 18,&db)?;
+    /// # Ok::<(), reindeer::Error>(())
     /// ```
     /// Note that we precise the type of the closure's parameter to let Rust infer the generic types of this function.
     pub fn get_with_filter<T : Entity, F : Fn(&T) -> bool>(&self, filter : F, db : &Db) -> Result<Vec<T>>  {
@@ -125,7 +140,7 @@ impl<'a> QueryBuilder<'a> {
                 let related_ids = self.get_related_ids::<T>(db)?;
                 let mut target_ids = Vec::new();
                 for related_id in related_ids {
-                    if self.ids.contains(&related_id.as_ref()) {
+                    if self.ids.contains(&related_id) {
                         target_ids.push(related_id.clone());
                     }
                 }
@@ -159,3 +174,265 @@ impl<'a> QueryBuilder<'a> {
         Ok(target_ids)
     }
 }
+
+type Filter<'a, T> = Box<dyn Fn(&T) -> bool + 'a>;
+type Comparator<'a, T> = Box<dyn Fn(&T, &T) -> Ordering + 'a>;
+
+/// Sort order to be used in [`EntityQuery::order_by`](struct.EntityQuery.html#method.order_by)
+/// and [`EntityQuery::then_by`](struct.EntityQuery.html#method.then_by).
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SortOrder {
+    /// Smallest values first
+    Ascending,
+    /// Greatest values first
+    Descending,
+}
+
+/// `EntityQuery` is a composable query on a single entity store.
+///
+/// Conditions are accumulated with `filter`, and the query is lazily evaluated over the store
+/// when calling one of `execute`, `count`, `exists` or `first`, providing the Db instance to run it on.
+/// `limit` and `offset` can be used to paginate the results, and `order_by` and `then_by` to sort them.
+///
+/// For instance, this will get the 10 first students older than 18 that are named "John".
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # use reindeer::EntityQuery;
+/// let students = EntityQuery::<Student>::new()
+///     .filter(|s| s.age > 18)
+///     .filter(|s| s.name == "John")
+///     .limit(10)
+///     .execute(&db)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+///
+/// ⚠ This will effectively iterate over the store until enough matching entities are found.
+/// Prefer [QueryBuilder](struct.QueryBuilder.html) if the targeted entities can be narrowed down
+/// using a set of ids, a parent, or relations.
+pub struct EntityQuery<'a, T: Entity> {
+    filters: Vec<Filter<'a, T>>,
+    sorters: Vec<Comparator<'a, T>>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+impl<'a, T: Entity> Default for EntityQuery<'a, T> {
+    fn default() -> Self {
+        EntityQuery {
+            filters: Vec::new(),
+            sorters: Vec::new(),
+            limit: None,
+            offset: 0,
+        }
+    }
+}
+
+impl<'a, T: Entity> EntityQuery<'a, T> {
+    /// Creates a new query matching every entity of the store.
+    pub fn new() -> EntityQuery<'a, T> {
+        Self::default()
+    }
+
+    /// Adds a condition that entities must match. This can be used multiple times,
+    /// in which case entities must match every condition.
+    pub fn filter<F: Fn(&T) -> bool + 'a>(&mut self, f: F) -> &mut EntityQuery<'a, T> {
+        self.filters.push(Box::new(f));
+        self
+    }
+
+    /// Limits the number of entities returned by the query.
+    pub fn limit(&mut self, limit: usize) -> &mut EntityQuery<'a, T> {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Skips the `offset` first matching entities.
+    pub fn offset(&mut self, offset: usize) -> &mut EntityQuery<'a, T> {
+        self.offset = offset;
+        self
+    }
+
+    /// Sorts the results of the query using a value extracted from each entity.
+    /// This replaces any sort previously defined on this query.
+    ///
+    /// When combined with `limit`, only the `offset + limit` first entities are kept in memory while
+    /// iterating over the store.
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # use reindeer::{EntityQuery, SortOrder};
+    /// let last_posts = EntityQuery::<Post>::new()
+    ///     .order_by(|p| p.created_at, SortOrder::Descending)
+    ///     .limit(10)
+    ///     .execute(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    pub fn order_by<K: Ord, F: Fn(&T) -> K + 'a>(
+        &mut self,
+        f: F,
+        order: SortOrder,
+    ) -> &mut EntityQuery<'a, T> {
+        self.sorters.clear();
+        self.then_by(f, order)
+    }
+
+    /// Adds a secondary sort, used to order entities for which all previous sorts are equal.
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # use reindeer::{EntityQuery, SortOrder};
+    /// let students = EntityQuery::<Student>::new()
+    ///     .order_by(|s| s.age, SortOrder::Descending)
+    ///     .then_by(|s| s.name.clone(), SortOrder::Ascending)
+    ///     .execute(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    pub fn then_by<K: Ord, F: Fn(&T) -> K + 'a>(
+        &mut self,
+        f: F,
+        order: SortOrder,
+    ) -> &mut EntityQuery<'a, T> {
+        self.sorters.push(Box::new(move |a, b| match order {
+            SortOrder::Ascending => f(a).cmp(&f(b)),
+            SortOrder::Descending => f(b).cmp(&f(a)),
+        }));
+        self
+    }
+
+    /// Executes the query and returns the result as a Vec of the chosen entity.
+    pub fn execute(&self, db: &Db) -> Result<Vec<T>> {
+        if self.sorters.is_empty() {
+            self.iter(db)?.collect()
+        } else {
+            self.sorted(self.limit, db)
+        }
+    }
+
+    /// Executes the query and returns the number of matching entities.
+    pub fn count(&self, db: &Db) -> Result<usize> {
+        self.iter(db)?.try_fold(0, |count, e| e.map(|_| count + 1))
+    }
+
+    /// Checks if at least one entity matches the query.
+    pub fn exists(&self, db: &Db) -> Result<bool> {
+        Ok(self.iter(db)?.next().transpose()?.is_some())
+    }
+
+    /// Executes the query and returns the first matching entity.
+    pub fn first(&self, db: &Db) -> Result<Option<T>> {
+        if self.sorters.is_empty() {
+            self.iter(db)?.next().transpose()
+        } else {
+            Ok(self.sorted(Some(1), db)?.into_iter().next())
+        }
+    }
+
+    /// Removes every entity matching the query, and returns the number of removed entities.
+    /// `order_by`, `limit` and `offset` are taken into account to choose which entities are removed.
+    ///
+    /// Deletion behaviours are checked for every entity before removing anything :
+    /// if any of them cannot be removed (`DeletionBehaviour::Error` found in the relation hierarchy),
+    /// this will result in an error and no entity will be removed. Relations between removed entities are ignored,
+    /// and all of them are then removed in a single transaction.
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # use reindeer::EntityQuery;
+    /// let removed_count = EntityQuery::<Student>::new()
+    ///     .filter(|s| s.age > 18)
+    ///     .delete_all(&db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    pub fn delete_all(&self, db: &Db) -> Result<usize> {
+        let keys = self
+            .execute(db)?
+            .iter()
+            .map(|e| e.get_key().as_bytes())
+            .collect::<Vec<Vec<u8>>>();
+        let mut removal = Removal::default();
+        for key in &keys {
+            removal.add::<T>(key);
+        }
+        removal.commit(db)?;
+        Ok(keys.len())
+    }
+
+    /// Applies `modifier` to every entity matching the query and saves them all at once,
+    /// then returns the number of updated entities.
+    /// `order_by`, `limit` and `offset` are taken into account to choose which entities are updated.
+    /// ```rust
+    /// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # use reindeer::EntityQuery;
+    /// let updated_count = EntityQuery::<Student>::new()
+    ///     .filter(|s| s.age > 18)
+    ///     .update_all(|s| s.adult = true, &db)?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    pub fn update_all<M: Fn(&mut T)>(&self, modifier: M, db: &Db) -> Result<usize> {
+        let mut entries = Vec::new();
+        for mut entity in self.execute(db)? {
+            modifier(&mut entity);
+            entries.push((
+                entity.get_key().as_bytes(),
+                entity.try_into_ivec_with_limit(T::MAX_SIZE_BYTES)?,
+            ));
+        }
+        EntityWriter::save::<T>(&entries, db)?;
+        Ok(entries.len())
+    }
+
+    fn matches(&self, entity: &T) -> bool {
+        self.filters.iter().all(|f| f(entity))
+    }
+
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.sorters
+            .iter()
+            .map(|sorter| sorter(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+
+    fn matching<'q>(&'q self, db: &Db) -> Result<impl Iterator<Item = Result<T>> + 'q> {
+        Ok(T::get_tree(db)?
+            .iter()
+            .map(|elem| -> Result<T> { T::try_from_ivec(elem?.1) })
+            .filter(move |e| match e {
+                Ok(v) => self.matches(v),
+                Err(_) => true,
+            }))
+    }
+
+    fn iter<'q>(&'q self, db: &Db) -> Result<impl Iterator<Item = Result<T>> + 'q> {
+        Ok(self
+            .matching(db)?
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX)))
+    }
+
+    fn sorted(&self, limit: Option<usize>, db: &Db) -> Result<Vec<T>> {
+        let mut result = Vec::new();
+        match limit {
+            None => {
+                for entity in self.matching(db)? {
+                    result.push(entity?);
+                }
+                result.sort_by(|a, b| self.compare(a, b));
+            }
+            Some(limit) => {
+                let capacity = self.offset + limit;
+                for entity in self.matching(db)? {
+                    let entity = entity?;
+                    let index = result.partition_point(|e| self.compare(e, &entity).is_le());
+                    if index < capacity {
+                        result.insert(index, entity);
+                        result.truncate(capacity);
+                    }
+                }
+            }
+        }
+        Ok(result.into_iter().skip(self.offset).collect())
+    }
+}
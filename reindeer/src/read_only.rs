@@ -0,0 +1,112 @@
+//! # Read-only Module
+//! This module provides [`ReadOnlyDb`](struct.ReadOnlyDb.html), a database handle that only allows reading entities.
+
+use std::path::Path;
+
+use sled::{Db, Tree};
+
+use crate::database::open_after_close;
+use crate::entity::AsBytes;
+use crate::error::Result;
+use crate::{Entity, Error, ErrorKind};
+
+/// Database handle that only exposes reading methods.
+///
+/// The underlying `Db` is never exposed, so none of the [`Entity`](trait.Entity.html) methods that would modify the
+/// database can be called with it : nothing is written through this handle, and stores that do not exist are considered
+/// empty instead of being created.
+///
+/// ⚠ `sled` has no read-only mode : its files are still opened for writing, and recovering a database that was not
+/// closed properly writes to them when it is opened.
+///
+/// It is obtained with [`open_read_only`](fn.open_read_only.html).
+pub struct ReadOnlyDb {
+    db: Db,
+}
+
+impl ReadOnlyDb {
+    fn tree<E: Entity>(&self) -> Result<Option<Tree>> {
+        if self
+            .db
+            .tree_names()
+            .iter()
+            .any(|name| name == E::store_name().as_bytes())
+        {
+            Ok(Some(self.db.open_tree(E::store_name())?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Retrieves an entity instance given its key.
+    ///
+    /// See [`Entity::get`](trait.Entity.html#method.get).
+    pub fn get<E: Entity>(&self, key: &E::Key) -> Result<Option<E>> {
+        match self.tree::<E>()? {
+            Some(tree) => tree.get(key.as_bytes())?.map(E::try_from_ivec).transpose(),
+            None => Ok(None),
+        }
+    }
+
+    /// Retrieves all entities of a given type.
+    ///
+    /// See [`Entity::get_all`](trait.Entity.html#method.get_all).
+    pub fn get_all<E: Entity>(&self) -> Result<Vec<E>> {
+        self.get_with_filter(|_: &E| true)
+    }
+
+    /// Gets all entities of a given type matching a condition materialized as a function returning a boolean.
+    ///
+    /// See [`Entity::get_with_filter`](trait.Entity.html#method.get_with_filter).
+    pub fn get_with_filter<E: Entity, F: Fn(&E) -> bool>(&self, f: F) -> Result<Vec<E>> {
+        let mut entities = Vec::new();
+        if let Some(tree) = self.tree::<E>()? {
+            for elem in tree.iter() {
+                let entity = E::try_from_ivec(elem?.1)?;
+                if f(&entity) {
+                    entities.push(entity);
+                }
+            }
+        }
+        Ok(entities)
+    }
+
+    /// Returns the number of saved instances for a given entity type.
+    pub fn get_count<E: Entity>(&self) -> Result<usize> {
+        Ok(self.tree::<E>()?.map(|tree| tree.len()).unwrap_or(0))
+    }
+
+    /// Checks if an entity exists, without fetching it.
+    pub fn exists<E: Entity>(&self, key: &E::Key) -> Result<bool> {
+        match self.tree::<E>()? {
+            Some(tree) => Ok(tree.contains_key(key.as_bytes())?),
+            None => Ok(false),
+        }
+    }
+}
+
+/// Opens an existing database at `path` for reading only.
+///
+/// ⚠ `sled` does not support sharing a database between processes : this will fail if the database is
+/// already opened elsewhere. A database that was just closed can still be locked for a moment by `sled`'s background
+/// threads, so acquiring its lock is retried for up to half a second.
+///
+/// ### Exemple
+/// ```rust,no_run
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # use std::path::Path;
+/// let db = reindeer::open_read_only(Path::new("./my-db"))?;
+/// let my_struct = db.get::<MyStruct>(&3)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn open_read_only(path: &Path) -> Result<ReadOnlyDb> {
+    if !path.exists() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("No database found at {}", path.display()),
+        ));
+    }
+    Ok(ReadOnlyDb {
+        db: open_after_close(&sled::Config::new().path(path).flush_every_ms(None))?,
+    })
+}
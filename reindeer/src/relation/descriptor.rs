@@ -4,9 +4,11 @@ use std::collections::HashMap;
 use std::hash::BuildHasherDefault;
 
 use crate::entity::AsBytes;
-use crate::Entity;
+use crate::error::Result;
+use crate::{Entity, Error, ErrorKind};
+use sled::{Db, Tree};
 
-use super::DeletionBehaviour;
+use super::{DeletionBehaviour, IntegrityIssue, RELATION_TREE_PREFIX};
 
 #[doc(hidden)]
 pub type RelationMap = HashMap<String, Vec<RelationDescriptor>, BuildHasherDefault<FxHasher>>;
@@ -39,11 +41,18 @@ impl RelationDescriptor {
     }
 }
 
-#[doc(hidden)]
-#[derive(Serialize, Deserialize, Default)]
+/// Sibling and child stores of an entity store, with their deletion behaviours, as returned by
+/// [`Entity::family_descriptor`](trait.Entity.html#method.family_descriptor).
+///
+/// Descriptors are saved by [`Entity::register`](trait.Entity.html#method.register) or
+/// [`register_all`](fn.register_all.html), and are used to check integrity when an entity is removed.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub struct FamilyDescriptor {
+    /// Name of the store
     pub tree_name: String,
+    /// Sibling stores, and the behaviour to apply to siblings when an entity of this store is removed
     pub sibling_trees: Vec<(String, DeletionBehaviour)>,
+    /// Child stores, and the behaviour to apply to children when an entity of this store is removed
     pub child_trees: Vec<(String, DeletionBehaviour)>,
 }
 
@@ -79,6 +88,13 @@ impl EntityRelations {
         }
     }
 
+    pub fn contains(&self, tree_name: &str, key: &[u8], name: Option<&str>) -> bool {
+        self.related_entities
+            .get(tree_name)
+            .map(|v| v.iter().any(|rd| rd.key == key && rd.name.as_deref() == name))
+            .unwrap_or(false)
+    }
+
     pub fn remove_related_by_key<E: Entity>(&mut self, e: &[u8]) {
         self.remove_related_by_key_and_tree_name(E::store_name(), e)
     }
@@ -89,7 +105,7 @@ impl EntityRelations {
 
     pub fn remove_related_by_key_and_tree_name(&mut self, tree: &str, e: &[u8]) {
         if let Some(v) = self.related_entities.get_mut(tree) {
-            v.retain(|rd| rd.key.to_ascii_lowercase() != e.to_ascii_lowercase());
+            v.retain(|rd| !rd.key.eq_ignore_ascii_case(e));
         }
     }
 
@@ -101,7 +117,7 @@ impl EntityRelations {
     ) {
         if let Some(v) = self.related_entities.get_mut(tree) {
             v.retain(|rd| {
-                rd.key.to_ascii_lowercase() != e.to_ascii_lowercase()
+                !rd.key.eq_ignore_ascii_case(e)
                     && if let Some(r_name) = &rd.name {
                         name == r_name
                     } else {
@@ -126,8 +142,152 @@ impl EntityRelations {
     }
 }
 
-#[doc(hidden)]
-impl Entity for FamilyDescriptor {
+impl FamilyDescriptor {
+    pub(crate) fn get(tree_name: &str, db: &Db) -> Result<Option<FamilyDescriptor>> {
+        Ok(StoredFamilyDescriptor::get(&String::from(tree_name), db)?.map(|stored| stored.0))
+    }
+
+    pub(crate) fn exists(tree_name: &str, db: &Db) -> Result<bool> {
+        StoredFamilyDescriptor::exists(&String::from(tree_name), db)
+    }
+
+    pub(crate) fn save(&self, db: &Db) -> Result<()> {
+        StoredFamilyDescriptor(self.clone()).save(db)
+    }
+
+    pub(crate) fn list_all(db: &Db) -> Result<Vec<FamilyDescriptor>> {
+        Ok(StoredFamilyDescriptor::get_all(db)?
+            .into_iter()
+            .map(|stored| stored.0)
+            .collect())
+    }
+
+    pub(crate) fn validate_all(db: &Db) -> Result<Vec<IntegrityIssue>> {
+        let descriptors = FamilyDescriptor::list_all(db)?;
+        let tree_names = db.tree_names();
+        let mut issues = Vec::new();
+        for descriptor in &descriptors {
+            if !tree_names
+                .iter()
+                .any(|name| name == descriptor.tree_name.as_bytes())
+            {
+                issues.push(IntegrityIssue::MissingTree {
+                    store: descriptor.tree_name.clone(),
+                });
+            }
+            for (store, _) in descriptor
+                .sibling_trees
+                .iter()
+                .chain(&descriptor.child_trees)
+            {
+                if !descriptors.iter().any(|other| &other.tree_name == store) {
+                    issues.push(IntegrityIssue::UnregisteredStore {
+                        store: store.clone(),
+                        referenced_by: descriptor.tree_name.clone(),
+                    });
+                }
+            }
+        }
+        let existing_tree = |store: &str| -> Result<Option<Tree>> {
+            if tree_names.iter().any(|name| name == store.as_bytes()) {
+                Ok(Some(db.open_tree(store)?))
+            } else {
+                Ok(None)
+            }
+        };
+        let contains = |tree: &Option<Tree>, key: &[u8]| -> Result<bool> {
+            match tree {
+                Some(tree) => Ok(tree.contains_key(key)?),
+                None => Ok(false),
+            }
+        };
+        for tree_name in &tree_names {
+            let store = match std::str::from_utf8(tree_name)
+                .ok()
+                .and_then(|name| name.strip_prefix(RELATION_TREE_PREFIX))
+            {
+                Some(store) => store,
+                None => continue,
+            };
+            let entity_tree = existing_tree(store)?;
+            for elem in db.open_tree(tree_name)?.iter() {
+                let (key, relations) = elem?;
+                if !contains(&entity_tree, &key)? {
+                    issues.push(IntegrityIssue::MissingEntity {
+                        store: String::from(store),
+                        key: key.to_vec(),
+                    });
+                }
+                let relations = bincode::deserialize::<EntityRelations>(&relations)?;
+                for (target_store, related_keys) in &relations.related_entities {
+                    let target_tree = existing_tree(target_store)?;
+                    for rd in related_keys {
+                        if !contains(&target_tree, &rd.key)? {
+                            issues.push(IntegrityIssue::BrokenRelation {
+                                store: String::from(store),
+                                key: key.to_vec(),
+                                target_store: target_store.clone(),
+                                target_key: rd.key.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        Ok(issues)
+    }
+
+    pub(crate) fn rename(old_name: &str, new_name: &str, db: &Db) -> Result<()> {
+        for mut descriptor in FamilyDescriptor::list_all(db)? {
+            if descriptor.tree_name == old_name {
+                StoredFamilyDescriptor::get_tree(db)?.remove(descriptor.tree_name.as_bytes())?;
+                if FamilyDescriptor::exists(new_name, db)? {
+                    continue;
+                }
+                descriptor.tree_name = String::from(new_name);
+            }
+            for (tree_name, _) in descriptor
+                .sibling_trees
+                .iter_mut()
+                .chain(descriptor.child_trees.iter_mut())
+            {
+                if tree_name == old_name {
+                    *tree_name = String::from(new_name);
+                }
+            }
+            descriptor.save(db)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn are_siblings(tree1: &str, tree2: &str, db: &Db) -> Result<bool> {
+        let declares = |tree: &str, sibling: &str| -> Result<bool> {
+            Ok(FamilyDescriptor::get(tree, db)?
+                .map(|desc| desc.sibling_trees.iter().any(|(name, _)| name == sibling))
+                .unwrap_or(false))
+        };
+        Ok(declares(tree1, tree2)? || declares(tree2, tree1)?)
+    }
+
+    pub(crate) fn check_siblings(tree1: &str, tree2: &str, db: &Db) -> Result<()> {
+        if FamilyDescriptor::are_siblings(tree1, tree2, db)? {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::IntegrityError,
+                format!("{} is not registered as a sibling of {}", tree2, tree1),
+            ))
+        }
+    }
+}
+
+/// Family descriptors as saved in the database. This wrapper keeps the `Entity` implementation out of the public API of
+/// `FamilyDescriptor`, and is serialized exactly like it.
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+struct StoredFamilyDescriptor(FamilyDescriptor);
+
+impl Entity for StoredFamilyDescriptor {
     type Key = String;
 
     fn store_name() -> &'static str {
@@ -135,10 +295,10 @@ impl Entity for FamilyDescriptor {
     }
 
     fn get_key(&self) -> &Self::Key {
-        &self.tree_name
+        &self.0.tree_name
     }
 
     fn set_key(&mut self, key: &Self::Key) {
-        self.tree_name = key.clone();
+        self.0.tree_name = key.clone();
     }
 }
@@ -1,16 +1,178 @@
 mod descriptor;
 use crate::entity::{AsBytes, Entity};
-use crate::error::Result;
+use crate::entity_ref::EntityRef;
+use crate::error::{entity_not_found, ErrorContext, Result};
+use crate::import_export::move_tree;
+use crate::migration::{find_database_id, get_database_id};
+use crate::sibling_link::SiblingLink;
 use crate::{Error, ErrorKind};
+use serde::de::DeserializeOwned;
 use serde_derive::{Deserialize, Serialize};
-use sled::Db;
+use crate::write::{EntityWriter, TreeSet};
+use sled::transaction::{ConflictableTransactionError, ConflictableTransactionResult, TransactionalTree};
+use sled::{Batch, Db, IVec, Transactional};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
 
 pub use self::descriptor::FamilyDescriptor;
+#[allow(unused_imports)]
 pub use self::descriptor::{EntityRelations, RelationMap};
 
+const RELATION_TREE_PREFIX: &str = "__$rel_";
+
+type SetNullFn = fn(&[u8], &str, &Db) -> Result<()>;
+type CustomFn = Arc<dyn Fn(&[u8], &Db) -> Result<()> + Send + Sync>;
+type PayloadKey = (Vec<u8>, String, Vec<u8>, Option<String>);
+/// Database id, store name and related store name
+type CustomKey = (u64, String, String);
+
+fn set_null_registry() -> &'static RwLock<HashMap<String, SetNullFn>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<String, SetNullFn>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Custom deletion functions, as they cannot be saved in family descriptors
+fn custom_registry() -> &'static RwLock<HashMap<CustomKey, CustomFn>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<CustomKey, CustomFn>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+fn unregistered_custom() -> CustomFn {
+    Arc::new(|_, _| {
+        Err(Error::new(
+            ErrorKind::IntegrityError,
+            String::from("Custom deletion behaviour was not registered"),
+        ))
+    })
+}
+
 pub struct Relation;
 
 impl Relation {
+    pub fn register_set_null<E: Entity>() {
+        set_null_registry()
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(
+                String::from(E::store_name()),
+                E::set_field_to_none_from_u8_array,
+            );
+    }
+
+    pub fn register_custom(descriptor: &FamilyDescriptor, db: &Db) -> Result<()> {
+        if !descriptor
+            .sibling_trees
+            .iter()
+            .chain(&descriptor.child_trees)
+            .any(|(_, behaviour)| matches!(behaviour, DeletionBehaviour::Custom(_)))
+        {
+            return Ok(());
+        }
+        let database_id = get_database_id(db)?;
+        let mut registry = custom_registry()
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        for (other_tree_name, behaviour) in descriptor
+            .sibling_trees
+            .iter()
+            .chain(&descriptor.child_trees)
+        {
+            if let DeletionBehaviour::Custom(f) = behaviour {
+                registry.insert(
+                    (
+                        database_id,
+                        descriptor.tree_name.clone(),
+                        other_tree_name.clone(),
+                    ),
+                    f.clone(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Puts back the custom behaviours registered for `db` in `descriptor`, so that registering an entity again does
+    /// not replace them with the behaviours it declares.
+    pub fn keep_custom(descriptor: &mut FamilyDescriptor, db: &Db) -> Result<()> {
+        let database_id = match find_database_id(db)? {
+            Some(database_id) => database_id,
+            None => return Ok(()),
+        };
+        let registry = custom_registry()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        for (other_tree_name, behaviour) in descriptor
+            .sibling_trees
+            .iter_mut()
+            .chain(descriptor.child_trees.iter_mut())
+        {
+            if let Some(f) = registry.get(&(
+                database_id,
+                descriptor.tree_name.clone(),
+                other_tree_name.clone(),
+            )) {
+                *behaviour = DeletionBehaviour::Custom(f.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn get_custom(tree_name: &str, other_tree_name: &str, db: &Db) -> Result<DeletionBehaviour> {
+        let database_id = find_database_id(db)?;
+        let registry = custom_registry()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        database_id
+            .and_then(|database_id| {
+                registry.get(&(
+                    database_id,
+                    String::from(tree_name),
+                    String::from(other_tree_name),
+                ))
+            })
+            .map(|f| DeletionBehaviour::Custom(f.clone()))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::UnregisteredEntity,
+                    format!(
+                        "No custom deletion behaviour registered from {} to {}",
+                        tree_name, other_tree_name
+                    ),
+                )
+            })
+    }
+
+    fn check_free_behaviours(behaviours: [&DeletionBehaviour; 2]) -> Result<()> {
+        if behaviours
+            .iter()
+            .any(|b| matches!(b, DeletionBehaviour::Custom(_)))
+        {
+            return Err(Error::new(
+                ErrorKind::IntegrityError,
+                String::from(
+                    "Custom deletion behaviours can only be used for sibling and child stores",
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn set_null(tree_name: &str, key: &[u8], field: &str, db: &Db) -> Result<()> {
+        let set_null = set_null_registry()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(tree_name)
+            .copied();
+        match set_null {
+            Some(set_null) => set_null(key, field, db),
+            None => Err(Error::new(
+                ErrorKind::UnregisteredEntity,
+                format!("Trying to use unregistered entity {}", tree_name),
+            )),
+        }
+    }
+
     pub fn create<E1: Entity, E2: Entity>(
         e1: &E1,
         e2: &E2,
@@ -18,9 +180,94 @@ impl Relation {
         e2_to_e1: DeletionBehaviour,
         name: Option<&str>,
         db: &Db,
+    ) -> Result<RelationCreateResult> {
+        Relation::check_free_behaviours([&e1_to_e2, &e2_to_e1])?;
+        let e1_to_e2_is_new = Relation::create_link(e1, e2, e1_to_e2, name, db)?;
+        let e2_to_e1_is_new = Relation::create_link(e2, e1, e2_to_e1, name, db)?;
+        Ok(RelationCreateResult {
+            was_new: e1_to_e2_is_new || e2_to_e1_is_new,
+        })
+    }
+
+    pub fn create_with_payload<E1: Entity, E2: Entity, P: serde::Serialize>(
+        e1: &E1,
+        e2: &E2,
+        payload: &P,
+        e1_to_e2: DeletionBehaviour,
+        e2_to_e1: DeletionBehaviour,
+        name: Option<&str>,
+        db: &Db,
+    ) -> Result<RelationCreateResult> {
+        Relation::check_free_behaviours([&e1_to_e2, &e2_to_e1])?;
+        let e1_key = e1.get_key().as_bytes();
+        let e2_key = e2.get_key().as_bytes();
+        let payload = bincode::serialize(&RelationWithPayload {
+            name: name.map(String::from),
+            payload,
+        })?;
+        let e1_payload_key = Relation::payload_key(&e1_key, E2::store_name(), &e2_key, name)?;
+        let e2_payload_key = Relation::payload_key(&e2_key, E1::store_name(), &e1_key, name)?;
+        let mut trees = TreeSet::default();
+        let e1_tree = trees.open(&Relation::tree_name(E1::store_name()), db)?;
+        let e2_tree = trees.open(&Relation::tree_name(E2::store_name()), db)?;
+        let e1_payloads = trees.open(&Relation::payload_tree_name(E1::store_name()), db)?;
+        let e2_payloads = trees.open(&Relation::payload_tree_name(E2::store_name()), db)?;
+        EntityWriter::transaction(&trees.trees, |trees| {
+            let e1_to_e2_is_new =
+                Relation::add_link(&trees[e1_tree], &e1_key, e2, e1_to_e2.clone(), name)?;
+            let e2_to_e1_is_new =
+                Relation::add_link(&trees[e2_tree], &e2_key, e1, e2_to_e1.clone(), name)?;
+            trees[e1_payloads].insert(e1_payload_key.as_slice(), payload.as_slice())?;
+            trees[e2_payloads].insert(e2_payload_key.as_slice(), payload.as_slice())?;
+            Ok(RelationCreateResult {
+                was_new: e1_to_e2_is_new || e2_to_e1_is_new,
+            })
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_batch<E1: Entity, E2: Entity>(
+        e1: &E1,
+        others: &[E2],
+        e1_to_e2: DeletionBehaviour,
+        e2_to_e1: DeletionBehaviour,
+        name: Option<&str>,
+        fail_on_duplicate: bool,
+        db: &Db,
     ) -> Result<()> {
-        Relation::create_link(e1, e2, e1_to_e2, name, db)?;
-        Relation::create_link(e2, e1, e2_to_e1, name, db)?;
+        Relation::check_free_behaviours([&e1_to_e2, &e2_to_e1])?;
+        let e1_key = e1.get_key().as_bytes();
+        let mut e1_descriptor = Self::get_descriptor(e1, db)?;
+        let mut batch = Batch::default();
+        for e2 in others {
+            let e2_key = e2.get_key().as_bytes();
+            if e1_descriptor.contains(E2::store_name(), &e2_key, name) {
+                if fail_on_duplicate {
+                    return Err(Error::new(
+                        ErrorKind::IntegrityError,
+                        format!("Relation already exists with entity in {}", E2::store_name()),
+                    ));
+                }
+                continue;
+            }
+            e1_descriptor.add_related(e2, e1_to_e2.clone(), name);
+            let mut e2_descriptor = Self::get_descriptor(e2, db)?;
+            e2_descriptor.add_related(e1, e2_to_e1.clone(), name);
+            batch.insert(e2_key, bincode::serialize(&e2_descriptor)?);
+        }
+        let e1_tree = db.open_tree(Relation::tree_name(E1::store_name()))?;
+        let e1_descriptor = bincode::serialize(&e1_descriptor)?;
+        if E1::store_name() == E2::store_name() {
+            batch.insert(e1_key, e1_descriptor);
+            e1_tree.apply_batch(batch)?;
+        } else {
+            let e2_tree = db.open_tree(Relation::tree_name(E2::store_name()))?;
+            (&e1_tree, &e2_tree).transaction(|(e1_tree, e2_tree)| {
+                e1_tree.insert(e1_key.as_slice(), e1_descriptor.as_slice())?;
+                e2_tree.apply_batch(&batch)?;
+                Ok(())
+            })?;
+        }
         Ok(())
     }
 
@@ -44,10 +291,22 @@ impl Relation {
                     &other_desc,
                     db,
                 )?;
+                Relation::move_payloads(
+                    other_tree,
+                    &bincode::serialize(&(&rd.key, tree_name, old_id))?,
+                    |key| key.2 = new_id.to_owned(),
+                    db,
+                )?;
             }
         }
         Relation::save_descriptor_with_key_and_tree_name(tree_name, new_id, &descriptor, db)?;
-        let family_descriptor = FamilyDescriptor::get(&String::from(tree_name), db)?;
+        Relation::move_payloads(
+            tree_name,
+            &bincode::serialize(old_id)?,
+            |key| key.0 = new_id.to_owned(),
+            db,
+        )?;
+        let family_descriptor = FamilyDescriptor::get(tree_name, db)?;
         if family_descriptor.is_none() {
             return Ok(());
         }
@@ -62,11 +321,16 @@ impl Relation {
             }
         }
         for (other_tree, _) in family_descriptor.sibling_trees {
+            if SiblingLink::is_unlinked(tree_name, &other_tree, old_id, db)? {
+                // The former sibling stays where it is, and the moved entity is linked to its new sibling, if any
+                SiblingLink::relink(tree_name, &other_tree, old_id, db)?;
+                continue;
+            }
             let tree = db.open_tree(&other_tree)?;
             if let Some(value) = tree.get(old_id)? {
-                tree.insert(&new_id, value)?;
+                tree.insert(new_id, value)?;
                 Relation::change_entity_id(&other_tree, old_id, new_id, db)?;
-                tree.remove(&old_id)?;
+                tree.remove(old_id)?;
             }
         }
         Ok(())
@@ -83,22 +347,25 @@ impl Relation {
         Ok(())
     }
 
-    pub fn remove_entity_entry<E1: Entity>(key: &[u8], db: &Db) -> Result<()> {
-        let descriptor = Self::get_descriptor_with_key::<E1>(key, db)?;
+    pub fn remove_all_for_key(store_name: &str, key: &[u8], db: &Db) -> Result<usize> {
+        let descriptor = Self::get_descriptor_with_key_and_tree_name(store_name, key, db)?;
+        let mut count = 0;
         for (tree_name, referers) in descriptor.related_entities {
             for referer in referers {
                 Self::remove_link_with_keys_and_tree_names(
                     &tree_name,
                     &referer.key,
-                    E1::store_name(),
+                    store_name,
                     key,
                     db,
                 )?;
+                count += 1;
             }
         }
-        let tree = db.open_tree(Relation::tree_name(E1::store_name()))?;
+        let tree = db.open_tree(Relation::tree_name(store_name))?;
         tree.remove(key)?;
-        Ok(())
+        Relation::remove_payloads(store_name, &bincode::serialize(key)?, db)?;
+        Ok(count)
     }
 
     pub fn remove_by_keys<E1: Entity, E2: Entity>(e1: &[u8], e2: &[u8], db: &Db) -> Result<()> {
@@ -111,50 +378,124 @@ impl Relation {
         Relation::get_descriptor(e1, db)
     }
 
+    /// Reads the relations of the `removed` entities, opening in `trees` the trees holding them, so that they can be
+    /// removed in a transaction over `trees` with [`RelationRemoval::apply`].
+    pub fn prepare_removal(
+        removed: &[(String, Vec<u8>)],
+        removed_set: &HashSet<(String, Vec<u8>)>,
+        trees: &mut TreeSet,
+        db: &Db,
+    ) -> Result<RelationRemoval> {
+        let mut removal = RelationRemoval::default();
+        for (store, key) in removed {
+            let tree = trees.open(&Relation::tree_name(store), db)?;
+            let current = trees.trees[tree].get(key)?;
+            let relations = match &current {
+                Some(relations) => bincode::deserialize::<EntityRelations>(relations)?,
+                None => EntityRelations::default(),
+            };
+            let payloads = trees.open(&Relation::payload_tree_name(store), db)?;
+            for payload_key in trees.trees[payloads]
+                .scan_prefix(bincode::serialize(key)?)
+                .keys()
+            {
+                removal.payloads.push((payloads, payload_key?));
+            }
+            let mut links = Vec::new();
+            for (other_store, related) in &relations.related_entities {
+                let other_tree = trees.open(&Relation::tree_name(other_store), db)?;
+                let other_payloads = trees.open(&Relation::payload_tree_name(other_store), db)?;
+                for rd in related {
+                    if removed_set.contains(&(other_store.clone(), rd.key.clone())) {
+                        continue;
+                    }
+                    links.push((other_tree, rd.key.clone()));
+                    let prefix = bincode::serialize(&(rd.key.as_slice(), store.as_str(), key.as_slice()))?;
+                    for payload_key in trees.trees[other_payloads].scan_prefix(prefix).keys() {
+                        removal.payloads.push((other_payloads, payload_key?));
+                    }
+                }
+            }
+            removal.entries.push(RemovedEntry {
+                tree,
+                store: store.clone(),
+                key: key.clone(),
+                current,
+                links,
+            });
+        }
+        Ok(removal)
+    }
+
+    /// Checks that the entity with key `e1` in `tree_name` can be removed, and adds the entities to be removed, updated
+    /// or notified along with it to `removable_entities`.
+    ///
+    /// `removed` holds the entities being removed at the same time : relations between them never prevent their removal.
     pub fn can_be_deleted(
         tree_name: &str,
         e1: &[u8],
-        already_checked: &[(String, Vec<u8>)],
+        removed: &mut HashSet<(String, Vec<u8>)>,
         removable_entities: &mut EntityRelations,
         db: &Db,
     ) -> Result<()> {
-        if already_checked
-            .iter()
-            .any(|(tn, k)| tn == tree_name && k == e1)
-        {
-            return Ok(());
+        let entry = (String::from(tree_name), e1.to_vec());
+        let inserted = removed.insert(entry.clone());
+        let result = Self::check_deletion(tree_name, e1, removed, removable_entities, db);
+        if inserted {
+            removed.remove(&entry);
         }
+        result
+    }
+
+    fn check_deletion(
+        tree_name: &str,
+        e1: &[u8],
+        removed: &mut HashSet<(String, Vec<u8>)>,
+        removable_entities: &mut EntityRelations,
+        db: &Db,
+    ) -> Result<()> {
         let descriptor = Self::get_descriptor_with_key_and_tree_name(tree_name, e1, db)?;
-        let family_descriptor = FamilyDescriptor::get(&String::from(tree_name), db)?;
+        let family_descriptor = FamilyDescriptor::get(tree_name, db)?;
 
         for (other_tree_name, entities) in &descriptor.related_entities {
             for rd in entities {
-                match rd.deletion_behaviour {
+                match &rd.deletion_behaviour {
                     DeletionBehaviour::Error => {
-                        if already_checked.iter().any(|(tn, k)| {
-                            tn == other_tree_name && k.as_bytes() == rd.key.as_bytes()
-                        }) {
+                        if removed.contains(&(other_tree_name.clone(), rd.key.clone())) {
                             continue;
                         }
                         return Err(Error::new(
                             ErrorKind::IntegrityError,
                             format!("Constrained related entity exists in {}", other_tree_name),
-                        ));
+                        )
+                        .with_context(ErrorContext::RelationConstraintViolation {
+                            source_store: String::from(tree_name),
+                            target_store: other_tree_name.clone(),
+                            behaviour: DeletionBehaviour::Error,
+                        }));
                     }
                     DeletionBehaviour::Cascade => {
-                        let mut new_already_checked = already_checked.to_owned();
-                        new_already_checked.push((String::from(tree_name), e1.to_vec()));
-                        Self::can_be_deleted(
+                        if !removed.contains(&(other_tree_name.clone(), rd.key.clone())) {
+                            Self::can_be_deleted(
+                                other_tree_name,
+                                &rd.key,
+                                removed,
+                                removable_entities,
+                                db,
+                            )?;
+                        }
+                        removable_entities.add_related_by_key(
                             other_tree_name,
                             &rd.key,
-                            &new_already_checked,
-                            removable_entities,
-                            db,
-                        )?;
+                            DeletionBehaviour::Cascade,
+                            None,
+                        );
+                    }
+                    DeletionBehaviour::SetNull(field) => {
                         removable_entities.add_related_by_key(
                             other_tree_name,
                             &rd.key,
-                            DeletionBehaviour::Cascade,
+                            DeletionBehaviour::SetNull(field.clone()),
                             None,
                         );
                     }
@@ -170,32 +511,37 @@ impl Relation {
         }
         let family_descriptor = family_descriptor.unwrap();
         for (other_tree_name, behaviour) in &family_descriptor.sibling_trees {
+            if SiblingLink::is_unlinked(tree_name, other_tree_name, e1, db)? {
+                continue;
+            }
             match behaviour {
                 DeletionBehaviour::Error => {
-                    if already_checked
-                        .iter()
-                        .any(|(tn, k)| tn == other_tree_name && k == e1)
-                    {
+                    if removed.contains(&(other_tree_name.clone(), e1.to_vec())) {
                         continue;
                     }
-                    let tree = db.open_tree(&other_tree_name)?;
+                    let tree = db.open_tree(other_tree_name)?;
                     if tree.contains_key(e1)? {
                         return Err(Error::new(
                             ErrorKind::IntegrityError,
                             format!("Constrained sibling entity exists in {}", &other_tree_name),
-                        ));
+                        )
+                        .with_context(ErrorContext::RelationConstraintViolation {
+                            source_store: String::from(tree_name),
+                            target_store: other_tree_name.clone(),
+                            behaviour: DeletionBehaviour::Error,
+                        }));
                     }
                 }
                 DeletionBehaviour::Cascade => {
-                    let mut new_already_checked = already_checked.to_owned();
-                    new_already_checked.push((String::from(tree_name), e1.to_vec()));
-                    Self::can_be_deleted(
-                        other_tree_name,
-                        e1,
-                        &new_already_checked,
-                        removable_entities,
-                        db,
-                    )?;
+                    if !removed.contains(&(other_tree_name.clone(), e1.to_vec())) {
+                        Self::can_be_deleted(
+                            other_tree_name,
+                            e1,
+                            removed,
+                            removable_entities,
+                            db,
+                        )?;
+                    }
                     removable_entities.add_related_by_key(
                         other_tree_name,
                         e1,
@@ -203,24 +549,50 @@ impl Relation {
                         None,
                     );
                 }
+                DeletionBehaviour::SetNull(field) => {
+                    let tree = db.open_tree(other_tree_name)?;
+                    if tree.contains_key(e1)? {
+                        removable_entities.add_related_by_key(
+                            other_tree_name,
+                            e1,
+                            DeletionBehaviour::SetNull(field.clone()),
+                            None,
+                        );
+                    }
+                }
+                DeletionBehaviour::Custom(_) => {
+                    let custom = Relation::get_custom(tree_name, other_tree_name, db)?;
+                    let tree = db.open_tree(other_tree_name)?;
+                    if tree.contains_key(e1)? {
+                        removable_entities.add_related_by_key(other_tree_name, e1, custom, None);
+                    }
+                }
                 _ => {}
             }
         }
         for (other_tree_name, behaviour) in &family_descriptor.child_trees {
             match behaviour {
                 DeletionBehaviour::Error => {
-                    let tree = db.open_tree(&other_tree_name)?;
-                    if tree.scan_prefix(e1).count() > 0 {
+                    let tree = db.open_tree(other_tree_name)?;
+                    let mut children = tree.scan_prefix(e1).keys();
+                    if children.try_fold(false, |found, key| {
+                        key.map(|key| {
+                            found || !removed.contains(&(other_tree_name.clone(), key.to_vec()))
+                        })
+                    })? {
                         return Err(Error::new(
                             ErrorKind::IntegrityError,
                             format!("Constrained child entity exists in {}", &other_tree_name),
-                        ));
+                        )
+                        .with_context(ErrorContext::RelationConstraintViolation {
+                            source_store: String::from(tree_name),
+                            target_store: other_tree_name.clone(),
+                            behaviour: DeletionBehaviour::Error,
+                        }));
                     }
                 }
                 DeletionBehaviour::Cascade => {
-                    let mut new_already_checked = already_checked.to_owned();
-                    new_already_checked.push((String::from(tree_name), e1.to_vec()));
-                    let tree = db.open_tree(&other_tree_name)?;
+                    let tree = db.open_tree(other_tree_name)?;
                     let keys = tree
                         .scan_prefix(e1)
                         .filter_map(|e| {
@@ -232,17 +604,42 @@ impl Relation {
                         })
                         .collect::<Vec<Vec<u8>>>();
                     for key in keys {
-                        Self::can_be_deleted(
+                        if !removed.contains(&(other_tree_name.clone(), key.clone())) {
+                            Self::can_be_deleted(
+                                other_tree_name,
+                                &key,
+                                removed,
+                                removable_entities,
+                                db,
+                            )?;
+                        }
+                        removable_entities.add_related_by_key(
                             other_tree_name,
                             &key,
-                            &new_already_checked.clone(),
-                            removable_entities,
-                            db,
-                        )?;
+                            DeletionBehaviour::Cascade,
+                            None,
+                        );
+                    }
+                }
+                DeletionBehaviour::SetNull(field) => {
+                    let tree = db.open_tree(other_tree_name)?;
+                    for (key, _) in tree.scan_prefix(e1).flatten() {
                         removable_entities.add_related_by_key(
                             other_tree_name,
                             &key,
-                            DeletionBehaviour::Cascade,
+                            DeletionBehaviour::SetNull(field.clone()),
+                            None,
+                        );
+                    }
+                }
+                DeletionBehaviour::Custom(_) => {
+                    let custom = Relation::get_custom(tree_name, other_tree_name, db)?;
+                    let tree = db.open_tree(other_tree_name)?;
+                    for (key, _) in tree.scan_prefix(e1).flatten() {
+                        removable_entities.add_related_by_key(
+                            other_tree_name,
+                            &key,
+                            custom.clone(),
                             None,
                         );
                     }
@@ -254,51 +651,199 @@ impl Relation {
     }
 
     pub fn get<E1: Entity, E2: Entity>(e1: &E1, db: &Db) -> Result<Vec<E2>> {
+        Ok(E2::get_each_u8(
+            Relation::get_related_keys::<E1, E2>(e1, db)?.as_slice(),
+            db,
+        ))
+    }
+
+    /// Related keys are sorted by bytes, so that related entities are returned in a deterministic order.
+    pub fn get_related_keys<E1: Entity, E2: Entity>(e1: &E1, db: &Db) -> Result<Vec<Vec<u8>>> {
         let referers = Relation::relations(e1, db)?;
-        if let Some(related_keys) = referers.related_entities.get(E2::store_name()) {
-            Ok(E2::get_each_u8(
-                (related_keys
-                    .iter()
-                    .map(|e| e.key.clone())
-                    .collect::<Vec<Vec<u8>>>())
-                .as_slice(),
-                db,
-            ))
-        } else {
-            Ok(Vec::new())
+        let mut keys: Vec<Vec<u8>> = referers
+            .related_entities
+            .get(E2::store_name())
+            .map(|related_keys| related_keys.iter().map(|rd| rd.key.clone()).collect())
+            .unwrap_or_default();
+        keys.sort();
+        Ok(keys)
+    }
+
+    pub fn get_refs<E1: Entity, E2: Entity>(e1: &E1, db: &Db) -> Result<Vec<EntityRef<E2>>> {
+        Ok(Relation::get_related_keys::<E1, E2>(e1, db)?
+            .into_iter()
+            .map(|key| EntityRef::from_key_bytes(key, db))
+            .collect())
+    }
+
+    pub fn get_with_payload<E1: Entity, E2: Entity, P: DeserializeOwned>(
+        e1: &E1,
+        db: &Db,
+    ) -> Result<Vec<(E2, P)>> {
+        let tree = db.open_tree(Relation::payload_tree_name(E1::store_name()))?;
+        let prefix = bincode::serialize(&(e1.get_key().as_bytes(), E2::store_name()))?;
+        let mut result = Vec::new();
+        for elem in tree.scan_prefix(prefix) {
+            let (key, payload) = elem?;
+            let (_, _, e2_key, _) = bincode::deserialize::<PayloadKey>(&key)?;
+            if let Some(e2) = E2::get_from_u8_array(&e2_key, db)? {
+                let relation = bincode::deserialize::<RelationWithPayload<P>>(&payload)?;
+                result.push((e2, relation.payload));
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn get_with_filter<E1: Entity, E2: Entity, F: Fn(&E2) -> bool>(
+        e1: &E1,
+        f: F,
+        db: &Db,
+    ) -> Result<Vec<E2>> {
+        let mut result = Vec::new();
+        for key in Relation::get_related_keys::<E1, E2>(e1, db)? {
+            if let Some(e2) = E2::get_from_u8_array(&key, db)? {
+                if f(&e2) {
+                    result.push(e2);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn get_paginated<E1: Entity, E2: Entity>(
+        e1: &E1,
+        page: usize,
+        page_size: usize,
+        db: &Db,
+    ) -> Result<Vec<E2>> {
+        let skipped = match page.checked_mul(page_size) {
+            Some(skipped) => skipped,
+            None => return Ok(Vec::new()),
+        };
+        let keys = Relation::get_related_keys::<E1, E2>(e1, db)?;
+        Ok(E2::get_each_u8(
+            (keys
+                .into_iter()
+                .skip(skipped)
+                .take(page_size)
+                .collect::<Vec<Vec<u8>>>())
+            .as_slice(),
+            db,
+        ))
+    }
+
+    pub fn count<E1: Entity, E2: Entity>(e1: &E1, db: &Db) -> Result<usize> {
+        let referers = Relation::relations(e1, db)?;
+        Ok(referers
+            .related_entities
+            .get(E2::store_name())
+            .map(|related_keys| related_keys.len())
+            .unwrap_or(0))
+    }
+
+    pub fn count_referrers<E1: Entity, E2: Entity>(e1: &E1, db: &Db) -> Result<usize> {
+        let key = e1.get_key().as_bytes();
+        let tree = db.open_tree(Relation::tree_name(E2::store_name()))?;
+        let mut count = 0;
+        for elem in tree.iter() {
+            let descriptor = bincode::deserialize::<EntityRelations>(&elem?.1)?;
+            if let Some(related_keys) = descriptor.related_entities.get(E1::store_name()) {
+                if related_keys.iter().any(|rd| rd.key == key) {
+                    count += 1;
+                }
+            }
         }
+        Ok(count)
+    }
+
+    pub fn find_orphans<E1: Entity, E2: Entity>(db: &Db) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let tree = db.open_tree(Relation::tree_name(E1::store_name()))?;
+        let e2_tree = E2::get_tree(db)?;
+        let mut result = Vec::new();
+        for elem in tree.iter() {
+            let (key, descriptor) = elem?;
+            let descriptor = bincode::deserialize::<EntityRelations>(&descriptor)?;
+            if let Some(related_keys) = descriptor.related_entities.get(E2::store_name()) {
+                for rd in related_keys {
+                    let pair = (key.to_vec(), rd.key.clone());
+                    if !e2_tree.contains_key(&rd.key)? && !result.contains(&pair) {
+                        result.push(pair);
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn repair_orphans<E1: Entity, E2: Entity>(db: &Db) -> Result<usize> {
+        let orphans = Relation::find_orphans::<E1, E2>(db)?;
+        for (e1, e2) in &orphans {
+            Relation::remove_link_with_keys::<E1, E2>(e1, e2, db)?;
+        }
+        Ok(orphans.len())
+    }
+
+    pub fn list_all_for_entity_key(
+        store_name: &str,
+        key: &[u8],
+        db: &Db,
+    ) -> Result<Vec<(String, String, Vec<u8>)>> {
+        let mut result = Vec::new();
+        for tree_name in db.tree_names() {
+            let source_store = match std::str::from_utf8(&tree_name)
+                .ok()
+                .and_then(|name| name.strip_prefix(RELATION_TREE_PREFIX))
+            {
+                Some(source_store) => source_store.to_owned(),
+                None => continue,
+            };
+            for elem in db.open_tree(&tree_name)?.iter() {
+                let (source_key, descriptor) = elem?;
+                let descriptor = bincode::deserialize::<EntityRelations>(&descriptor)?;
+                for (target_store, related_keys) in &descriptor.related_entities {
+                    for rd in related_keys {
+                        if (source_store == store_name && source_key == key)
+                            || (target_store == store_name && rd.key == key)
+                        {
+                            result.push((
+                                source_store.clone(),
+                                target_store.clone(),
+                                rd.key.clone(),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(result)
     }
 
     pub fn get_with_name<E1: Entity, E2: Entity>(e1: &E1, name: &str, db: &Db) -> Result<Vec<E2>> {
         let referers = Relation::relations(e1, db)?;
         if let Some(related_keys) = referers.related_entities.get(E2::store_name()) {
-            Ok(E2::get_each_u8(
-                (related_keys
-                    .iter()
-                    .filter(|rd| match &rd.name {
-                        Some(n) => name == n,
-                        None => false,
-                    })
-                    .map(|e| e.key.clone())
-                    .collect::<Vec<Vec<u8>>>())
-                .as_slice(),
-                db,
-            ))
+            let mut keys = related_keys
+                .iter()
+                .filter(|rd| match &rd.name {
+                    Some(n) => name == n,
+                    None => false,
+                })
+                .map(|e| e.key.clone())
+                .collect::<Vec<Vec<u8>>>();
+            keys.sort();
+            Ok(E2::get_each_u8(keys.as_slice(), db))
         } else {
             Ok(Vec::new())
         }
     }
 
+    fn get_existing<E: Entity>(key: &[u8], db: &Db) -> Result<E> {
+        E::get_from_u8_array(key, db)?.ok_or_else(|| entity_not_found(E::store_name(), key))
+    }
+
     pub fn get_one<E1: Entity, E2: Entity>(e1: &E1, db: &Db) -> Result<Option<E2>> {
-        let referers = Relation::relations(e1, db)?;
-        if let Some(related_keys) = referers.related_entities.get(E2::store_name()) {
-            if !related_keys.is_empty() {
-                E2::get_from_u8_array(&related_keys[0].key, db)
-            } else {
-                Ok(None)
-            }
-        } else {
-            Ok(None)
+        match Relation::get_related_keys::<E1, E2>(e1, db)?.first() {
+            Some(key) => Relation::get_existing(key, db).map(Some),
+            None => Ok(None),
         }
     }
 
@@ -309,15 +854,12 @@ impl Relation {
     ) -> Result<Option<E2>> {
         let referers = Relation::relations(e1, db)?;
         if let Some(related_keys) = referers.related_entities.get(E2::store_name()) {
-            let item = related_keys.iter().find(|rd| {
-                if let Some(n) = &rd.name {
-                    name == n
-                } else {
-                    false
-                }
-            });
+            let item = related_keys
+                .iter()
+                .filter(|rd| rd.name.as_deref() == Some(name))
+                .min_by(|rd1, rd2| rd1.key.cmp(&rd2.key));
             match item {
-                Some(rd) => E2::get_from_u8_array(&rd.key, db),
+                Some(rd) => Relation::get_existing(&rd.key, db).map(Some),
                 None => Ok(None),
             }
         } else {
@@ -325,12 +867,11 @@ impl Relation {
         }
     }
 
-    pub fn are_related<E1: Entity, E2: Entity>(e1: &E1, e2: &E2, db: &Db) -> Result<bool> {
+    pub fn exists<E1: Entity, E2: Entity>(e1: &E1, e2: &E2, db: &Db) -> Result<bool> {
         let referers = Relation::relations(e1, db)?;
         if let Some(related_keys) = referers.related_entities.get(E2::store_name()) {
-            Ok(related_keys
-                .iter()
-                .any(|rd| rd.key == e2.get_key().as_bytes()))
+            let key = e2.get_key().as_bytes();
+            Ok(related_keys.iter().any(|rd| rd.key == key))
         } else {
             Ok(false)
         }
@@ -376,8 +917,90 @@ impl Relation {
         }
     }
 
+    pub fn rename_tree(old_name: &str, new_name: &str, db: &Db) -> Result<()> {
+        for tree_name in db.tree_names() {
+            let tree_name = String::from_utf8_lossy(&tree_name).into_owned();
+            if tree_name.starts_with(RELATION_TREE_PREFIX) {
+                let tree = db.open_tree(&tree_name)?;
+                let mut batch = Batch::default();
+                for elem in tree.iter() {
+                    let (key, value) = elem?;
+                    let mut relations = bincode::deserialize::<EntityRelations>(&value)?;
+                    if let Some(related) = relations.related_entities.remove(old_name) {
+                        relations
+                            .related_entities
+                            .insert(String::from(new_name), related);
+                        batch.insert(key, bincode::serialize(&relations)?);
+                    }
+                }
+                tree.apply_batch(batch)?;
+            } else if tree_name.starts_with(&Relation::payload_tree_name("")) {
+                let tree = db.open_tree(&tree_name)?;
+                let mut batch = Batch::default();
+                for elem in tree.iter() {
+                    let (key, payload) = elem?;
+                    let mut payload_key = bincode::deserialize::<PayloadKey>(&key)?;
+                    if payload_key.1 == old_name {
+                        payload_key.1 = String::from(new_name);
+                        batch.remove(key);
+                        batch.insert(bincode::serialize(&payload_key)?, payload);
+                    }
+                }
+                tree.apply_batch(batch)?;
+            }
+        }
+        move_tree(
+            &Relation::tree_name(old_name),
+            &Relation::tree_name(new_name),
+            db,
+        )?;
+        move_tree(
+            &Relation::payload_tree_name(old_name),
+            &Relation::payload_tree_name(new_name),
+            db,
+        )?;
+        Ok(())
+    }
+
     fn tree_name(entity_tree: &str) -> String {
-        format!("__$rel_{}", entity_tree)
+        format!("{}{}", RELATION_TREE_PREFIX, entity_tree)
+    }
+
+    fn payload_tree_name(entity_tree: &str) -> String {
+        format!("__$payload_rel_{}", entity_tree)
+    }
+
+    fn payload_key(e1: &[u8], tree2: &str, e2: &[u8], name: Option<&str>) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&(e1, tree2, e2, name))?)
+    }
+
+    fn remove_payloads(tree_name: &str, prefix: &[u8], db: &Db) -> Result<()> {
+        let tree = db.open_tree(Relation::payload_tree_name(tree_name))?;
+        let mut batch = Batch::default();
+        for key in tree.scan_prefix(prefix).keys() {
+            batch.remove(key?);
+        }
+        tree.apply_batch(batch)?;
+        Ok(())
+    }
+
+    fn move_payloads<F: Fn(&mut PayloadKey)>(
+        tree_name: &str,
+        prefix: &[u8],
+        f: F,
+        db: &Db,
+    ) -> Result<()> {
+        let tree = db.open_tree(Relation::payload_tree_name(tree_name))?;
+        let mut batch = Batch::default();
+        for elem in tree.scan_prefix(prefix) {
+            let (key, payload) = elem?;
+            let mut payload_key = bincode::deserialize::<PayloadKey>(&key)?;
+            f(&mut payload_key);
+            batch.remove(key);
+            batch.insert(bincode::serialize(&payload_key)?, payload);
+        }
+        tree.apply_batch(batch)?;
+        Ok(())
     }
 
     pub fn get_descriptor_with_key_and_tree_name(
@@ -423,30 +1046,71 @@ impl Relation {
         Self::save_descriptor_with_key::<E>(&e.get_key().as_bytes(), r_d, db)
     }
 
-    pub fn remove_descriptor(tree_name: &str, e: &[u8], db: &Db) -> Result<()> {
-        let tree = db.open_tree(Relation::tree_name(tree_name))?;
-        tree.remove(e)?;
-        Ok(())
-    }
-
     fn create_link<E1: Entity, E2: Entity>(
         e1: &E1,
         e2: &E2,
         e1_to_e2: DeletionBehaviour,
         name: Option<&str>,
         db: &Db,
-    ) -> Result<()> {
-        let mut e1_descriptor = Self::get_descriptor(e1, db)?;
+    ) -> Result<bool> {
+        let tree = db.open_tree(Relation::tree_name(E1::store_name()))?;
+        let e1_key = e1.get_key().as_bytes();
+        let e2_key = e2.get_key().as_bytes();
+        loop {
+            let current = tree.get(&e1_key)?;
+            let mut e1_descriptor = match &current {
+                Some(descriptor) => bincode::deserialize::<EntityRelations>(descriptor)?,
+                None => EntityRelations::default(),
+            };
+            if e1_descriptor.contains(E2::store_name(), &e2_key, name) {
+                return Ok(false);
+            }
+            e1_descriptor.add_related(e2, e1_to_e2.clone(), name);
+            if tree
+                .compare_and_swap(
+                    &e1_key,
+                    current,
+                    Some(bincode::serialize(&e1_descriptor)?),
+                )?
+                .is_ok()
+            {
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Transactional counterpart of `create_link`, on the relation tree of the store of the entity with key `e1_key`
+    fn add_link<E2: Entity>(
+        tree: &TransactionalTree,
+        e1_key: &[u8],
+        e2: &E2,
+        e1_to_e2: DeletionBehaviour,
+        name: Option<&str>,
+    ) -> ConflictableTransactionResult<bool, Error> {
+        let abort = |e: bincode::Error| ConflictableTransactionError::Abort(Error::from(e));
+        let mut e1_descriptor = match tree.get(e1_key)? {
+            Some(descriptor) => {
+                bincode::deserialize::<EntityRelations>(&descriptor).map_err(abort)?
+            }
+            None => EntityRelations::default(),
+        };
+        if e1_descriptor.contains(E2::store_name(), &e2.get_key().as_bytes(), name) {
+            return Ok(false);
+        }
         e1_descriptor.add_related(e2, e1_to_e2, name);
-        Self::save_descriptor(e1, &e1_descriptor, db)?;
-        Ok(())
+        tree.insert(e1_key, bincode::serialize(&e1_descriptor).map_err(abort)?)?;
+        Ok(true)
     }
 
     fn remove_link_with_keys<E1: Entity, E2: Entity>(e1: &[u8], e2: &[u8], db: &Db) -> Result<()> {
         let mut e1_descriptor = Self::get_descriptor_with_key::<E1>(e1, db)?;
         e1_descriptor.remove_related_by_key::<E2>(e2);
         Self::save_descriptor_with_key::<E1>(e1, &e1_descriptor, db)?;
-        Ok(())
+        Self::remove_payloads(
+            E1::store_name(),
+            &bincode::serialize(&(e1, E2::store_name(), e2))?,
+            db,
+        )
     }
 
     fn remove_link_with_keys_and_relation_name<E1: Entity, E2: Entity>(
@@ -458,6 +1122,8 @@ impl Relation {
         let mut e1_descriptor = Self::get_descriptor_with_key::<E1>(e1, db)?;
         e1_descriptor.remove_related_by_key_with_name::<E2>(e2, name);
         Self::save_descriptor_with_key::<E1>(e1, &e1_descriptor, db)?;
+        db.open_tree(Relation::payload_tree_name(E1::store_name()))?
+            .remove(Relation::payload_key(e1, E2::store_name(), e2, Some(name))?)?;
         Ok(())
     }
 
@@ -471,7 +1137,7 @@ impl Relation {
         let mut e1_descriptor = Self::get_descriptor_with_key_and_tree_name(tree1, e1, db)?;
         e1_descriptor.remove_related_by_key_and_tree_name(tree2, e2);
         Self::save_descriptor_with_key_and_tree_name(tree1, e1, &e1_descriptor, db)?;
-        Ok(())
+        Self::remove_payloads(tree1, &bincode::serialize(&(e1, tree2, e2))?, db)
     }
 
     fn remove_link<E1: Entity, E2: Entity>(e1: &E1, e2: &E2, db: &Db) -> Result<()> {
@@ -497,8 +1163,88 @@ impl Relation {
     }
 }
 
+/// Relation descriptor of a removed entity, and the related entities to unlink from it
+struct RemovedEntry {
+    tree: usize,
+    store: String,
+    key: Vec<u8>,
+    current: Option<IVec>,
+    links: Vec<(usize, Vec<u8>)>,
+}
+
+/// Relations and relation payloads of removed entities, read by [`Relation::prepare_removal`].
+#[derive(Default)]
+pub struct RelationRemoval {
+    entries: Vec<RemovedEntry>,
+    payloads: Vec<(usize, IVec)>,
+}
+
+impl RelationRemoval {
+    /// Removes the relations of the removed entities, in a transaction over the trees they were prepared with.
+    /// Returns `false` without removing anything if the relations of a removed entity were changed since.
+    pub fn apply(&self, trees: &[TransactionalTree]) -> ConflictableTransactionResult<bool, Error> {
+        for entry in &self.entries {
+            if trees[entry.tree].get(&entry.key)? != entry.current {
+                return Ok(false);
+            }
+        }
+        let abort = |e: bincode::Error| ConflictableTransactionError::Abort(Error::from(e));
+        for entry in &self.entries {
+            for (tree, key) in &entry.links {
+                if let Some(relations) = trees[*tree].get(key)? {
+                    let mut relations =
+                        bincode::deserialize::<EntityRelations>(&relations).map_err(abort)?;
+                    relations.remove_related_by_key_and_tree_name(&entry.store, &entry.key);
+                    trees[*tree]
+                        .insert(key.as_slice(), bincode::serialize(&relations).map_err(abort)?)?;
+                }
+            }
+            trees[entry.tree].remove(entry.key.as_slice())?;
+        }
+        for (tree, key) in &self.payloads {
+            trees[*tree].remove(key)?;
+        }
+        Ok(true)
+    }
+}
+
+/// Entry stored for both ends of a free relation created with
+/// [`create_relation_with_payload`](entity/trait.Entity.html#method.create_relation_with_payload).
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct RelationWithPayload<P> {
+    /// Name of the relation, if any
+    pub name: Option<String>,
+    /// Payload attached to the relation
+    pub payload: P,
+}
+
+/// Result of the creation of a free relation between two entities.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct RelationCreateResult {
+    /// `false` if the two entities were already linked with the same relation name, in which case nothing was changed.
+    pub was_new: bool,
+}
+
+/// Integrity issue found in the database by [`validate_all`](fn.validate_all.html).
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum IntegrityIssue {
+    /// `store` is declared as a sibling or child store of `referenced_by`, but its entity was never registered
+    UnregisteredStore { store: String, referenced_by: String },
+    /// The entity of `store` is registered, but the store does not exist in the database
+    MissingTree { store: String },
+    /// A relation descriptor exists for the key `key`, but no entity with this key exists in `store`
+    MissingEntity { store: String, key: Vec<u8> },
+    /// The entity with key `key` in `store` is related to an entity of `target_store` that does not exist
+    BrokenRelation {
+        store: String,
+        key: Vec<u8>,
+        target_store: String,
+        target_key: Vec<u8>,
+    },
+}
+
 /// Enum for use in relation description, defining how the database must behave if one end of the relation is removed.
-#[derive(PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Debug)]
+#[derive(Serialize, Deserialize, Clone)]
 pub enum DeletionBehaviour {
     /// Trying to remove the current entity while a related one still exists will result in an error
     Error,
@@ -506,4 +1252,76 @@ pub enum DeletionBehaviour {
     BreakLink,
     /// Related entities are also removed if the current one is removed
     Cascade,
+    /// Related entities are kept, but the given `Option` field is set to `None` if the current one is removed.
+    ///
+    /// ⚠ The related entity type must override [`set_field_to_none`](trait.Entity.html#method.set_field_to_none)
+    /// (which is done automatically by the derive macro) and be registered.
+    SetNull(String),
+    /// The given function is called with the key of each related entity, as bytes, if the current one is removed.
+    /// Related entities and links are otherwise left untouched : the function can for instance archive related
+    /// entities to another store, or remove them.
+    ///
+    /// This can only be used for sibling and child stores, and cannot be declared with the derive macro : override
+    /// [`Entity::family_descriptor`](trait.Entity.html#method.family_descriptor) or register a
+    /// [`FamilyDescriptor`](struct.FamilyDescriptor.html) with [`register_all`](fn.register_all.html).
+    ///
+    /// ⚠ Functions are not saved in the database : they must be registered again each time the database is opened. They
+    /// are only used for the database they were registered with, and are kept when the entity is registered again with
+    /// [`Entity::register`](trait.Entity.html#method.register).
+    ///
+    /// ⚠ Functions are called before the removal of the current entity is committed, and their side effects are not
+    /// reverted if the removal fails afterwards.
+    ///
+    /// ### Example
+    /// ```rust
+    /// # mod setup { include!("../../doctest_setup.rs"); } use setup::*;
+    /// # let db = doctest_db()?;
+    /// # use std::sync::Arc;
+    /// let mut descriptor = Order::family_descriptor();
+    /// descriptor.child_trees.push((
+    ///     String::from("order_line"),
+    ///     DeletionBehaviour::Custom(Arc::new(|key, db| {
+    ///         if let Some(line) = OrderLine::get_from_u8_array(key, db)? {
+    ///             ArchivedOrderLine::from(line).save(db)?;
+    ///         }
+    ///         OrderLine::remove_from_u8_array(key, db)
+    ///     })),
+    /// ));
+    /// reindeer::register_all(&db, &[descriptor])?;
+    /// # Ok::<(), reindeer::Error>(())
+    /// ```
+    #[allow(clippy::type_complexity)]
+    Custom(
+        #[serde(skip, default = "unregistered_custom")]
+        Arc<dyn Fn(&[u8], &Db) -> Result<()> + Send + Sync>,
+    ),
+}
+
+impl PartialEq for DeletionBehaviour {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (DeletionBehaviour::Error, DeletionBehaviour::Error)
+            | (DeletionBehaviour::BreakLink, DeletionBehaviour::BreakLink)
+            | (DeletionBehaviour::Cascade, DeletionBehaviour::Cascade) => true,
+            (DeletionBehaviour::SetNull(f1), DeletionBehaviour::SetNull(f2)) => f1 == f2,
+            (DeletionBehaviour::Custom(f1), DeletionBehaviour::Custom(f2)) => {
+                std::ptr::addr_eq(Arc::as_ptr(f1), Arc::as_ptr(f2))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for DeletionBehaviour {}
+
+impl fmt::Debug for DeletionBehaviour {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeletionBehaviour::Error => write!(f, "Error"),
+            DeletionBehaviour::BreakLink => write!(f, "BreakLink"),
+            DeletionBehaviour::Cascade => write!(f, "Cascade"),
+            DeletionBehaviour::SetNull(field) => f.debug_tuple("SetNull").field(field).finish(),
+            DeletionBehaviour::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
 }
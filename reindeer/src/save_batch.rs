@@ -0,0 +1,115 @@
+//! # Save Batch Module
+//! This module provides [`SaveBatch`](struct.SaveBatch.html), to save many entities of a store with a single `sled`
+//! batch instead of one write per entity.
+
+use sled::Db;
+
+use crate::entity::{AsBytes, Entity};
+use crate::error::Result;
+use crate::relation::{DeletionBehaviour, Relation};
+use crate::write::EntityWriter;
+
+type CreateRelationFn<T> = Box<dyn Fn(&T, &Db) -> Result<()>>;
+
+/// A set of entities to be saved at once with [`commit`](struct.SaveBatch.html#method.commit).
+///
+/// Entities are serialized and validated when the batch is committed, and are then all written with a single
+/// `sled::Batch`. Relations added with [`add_with_relation`](struct.SaveBatch.html#method.add_with_relation) are
+/// created once all entities have been written.
+///
+/// ⚠ Entities of a store with unique fields are saved one by one, to check their constraints.
+///
+/// ### Example
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # use reindeer::SaveBatch;
+/// let mut batch = SaveBatch::new();
+/// for i in 0..10000 {
+///     batch.add(MyStruct { key: i, prop1: i.to_string(), prop2: i * 2 });
+/// }
+/// let count = batch.commit(&db)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub struct SaveBatch<T: Entity> {
+    entities: Vec<T>,
+    relations: Vec<(usize, CreateRelationFn<T>)>,
+}
+
+impl<T: Entity> Default for SaveBatch<T> {
+    fn default() -> Self {
+        SaveBatch::new()
+    }
+}
+
+impl<T: Entity> SaveBatch<T> {
+    /// Creates an empty batch
+    pub fn new() -> SaveBatch<T> {
+        SaveBatch {
+            entities: Vec::new(),
+            relations: Vec::new(),
+        }
+    }
+
+    /// Adds an entity to be saved
+    pub fn add(&mut self, entity: T) {
+        self.entities.push(entity);
+    }
+
+    /// Adds an entity to be saved, and a free relation to create between it and `related` once it is saved.
+    ///
+    /// See [`Entity::create_relation`](trait.Entity.html#method.create_relation).
+    pub fn add_with_relation<E: Entity + 'static>(
+        &mut self,
+        entity: T,
+        related: E,
+        entity_to_related: DeletionBehaviour,
+        related_to_entity: DeletionBehaviour,
+        name: Option<&str>,
+    ) {
+        let name = name.map(String::from);
+        self.relations.push((
+            self.entities.len(),
+            Box::new(move |entity, db| {
+                Relation::create(
+                    entity,
+                    &related,
+                    entity_to_related.clone(),
+                    related_to_entity.clone(),
+                    name.as_deref(),
+                    db,
+                )?;
+                Ok(())
+            }),
+        ));
+        self.entities.push(entity);
+    }
+
+    /// Number of entities in this batch
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns `true` if no entity has been added to this batch
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Saves all the entities of this batch, creates their relations, and returns the number of saved entities.
+    ///
+    /// If an entity cannot be serialized, is invalid or breaks a unique constraint, no entity of the batch is written.
+    pub fn commit(self, db: &Db) -> Result<usize> {
+        let mut entries = Vec::new();
+        for entity in &self.entities {
+            entries.push((
+                entity.get_key().as_bytes(),
+                entity.try_into_ivec_with_limit(T::MAX_SIZE_BYTES)?,
+            ));
+        }
+        EntityWriter::save::<T>(&entries, db)?;
+        for (index, create_relation) in &self.relations {
+            create_relation(&self.entities[*index], db)?;
+        }
+        Ok(self.entities.len())
+    }
+}
@@ -0,0 +1,126 @@
+//! # Sibling link Module
+//! This module keeps track of the sibling entities whose link was broken with
+//! [`Entity::remove_sibling_link`](trait.Entity.html#method.remove_sibling_link) : both entities keep their key, but are
+//! not considered as siblings anymore.
+
+use sled::{Db, Tree};
+
+use crate::error::Result;
+
+pub(crate) const UNLINKED_TREE_PREFIX: &str = "__$unlinked_";
+
+pub struct SiblingLink;
+
+impl SiblingLink {
+    fn tree_name(store: &str) -> String {
+        format!("{}{}", UNLINKED_TREE_PREFIX, store)
+    }
+
+    /// Opens the tree of the unlinked entities of `store` if it exists, without creating it.
+    pub fn get_existing_tree(store: &str, db: &Db) -> Result<Option<Tree>> {
+        let tree_name = SiblingLink::tree_name(store);
+        if !db
+            .tree_names()
+            .iter()
+            .any(|name| name == tree_name.as_bytes())
+        {
+            return Ok(None);
+        }
+        Ok(Some(db.open_tree(tree_name)?))
+    }
+
+    fn get_unlinked_stores(tree: &Tree, key: &[u8]) -> Result<Vec<String>> {
+        match tree.get(key)? {
+            Some(value) => Ok(bincode::deserialize(&value)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn set_unlinked_stores(tree: &Tree, key: &[u8], stores: &[String]) -> Result<()> {
+        if stores.is_empty() {
+            tree.remove(key)?;
+        } else {
+            tree.insert(key, bincode::serialize(stores)?)?;
+        }
+        Ok(())
+    }
+
+    fn add(store: &str, other: &str, key: &[u8], db: &Db) -> Result<()> {
+        let tree = db.open_tree(SiblingLink::tree_name(store))?;
+        let mut stores = SiblingLink::get_unlinked_stores(&tree, key)?;
+        if !stores.iter().any(|s| s == other) {
+            stores.push(String::from(other));
+        }
+        SiblingLink::set_unlinked_stores(&tree, key, &stores)
+    }
+
+    fn remove(store: &str, other: &str, key: &[u8], db: &Db) -> Result<()> {
+        if let Some(tree) = SiblingLink::get_existing_tree(store, db)? {
+            let mut stores = SiblingLink::get_unlinked_stores(&tree, key)?;
+            stores.retain(|s| s != other);
+            SiblingLink::set_unlinked_stores(&tree, key, &stores)?;
+        }
+        Ok(())
+    }
+
+    /// Breaks the link between the entities with key `key` in the sibling stores `store` and `other`.
+    pub fn unlink(store: &str, other: &str, key: &[u8], db: &Db) -> Result<()> {
+        SiblingLink::add(store, other, key, db)?;
+        SiblingLink::add(other, store, key, db)
+    }
+
+    /// Restores the link between the entities with key `key` in the sibling stores `store` and `other`.
+    pub fn relink(store: &str, other: &str, key: &[u8], db: &Db) -> Result<()> {
+        SiblingLink::remove(store, other, key, db)?;
+        SiblingLink::remove(other, store, key, db)
+    }
+
+    /// Tells whether the link between the entities with key `key` in the sibling stores `store` and `other` was broken.
+    pub fn is_unlinked(store: &str, other: &str, key: &[u8], db: &Db) -> Result<bool> {
+        SiblingLink::is_unlinked_in(&SiblingLink::get_existing_tree(store, db)?, other, key)
+    }
+
+    /// Same as [`SiblingLink::is_unlinked`], in a tree returned by [`SiblingLink::get_existing_tree`].
+    pub fn is_unlinked_in(tree: &Option<Tree>, other: &str, key: &[u8]) -> Result<bool> {
+        match tree {
+            Some(tree) => Ok(SiblingLink::get_unlinked_stores(tree, key)?
+                .iter()
+                .any(|s| s == other)),
+            None => Ok(false),
+        }
+    }
+
+    /// Forgets the broken links of the removed entity with key `key` in `store`, so that an entity saved later with
+    /// the same key is linked to its siblings again.
+    pub fn clear(store: &str, key: &[u8], db: &Db) -> Result<()> {
+        let tree = match SiblingLink::get_existing_tree(store, db)? {
+            Some(tree) => tree,
+            None => return Ok(()),
+        };
+        if let Some(value) = tree.remove(key)? {
+            for other in bincode::deserialize::<Vec<String>>(&value)? {
+                SiblingLink::remove(&other, store, key, db)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces `old_name` with `new_name` in the broken links of the sibling stores of a renamed store.
+    pub fn rename_store(old_name: &str, new_name: &str, siblings: &[String], db: &Db) -> Result<()> {
+        for sibling in siblings {
+            let tree = match SiblingLink::get_existing_tree(sibling, db)? {
+                Some(tree) => tree,
+                None => continue,
+            };
+            for elem in tree.iter() {
+                let (key, value) = elem?;
+                let mut stores = bincode::deserialize::<Vec<String>>(&value)?;
+                if let Some(store) = stores.iter_mut().find(|s| *s == old_name) {
+                    *store = String::from(new_name);
+                    SiblingLink::set_unlinked_stores(&tree, &key, &stores)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
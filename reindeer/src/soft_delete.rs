@@ -0,0 +1,88 @@
+//! # Soft delete Module
+//! This module keeps track of the entities marked as deleted with
+//! [`Entity::soft_remove`](trait.Entity.html#method.soft_remove).
+
+use std::collections::HashSet;
+use std::sync::{OnceLock, PoisonError, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sled::{Db, Tree};
+
+use crate::error::Result;
+use crate::{Entity, FromBytes};
+
+pub(crate) const DELETED_TREE_PREFIX: &str = "__$deleted_";
+
+fn enabled_stores() -> &'static RwLock<HashSet<String>> {
+    static STORES: OnceLock<RwLock<HashSet<String>>> = OnceLock::new();
+    STORES.get_or_init(Default::default)
+}
+
+pub struct SoftDelete;
+
+impl SoftDelete {
+    fn tree_name(store: &str) -> String {
+        format!("{}{}", DELETED_TREE_PREFIX, store)
+    }
+
+    fn is_enabled(store: &str) -> bool {
+        enabled_stores()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains(store)
+    }
+
+    /// Marks `store` as having deleted entities, so that reads look them up. The write lock is only taken the first
+    /// time, reads of the set being much more frequent.
+    fn enable(store: &str) {
+        if !SoftDelete::is_enabled(store) {
+            enabled_stores()
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(String::from(store));
+        }
+    }
+
+    fn get_tree(store: &str, db: &Db) -> Result<Tree> {
+        Ok(db.open_tree(SoftDelete::tree_name(store))?)
+    }
+
+    pub fn register<E: Entity>(db: &Db) -> Result<()> {
+        let tree_name = SoftDelete::tree_name(E::store_name());
+        if db
+            .tree_names()
+            .iter()
+            .any(|name| name == tree_name.as_bytes())
+        {
+            SoftDelete::enable(E::store_name());
+        }
+        Ok(())
+    }
+
+    pub fn mark(store: &str, key: &[u8], db: &Db) -> Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        SoftDelete::enable(store);
+        SoftDelete::get_tree(store, db)?.insert(key, &timestamp.to_be_bytes())?;
+        Ok(())
+    }
+
+    pub fn clear(store: &str, key: &[u8], db: &Db) -> Result<()> {
+        if SoftDelete::is_enabled(store) {
+            SoftDelete::get_tree(store, db)?.remove(key)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_deleted_at(store: &str, key: &[u8], db: &Db) -> Result<Option<u64>> {
+        if !SoftDelete::is_enabled(store) {
+            return Ok(None);
+        }
+        SoftDelete::get_tree(store, db)?
+            .get(key)?
+            .map(|value| u64::from_bytes(&value))
+            .transpose()
+    }
+}
@@ -0,0 +1,74 @@
+//! # Subscriber Module
+//! This module provides [`EntitySubscriber`](struct.EntitySubscriber.html), to be notified of changes in an entity store.
+
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use sled::{Event, Subscriber};
+
+use crate::error::Result;
+use crate::Entity;
+
+/// A change that happened to an entity of a watched store.
+#[derive(Debug)]
+pub enum EntityEvent<E> {
+    /// The entity has been saved or updated
+    Set(E),
+    /// The entity with this key has been removed
+    Remove(Vec<u8>),
+}
+
+/// Iterator over the changes of watched entities, as returned by [`Entity::watch`](trait.Entity.html#method.watch)
+/// and [`Entity::watch_all_prefix`](trait.Entity.html#method.watch_all_prefix).
+///
+/// `next` blocks until a change occurs.
+pub struct EntitySubscriber<E> {
+    subscriber: Subscriber,
+    key: Option<Vec<u8>>,
+    entity_type: PhantomData<E>,
+}
+
+impl<E: Entity> EntitySubscriber<E> {
+    pub(crate) fn new(subscriber: Subscriber, key: Option<Vec<u8>>) -> Self {
+        EntitySubscriber {
+            subscriber,
+            key,
+            entity_type: PhantomData,
+        }
+    }
+
+    /// Waits for the next change for at most `timeout`, and returns `None` if none occurred.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Option<Result<EntityEvent<E>>> {
+        loop {
+            let event = self.subscriber.next_timeout(timeout).ok()?;
+            if let Some(event) = self.convert(event) {
+                return Some(event);
+            }
+        }
+    }
+
+    fn convert(&self, event: Event) -> Option<Result<EntityEvent<E>>> {
+        if let Some(key) = &self.key {
+            if event.key() != key {
+                return None;
+            }
+        }
+        Some(match event {
+            Event::Insert { value, .. } => E::try_from_ivec(value).map(EntityEvent::Set),
+            Event::Remove { key } => Ok(EntityEvent::Remove(key.to_vec())),
+        })
+    }
+}
+
+impl<E: Entity> Iterator for EntitySubscriber<E> {
+    type Item = Result<EntityEvent<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let event = self.subscriber.next()?;
+            if let Some(event) = self.convert(event) {
+                return Some(event);
+            }
+        }
+    }
+}
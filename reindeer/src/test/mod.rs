@@ -1,12 +1,15 @@
 mod test_entities;
 
 use crate::{
-    error::Result, relation::FamilyDescriptor,
-    test::test_entities::GrandChildEntity, AutoIncrementEntity, DeletionBehaviour, Entity,
-    QueryBuilder,
+    error::Result, relation::FamilyDescriptor, test::test_entities::GrandChildEntity, AsBytes,
+    AutoIncrementEntity, ConflictPolicy, DeletionBehaviour, Entity, EntityCollection, EntityQuery, EntityRef, FromBytes,
+    FullDatabaseDump, ImportReport, IntegrityIssue, QueryBuilder, SaveBatch, SortOrder,
 };
 use test_entities::{
-    set_up, set_up_content, tear_down, ChildEntity1, ChildEntity2, Entity1, Entity2, Entity3,
+    register_entities, set_up, set_up_content, tear_down, ChildEntity1, ChildEntity2, ChildEntity3,
+    ConflictingEntity1, ConflictingEntity2, Entity1, Entity2, Entity2V2, Entity3, Entity4, GenericEntity, MigratedEntity, MigratedEntityV1,
+    NewtypeKey, NewtypeKeyEntity, RenamedEntity2, SkippedFieldEntity, TimestampedEntity,
+    TypedKeyEntity, TypedKeyEntityId, UniqueEntity, ValidatedEntity,
 };
 use uuid::Uuid;
 
@@ -523,4 +526,3007 @@ fn test_filtered_query_builder() -> Result<()> {
     assert_eq!(result[0].id,2);
     tear_down(&name)?;
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn test_estimate_count_with_filter() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let (estimate, std_error) = Entity2::estimate_count_with_filter(|e| e.prop2 > 4, 3, &db)?;
+    assert_eq!(estimate, 2.0);
+    assert_eq!(std_error, 0.0);
+    let mut e1 = Entity1 {
+        id: 0,
+        prop1: String::new(),
+    };
+    for i in 0..100 {
+        e1.prop1 = if i % 3 == 0 { String::from("match") } else { String::new() };
+        e1.save_next(&db)?;
+    }
+    let (estimate, std_error) =
+        Entity1::estimate_count_with_filter(|e| e.prop1 == "match", 20, &db)?;
+    // 7 of the 20 sampled entities (keys i * 103 / 20) match, while 34 of the 103 entities actually do
+    assert!((estimate - 7.0 / 20.0 * 103.0).abs() < 1e-9);
+    assert!(std_error > 0.0);
+    assert!((estimate - 34.0).abs() <= 2.0 * std_error);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_estimate_count_with_filter_samples_whole_store() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    for id in 0..199 {
+        Entity1 {
+            id,
+            prop1: if id >= 100 { String::from("match") } else { String::new() },
+        }
+        .save(&db)?;
+    }
+    let (estimate, std_error) =
+        Entity1::estimate_count_with_filter(|e| e.prop1 == "match", 100, &db)?;
+    // Sampled keys are i * 199 / 100 : the 49 of them from i = 51 are in the upper half, where the 99 matches are
+    assert!((estimate - 49.0 / 100.0 * 199.0).abs() < 1e-9);
+    assert!((estimate - 99.0).abs() <= 2.0 * std_error);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_entity_query() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let result = EntityQuery::<Entity2>::new()
+        .filter(|e| e.prop2 > 4)
+        .execute(&db)?;
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[0].id, "id2");
+    let mut query = EntityQuery::<Entity2>::new();
+    query.filter(|e| e.prop2 > 2).filter(|e| e.prop2 < 500);
+    assert_eq!(query.count(&db)?, 2);
+    assert_eq!(query.first(&db)?.unwrap().id, "id1");
+    assert!(query.exists(&db)?);
+    let result = query.offset(1).limit(5).execute(&db)?;
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].id, "id2");
+    assert!(!EntityQuery::<Entity2>::new()
+        .filter(|e| e.prop2 == 0)
+        .exists(&db)?);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_entity_query_order_by() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let result = EntityQuery::<Entity2>::new()
+        .order_by(|e| e.prop2, SortOrder::Descending)
+        .execute(&db)?;
+    assert_eq!(result.len(), 3);
+    assert_eq!(result[0].id, "id3");
+    assert_eq!(result[2].id, "id1");
+    let result = EntityQuery::<Entity2>::new()
+        .order_by(|e| e.prop2, SortOrder::Descending)
+        .offset(1)
+        .limit(1)
+        .execute(&db)?;
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].id, "id2");
+    let result = EntityQuery::<Entity3>::new()
+        .order_by(|e| e.some_bool, SortOrder::Descending)
+        .then_by(|e| e.id, SortOrder::Descending)
+        .execute(&db)?;
+    assert_eq!(
+        result.iter().map(|e| e.id).collect::<Vec<u32>>(),
+        vec![2, 1, 0]
+    );
+    let first = EntityQuery::<Entity1>::new()
+        .order_by(|e| e.prop1.clone(), SortOrder::Ascending)
+        .first(&db)?;
+    assert_eq!(first.unwrap().prop1, "Hello, Nancy!");
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_entity_query_delete_all() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1_0 = Entity1::get(&0, &db)?.unwrap();
+    let e2_1 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    e1_0.create_relation(
+        &e2_1,
+        DeletionBehaviour::BreakLink,
+        DeletionBehaviour::Error,
+        None,
+        &db,
+    )?;
+    assert!(EntityQuery::<Entity2>::new().delete_all(&db).is_err());
+    assert_eq!(Entity2::get_count(&db)?, 3);
+    assert_eq!(ChildEntity1::get_count(&db)?, 4);
+    Entity2::get(&String::from("id2"), &db)?.unwrap().create_relation(
+        &Entity2::get(&String::from("id3"), &db)?.unwrap(),
+        DeletionBehaviour::Error,
+        DeletionBehaviour::Error,
+        None,
+        &db,
+    )?;
+    let removed = EntityQuery::<Entity2>::new()
+        .filter(|e| e.prop2 > 4)
+        .delete_all(&db)?;
+    assert_eq!(removed, 2);
+    assert_eq!(Entity2::get_count(&db)?, 1);
+    assert_eq!(ChildEntity1::get_count(&db)?, 0);
+    assert!(crate::validate_all(&db)?.is_empty());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_entity_query_update_all() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let updated = EntityQuery::<Entity2>::new()
+        .filter(|e| e.prop2 < 10)
+        .update_all(|e| e.prop2 += 100, &db)?;
+    assert_eq!(updated, 2);
+    assert_eq!(Entity2::get(&String::from("id1"), &db)?.unwrap().prop2, 103);
+    assert_eq!(Entity2::get(&String::from("id2"), &db)?.unwrap().prop2, 105);
+    assert_eq!(
+        Entity2::get(&String::from("id3"), &db)?.unwrap().prop2,
+        1000
+    );
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_set_null() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e2_1 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    let mut child = ChildEntity3 {
+        id: (String::new(), 0),
+        label: Some(String::from("label")),
+    };
+    e2_1.save_next_child(&mut child, &db)?;
+    let mut e4 = Entity4 {
+        id: 0,
+        entity_2_id: Some(e2_1.id.clone()),
+    };
+    e4.save_next(&db)?;
+    e2_1.create_relation(
+        &e4,
+        DeletionBehaviour::SetNull(String::from("entity_2_id")),
+        DeletionBehaviour::BreakLink,
+        None,
+        &db,
+    )?;
+    Entity2::remove(&e2_1.id, &db)?;
+    let child = ChildEntity3::get(child.get_key(), &db)?.unwrap();
+    assert!(child.label.is_none());
+    let e4 = Entity4::get(e4.get_key(), &db)?.unwrap();
+    assert!(e4.entity_2_id.is_none());
+    assert!(e4.get_related::<Entity2>(&db)?.is_empty());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_related_count() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e2_1 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    let e3_1 = Entity3::get(&0, &db)?.unwrap();
+    let e3_3 = Entity3::get(&2, &db)?.unwrap();
+    assert_eq!(e2_1.get_related_count::<Entity3>(&db)?, 0);
+    for e3 in [&e3_1, &e3_3] {
+        e2_1.create_relation(
+            e3,
+            DeletionBehaviour::BreakLink,
+            DeletionBehaviour::BreakLink,
+            None,
+            &db,
+        )?;
+    }
+    assert_eq!(e2_1.get_related_count::<Entity3>(&db)?, 2);
+    assert_eq!(e2_1.get_referrer_count::<Entity3>(&db)?, 2);
+    assert_eq!(e3_1.get_related_count::<Entity2>(&db)?, 1);
+    assert_eq!(e3_1.get_referrer_count::<Entity2>(&db)?, 1);
+    assert_eq!(e2_1.get_related_count::<Entity1>(&db)?, 0);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_related_paginated() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&2, &db)?.unwrap();
+    for id in ["id3", "id1", "id2"] {
+        let e2 = Entity2::get(&String::from(id), &db)?.unwrap();
+        e1.create_relation(
+            &e2,
+            DeletionBehaviour::BreakLink,
+            DeletionBehaviour::BreakLink,
+            None,
+            &db,
+        )?;
+    }
+    let page_0 = e1.get_related_paginated::<Entity2>(0, 2, &db)?;
+    assert_eq!(page_0.len(), 2);
+    assert_eq!(page_0[0].id, "id1");
+    assert_eq!(page_0[1].id, "id2");
+    let page_1 = e1.get_related_paginated::<Entity2>(1, 2, &db)?;
+    assert_eq!(page_1.len(), 1);
+    assert_eq!(page_1[0].id, "id3");
+    assert!(e1.get_related_paginated::<Entity2>(2, 2, &db)?.is_empty());
+    assert!(e1
+        .get_related_paginated::<Entity2>(usize::MAX, 2, &db)?
+        .is_empty());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_related_with_filter() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&2, &db)?.unwrap();
+    for id in ["id1", "id2", "id3"] {
+        let e2 = Entity2::get(&String::from(id), &db)?.unwrap();
+        e1.create_relation(
+            &e2,
+            DeletionBehaviour::BreakLink,
+            DeletionBehaviour::BreakLink,
+            None,
+            &db,
+        )?;
+    }
+    let related = e1.get_related_with_filter(|e: &Entity2| e.prop2 > 4, &db)?;
+    assert_eq!(related.len(), 2);
+    assert_eq!(related[0].id, "id2");
+    assert_eq!(related[1].id, "id3");
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_create_relation_batch() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&2, &db)?.unwrap();
+    let e2s = Entity2::get_each(&[String::from("id1"), String::from("id2")], &db);
+    e1.create_relation_batch(
+        &e2s,
+        DeletionBehaviour::BreakLink,
+        DeletionBehaviour::BreakLink,
+        Some("batch"),
+        false,
+        &db,
+    )?;
+    assert_eq!(e1.get_related_count::<Entity2>(&db)?, 2);
+    assert!(e2s[1].is_related_to_with_name(&e1, "batch", &db)?);
+    let e2s = Entity2::get_each(&[String::from("id2"), String::from("id3")], &db);
+    assert!(e1
+        .create_relation_batch(
+            &e2s,
+            DeletionBehaviour::BreakLink,
+            DeletionBehaviour::BreakLink,
+            Some("batch"),
+            true,
+            &db,
+        )
+        .is_err());
+    assert_eq!(e1.get_related_count::<Entity2>(&db)?, 2);
+    e1.create_relation_batch(
+        &e2s,
+        DeletionBehaviour::BreakLink,
+        DeletionBehaviour::BreakLink,
+        Some("batch"),
+        false,
+        &db,
+    )?;
+    assert_eq!(e1.get_related_count::<Entity2>(&db)?, 3);
+    assert_eq!(e2s[0].get_related_count::<Entity1>(&db)?, 1);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_create_relation_idempotent() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&2, &db)?.unwrap();
+    let e2 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    for (relation_name, was_new) in [(None, true), (None, false), (Some("named"), true)] {
+        let result = e1.create_relation(
+            &e2,
+            DeletionBehaviour::BreakLink,
+            DeletionBehaviour::BreakLink,
+            relation_name,
+            &db,
+        )?;
+        assert_eq!(result.was_new, was_new);
+    }
+    assert_eq!(e1.get_related_count::<Entity2>(&db)?, 2);
+    assert_eq!(e2.get_related_count::<Entity1>(&db)?, 2);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_relation_payload() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&2, &db)?.unwrap();
+    let e2_1 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    let e2_2 = Entity2::get(&String::from("id2"), &db)?.unwrap();
+    let e2_3 = Entity2::get(&String::from("id3"), &db)?.unwrap();
+    e1.create_relation_with_payload(
+        &e2_1,
+        &(1.5f64, String::from("weight")),
+        DeletionBehaviour::BreakLink,
+        DeletionBehaviour::BreakLink,
+        None,
+        &db,
+    )?;
+    e1.create_relation_with_payload(
+        &e2_2,
+        &(3f64, String::from("other")),
+        DeletionBehaviour::BreakLink,
+        DeletionBehaviour::BreakLink,
+        None,
+        &db,
+    )?;
+    e1.create_relation(
+        &e2_3,
+        DeletionBehaviour::BreakLink,
+        DeletionBehaviour::BreakLink,
+        None,
+        &db,
+    )?;
+    let related = e1.get_related_with_payload::<Entity2, (f64, String)>(&db)?;
+    assert_eq!(related.len(), 2);
+    assert_eq!(related[0].0.id, "id1");
+    assert_eq!(related[0].1, (1.5f64, String::from("weight")));
+    let referrers = e2_2.get_related_with_payload::<Entity1, (f64, String)>(&db)?;
+    assert_eq!(referrers.len(), 1);
+    assert_eq!(referrers[0].1 .0, 3f64);
+    e1.remove_relation(&e2_1, &db)?;
+    assert_eq!(
+        e1.get_related_with_payload::<Entity2, (f64, String)>(&db)?
+            .len(),
+        1
+    );
+    Entity2::remove(&String::from("id2"), &db)?;
+    assert!(e1
+        .get_related_with_payload::<Entity2, (f64, String)>(&db)?
+        .is_empty());
+    let result = e2_1.create_relation_with_payload(
+        &e2_3,
+        &7u32,
+        DeletionBehaviour::BreakLink,
+        DeletionBehaviour::BreakLink,
+        Some("same_store"),
+        &db,
+    )?;
+    assert!(result.was_new);
+    assert_eq!(e2_1.get_related_with_payload::<Entity2, u32>(&db)?[0].1, 7);
+    assert_eq!(e2_3.get_related::<Entity2>(&db)?[0].id, "id1");
+    let (_, stored) = db
+        .open_tree("__$payload_rel_entity_2")?
+        .iter()
+        .next()
+        .unwrap()?;
+    assert_eq!(
+        bincode::deserialize::<crate::RelationWithPayload<u32>>(&stored).unwrap(),
+        crate::RelationWithPayload {
+            name: Some(String::from("same_store")),
+            payload: 7
+        }
+    );
+    assert!(e2_1
+        .create_relation_with_payload(
+            &e2_3,
+            &7u32,
+            DeletionBehaviour::Custom(std::sync::Arc::new(|_, _| Ok(()))),
+            DeletionBehaviour::BreakLink,
+            None,
+            &db,
+        )
+        .is_err());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_get_parent() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let child = ChildEntity1::get(&(String::from("id2"), 0), &db)?.unwrap();
+    let parent = child.get_parent::<Entity2, _>(&db)?.unwrap();
+    assert_eq!(parent.id, "id2");
+    let grand_child = GrandChildEntity::get(&((String::from("id3"), 2), 0), &db)?.unwrap();
+    let child = grand_child.get_parent::<ChildEntity1, _>(&db)?.unwrap();
+    assert_eq!(child.get_key(), &(String::from("id3"), 2));
+    let orphan = ChildEntity3 {
+        id: (String::from("id9"), 0),
+        label: None,
+    };
+    assert!(orphan.get_parent::<Entity2, _>(&db)?.is_none());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_remove_children() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e2 = Entity2::get(&String::from("id3"), &db)?.unwrap();
+    assert_eq!(e2.remove_children::<ChildEntity1>(&db)?, 3);
+    assert!(e2.get_children::<ChildEntity1>(&db)?.is_empty());
+    assert_eq!(GrandChildEntity::get_count(&db)?, 0);
+    assert!(Entity2::exists(&String::from("id3"), &db)?);
+    assert_eq!(ChildEntity1::get_count(&db)?, 1);
+    assert_eq!(e2.remove_children::<ChildEntity1>(&db)?, 0);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_save_child_updates_existing() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e2 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    let mut child = ChildEntity3 {
+        id: (String::new(), 4),
+        label: Some(String::from("first")),
+    };
+    let key = e2.save_child(&mut child, &db)?;
+    assert_eq!(key, (String::from("id1"), 4));
+    child.label = Some(String::from("second"));
+    e2.save_child(&mut child, &db)?;
+    let children = e2.get_children::<ChildEntity3>(&db)?;
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0].label, Some(String::from("second")));
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_get_sibling() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&1, &db)?.unwrap();
+    let mut sibling = Entity3 {
+        id: 0,
+        some_bool: true,
+    };
+    e1.save_sibling(&mut sibling, &db)?;
+    assert!(e1.get_sibling::<Entity3>(&db)?.unwrap().some_bool);
+    let e3 = Entity3::get(&1, &db)?.unwrap();
+    assert_eq!(
+        e3.get_sibling::<Entity1>(&db)?.unwrap().prop1,
+        "Hello, Nancy!"
+    );
+    assert!(e1.get_sibling::<Entity4>(&db).is_err());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_remove_with_sibling() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e3 = Entity3::get(&1, &db)?.unwrap();
+    assert!(Entity3::remove(&1, &db).is_err());
+    e3.remove_with_sibling::<Entity1>(&db)?;
+    assert!(!Entity1::exists(&1, &db)?);
+    assert!(!Entity3::exists(&1, &db)?);
+    let e1 = Entity1::get(&0, &db)?.unwrap();
+    let e2 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    e1.create_relation(
+        &Entity3::get(&0, &db)?.unwrap(),
+        DeletionBehaviour::Error,
+        DeletionBehaviour::Error,
+        None,
+        &db,
+    )?;
+    e1.create_relation_with_payload(
+        &e2,
+        &5u32,
+        DeletionBehaviour::BreakLink,
+        DeletionBehaviour::BreakLink,
+        None,
+        &db,
+    )?;
+    e1.remove_with_sibling::<Entity3>(&db)?;
+    assert!(!Entity1::exists(&0, &db)?);
+    assert!(!Entity3::exists(&0, &db)?);
+    assert!(e2.get_related::<Entity1>(&db)?.is_empty());
+    assert!(e2.get_related_with_payload::<Entity1, u32>(&db)?.is_empty());
+    assert!(crate::validate_all(&db)?.is_empty());
+    let e1 = Entity1::get(&2, &db)?.unwrap();
+    assert!(e1.remove_with_sibling::<Entity3>(&db).is_err());
+    assert!(Entity1::exists(&2, &db)?);
+    assert!(Entity3::exists(&2, &db)?);
+    assert!(e1.remove_with_sibling::<Entity4>(&db).is_err());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_remove_sibling_link() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&1, &db)?.unwrap();
+    let e3 = Entity3::get(&1, &db)?.unwrap();
+    e1.remove_sibling_link::<Entity3>(&db)?;
+    assert!(Entity1::exists(&1, &db)?);
+    assert!(Entity3::exists(&1, &db)?);
+    assert!(e1.get_sibling::<Entity3>(&db)?.is_none());
+    assert!(e3.get_sibling::<Entity1>(&db)?.is_none());
+    assert_eq!(Entity1::count_without_sibling::<Entity3>(&db)?, 1);
+    let mut sibling = Entity3 { id: 0, some_bool: true };
+    e1.save_sibling(&mut sibling, &db)?;
+    assert!(e1.get_sibling::<Entity3>(&db)?.unwrap().some_bool);
+    e3.remove_sibling_link::<Entity1>(&db)?;
+    Entity3::remove(&1, &db)?;
+    assert!(Entity1::exists(&1, &db)?);
+    assert!(!Entity3::exists(&1, &db)?);
+    Entity3 { id: 1, some_bool: false }.save(&db)?;
+    assert!(e1.get_sibling::<Entity3>(&db)?.is_some());
+    let e1 = Entity1::get(&2, &db)?.unwrap();
+    e1.remove_sibling_link::<Entity3>(&db)?;
+    Entity1::remove(&2, &db)?;
+    assert!(Entity3::exists(&2, &db)?);
+    assert!(e1.remove_sibling_link::<Entity4>(&db).is_err());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_validate_sibling_constraint() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    assert!(Entity1::validate_sibling_constraint::<Entity3>(&db)?.is_empty());
+    assert_eq!(Entity1::count_without_sibling::<Entity3>(&db)?, 0);
+    let e4 = Entity4 {
+        id: 1,
+        entity_2_id: None,
+    };
+    e4.save(&db)?;
+    assert_eq!(
+        Entity1::validate_sibling_constraint::<Entity4>(&db)?,
+        vec![0, 2]
+    );
+    assert_eq!(Entity1::count_without_sibling::<Entity4>(&db)?, 2);
+    assert!(Entity4::validate_sibling_constraint::<Entity1>(&db)?.is_empty());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_orphan_relations() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&0, &db)?.unwrap();
+    for id in ["id1", "id2"] {
+        let e2 = Entity2::get(&String::from(id), &db)?.unwrap();
+        e1.create_relation(
+            &e2,
+            DeletionBehaviour::BreakLink,
+            DeletionBehaviour::BreakLink,
+            None,
+            &db,
+        )?;
+    }
+    assert!(Entity1::find_orphan_relations::<Entity2>(&db)?.is_empty());
+    Entity2::get_tree(&db)?.remove(String::from("id2").as_bytes())?;
+    assert_eq!(
+        Entity1::find_orphan_relations::<Entity2>(&db)?,
+        vec![(0u32.as_bytes(), b"id2".to_vec())]
+    );
+    assert_eq!(Entity1::repair_orphan_relations::<Entity2>(&db)?, 1);
+    assert!(Entity1::find_orphan_relations::<Entity2>(&db)?.is_empty());
+    assert_eq!(e1.get_related_count::<Entity2>(&db)?, 1);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_list_all_relations_for_key() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&0, &db)?.unwrap();
+    let e2 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    e1.create_relation(
+        &e2,
+        DeletionBehaviour::BreakLink,
+        DeletionBehaviour::Error,
+        None,
+        &db,
+    )?;
+    let mut relations = crate::list_all_relations_for_key("entity_2", b"id1", &db)?;
+    relations.sort();
+    assert_eq!(
+        relations,
+        vec![
+            (
+                String::from("entity_1"),
+                String::from("entity_2"),
+                b"id1".to_vec()
+            ),
+            (
+                String::from("entity_2"),
+                String::from("entity_1"),
+                0u32.as_bytes()
+            ),
+        ]
+    );
+    assert!(crate::list_all_relations_for_key("entity_2", b"id2", &db)?.is_empty());
+    assert_eq!(crate::list_all_relations_for_key("entity_1", &0u32.as_bytes(), &db)?.len(), 2);
+    assert!(crate::list_all_relations_for_key("entity_3", &0u32.as_bytes(), &db)?.is_empty());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_export_dot() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let dot = crate::export_dot(&db)?;
+    assert!(dot.starts_with("digraph reindeer {\n"));
+    assert!(dot.ends_with("}\n"));
+    assert!(dot.contains("    \"entity_4\";\n"));
+    assert!(dot.contains("    \"entity_1\" -> \"entity_3\" [label=\"cascade\", style=dashed];\n"));
+    assert!(dot.contains("    \"entity_3\" -> \"child_entity_2\" [label=\"error\"];\n"));
+    assert!(dot.contains("    \"entity_2\" -> \"child_entity_3\" [label=\"set_null(label)\"];\n"));
+    let dot = crate::export_dot_with_data(&db)?;
+    assert!(dot.contains("    \"entity_2\" [label=\"entity_2 (3)\"];\n"));
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_get_all_registered_stores() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    let mut stores = crate::get_all_registered_stores(&db)?;
+    stores.sort();
+    assert_eq!(
+        stores,
+        vec![
+            "child_entity_1",
+            "child_entity_2",
+            "child_entity_3",
+            "entity_1",
+            "entity_2",
+            "entity_3",
+            "entity_4",
+            "grand_child_entity"
+        ]
+    );
+    assert_eq!(FamilyDescriptor::list_all(&db)?.len(), stores.len());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_validate_all() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    Entity4 {
+        id: 0,
+        entity_2_id: None,
+    }
+    .save(&db)?;
+    ChildEntity3 {
+        id: (String::from("id1"), 0),
+        label: None,
+    }
+    .save(&db)?;
+    assert!(crate::validate_all(&db)?.is_empty());
+    let e1 = Entity1::get(&0, &db)?.unwrap();
+    let e2 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    e1.create_relation(
+        &e2,
+        DeletionBehaviour::BreakLink,
+        DeletionBehaviour::BreakLink,
+        None,
+        &db,
+    )?;
+    Entity2::get_tree(&db)?.remove(b"id1")?;
+    let issues = crate::validate_all(&db)?;
+    assert_eq!(issues.len(), 2);
+    assert!(issues.contains(&IntegrityIssue::MissingEntity {
+        store: String::from("entity_2"),
+        key: b"id1".to_vec(),
+    }));
+    assert!(issues.contains(&IntegrityIssue::BrokenRelation {
+        store: String::from("entity_1"),
+        key: 0u32.as_bytes(),
+        target_store: String::from("entity_2"),
+        target_key: b"id1".to_vec(),
+    }));
+    db.drop_tree("entity_2")?;
+    let issues = crate::validate_all(&db)?;
+    assert_eq!(issues.len(), 3);
+    assert!(issues.contains(&IntegrityIssue::MissingTree {
+        store: String::from("entity_2"),
+    }));
+    assert!(crate::export_dot_with_data(&db)?.contains("    \"entity_2\" [label=\"entity_2 (missing)\"];\n"));
+    assert!(!db.tree_names().iter().any(|name| name == b"entity_2"));
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_export_import_all() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&0, &db)?.unwrap();
+    let e2 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    e1.create_relation(
+        &e2,
+        DeletionBehaviour::BreakLink,
+        DeletionBehaviour::Error,
+        None,
+        &db,
+    )?;
+    let mut dir = std::env::temp_dir();
+    dir.push(format!("{}-export", name));
+    crate::export_all(&dir, &db)?;
+    assert!(dir.join("entity_1.json").exists());
+    assert!(dir.join("relations.json").exists());
+
+    let other_name = get_random_name();
+    let mut other_dir = std::env::temp_dir();
+    other_dir.push(&other_name);
+    let other_db = crate::open(other_dir.to_str().unwrap())?;
+    crate::import_all(&dir, &other_db)?;
+    assert_eq!(Entity1::get_count(&other_db)?, 3);
+    assert_eq!(ChildEntity1::get_count(&other_db)?, 4);
+    let e1 = Entity1::get(&0, &other_db)?.unwrap();
+    assert_eq!(e1.prop1, "Hello, World!");
+    assert_eq!(e1.get_related::<Entity2>(&other_db)?[0].id, "id1");
+    assert!(Entity2::remove(&String::from("id1"), &other_db).is_err());
+    assert_eq!(crate::get_all_registered_stores(&other_db)?.len(), 8);
+    std::fs::remove_dir_all(&dir)?;
+    crate::register_all(
+        &db,
+        &[FamilyDescriptor {
+            tree_name: String::from("../escaped"),
+            sibling_trees: Vec::new(),
+            child_trees: Vec::new(),
+        }],
+    )?;
+    assert!(crate::export_all(&dir, &db).is_err());
+    assert!(!dir.exists());
+    tear_down(&other_name)?;
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_export_json_filtered() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let mut path = std::env::temp_dir();
+    path.push(format!("{}.json", name));
+    let count =
+        Entity2::export_json_filtered(|e| e.prop2 < 10, std::fs::File::create(&path)?, &db)?;
+    assert_eq!(count, 2);
+    Entity2::remove(&String::from("id1"), &db)?;
+    Entity2::remove(&String::from("id3"), &db)?;
+    Entity2::import_json(std::fs::File::open(&path)?, &db)?;
+    assert!(Entity2::exists(&String::from("id1"), &db)?);
+    assert!(!Entity2::exists(&String::from("id3"), &db)?);
+    std::fs::remove_file(&path)?;
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_json_writer_and_reader() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e2 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    let e4 = Entity4 {
+        id: 0,
+        entity_2_id: None,
+    };
+    e4.save(&db)?;
+    e2.create_relation(
+        &e4,
+        DeletionBehaviour::BreakLink,
+        DeletionBehaviour::BreakLink,
+        None,
+        &db,
+    )?;
+    let mut buffer = Vec::new();
+    Entity2::export_json_writer(&mut buffer, &db)?;
+    let mut path = std::env::temp_dir();
+    path.push(format!("{}.json", name));
+    Entity2::export_json(std::fs::File::create(&path)?, false, &db)?;
+    assert_eq!(buffer, std::fs::read(&path)?);
+    std::fs::remove_file(&path)?;
+
+    let other_name = get_random_name();
+    let other_db = set_up(&other_name)?;
+    assert_eq!(Entity2::import_json_reader(buffer.as_slice(), &other_db)?, 3);
+    assert_eq!(Entity2::get_count(&other_db)?, 3);
+    let e2 = Entity2::get(&String::from("id1"), &other_db)?.unwrap();
+    assert!(e2.is_related_to(&e4, &other_db)?);
+    tear_down(&other_name)?;
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_ndjson() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let mut buffer = Vec::new();
+    assert_eq!(Entity2::export_ndjson(&mut buffer, &db)?, 3);
+    let output = String::from_utf8(buffer).unwrap();
+    let lines = output.lines().collect::<Vec<&str>>();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], r#"{"id":"id1","prop2":3}"#);
+
+    let other_name = get_random_name();
+    let other_db = set_up(&other_name)?;
+    let input = format!("{}\n\n{}\n", lines[1], lines[2]);
+    assert_eq!(Entity2::import_ndjson(input.as_bytes(), &other_db)?, 2);
+    assert_eq!(
+        Entity2::get(&String::from("id3"), &other_db)?.unwrap().prop2,
+        1000
+    );
+    assert!(Entity2::import_ndjson("{\"id\":3}".as_bytes(), &other_db).is_err());
+    tear_down(&other_name)?;
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn test_csv() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let mut buffer = Vec::new();
+    assert_eq!(Entity2::export_csv(&mut buffer, &db)?, 3);
+    assert_eq!(
+        String::from_utf8(buffer.clone()).unwrap(),
+        "id,prop2\nid1,3\nid2,5\nid3,1000\n"
+    );
+    let other_name = get_random_name();
+    let other_db = set_up(&other_name)?;
+    assert_eq!(Entity2::import_csv(buffer.as_slice(), &other_db)?, 3);
+    assert_eq!(
+        Entity2::get(&String::from("id2"), &other_db)?.unwrap().prop2,
+        5
+    );
+    tear_down(&other_name)?;
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[cfg(feature = "messagepack")]
+#[test]
+fn test_migrate_serialization_format() -> Result<()> {
+    let name = get_random_name();
+    let mut dir = std::env::temp_dir();
+    dir.push(&name);
+    let db = crate::open(dir.to_str().unwrap())?;
+    for (id, prop2) in [("id1", 1u32), ("id2", 2)] {
+        let legacy = Entity2 {
+            id: String::from(id),
+            prop2,
+        };
+        Entity2::get_tree(&db)?.insert(id.as_bytes(), bincode::serialize(&legacy)?)?;
+    }
+    register_entities(&db)?;
+    assert!(Entity2::get(&String::from("id1"), &db).is_err());
+    assert_eq!(crate::migrate_serialization_format::<Entity2>(&db)?, 2);
+    assert_eq!(Entity2::get(&String::from("id2"), &db)?.unwrap().prop2, 2);
+    assert_eq!(crate::migrate_serialization_format::<Entity2>(&db)?, 0);
+    // Stores empty when first registered are MessagePack from the start
+    Entity1 {
+        id: 0,
+        prop1: String::from("new"),
+    }
+    .save(&db)?;
+    assert_eq!(crate::migrate_serialization_format::<Entity1>(&db)?, 0);
+    assert_eq!(Entity1::get(&0, &db)?.unwrap().prop1, "new");
+    // Entities that cannot be read as bincode abort the whole migration
+    let legacy = bincode::serialize(&Entity3 {
+        id: 1,
+        some_bool: true,
+    })?;
+    db.open_tree("__$meta")?.remove("store_format_entity_3")?;
+    Entity3 {
+        id: 0,
+        some_bool: false,
+    }
+    .save(&db)?;
+    Entity3::get_tree(&db)?.insert(1u32.as_bytes(), legacy)?;
+    Entity3::get_tree(&db)?.insert(2u32.as_bytes(), vec![0xc1])?;
+    assert!(crate::migrate_serialization_format::<Entity3>(&db).is_err());
+    assert!(Entity3::get(&1, &db).is_err());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_import_json_with_policy() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let mut path = std::env::temp_dir();
+    path.push(format!("{}.json", name));
+    Entity2::export_json(std::fs::File::create(&path)?, false, &db)?;
+    Entity2::update(&String::from("id1"), |e| e.prop2 = 42, &db)?;
+    Entity2::remove(&String::from("id3"), &db)?;
+
+    let report =
+        Entity2::import_json_with_policy(std::fs::File::open(&path)?, ConflictPolicy::Error, &db);
+    assert!(report.is_err());
+    assert!(!Entity2::exists(&String::from("id3"), &db)?);
+
+    let report =
+        Entity2::import_json_with_policy(std::fs::File::open(&path)?, ConflictPolicy::Skip, &db)?;
+    assert_eq!(
+        report,
+        ImportReport {
+            inserted: 1,
+            overwritten: 0,
+            skipped: 2
+        }
+    );
+    assert_eq!(Entity2::get(&String::from("id1"), &db)?.unwrap().prop2, 42);
+    assert!(Entity2::exists(&String::from("id3"), &db)?);
+
+    let report = Entity2::import_json_with_policy(
+        std::fs::File::open(&path)?,
+        ConflictPolicy::Overwrite,
+        &db,
+    )?;
+    assert_eq!(report.overwritten, 3);
+    assert_eq!(Entity2::get(&String::from("id1"), &db)?.unwrap().prop2, 3);
+    std::fs::remove_file(&path)?;
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_full_database_dump() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let dump = serde_json::to_string(&FullDatabaseDump::capture(&db)?)?;
+
+    let mut other_dir = std::env::temp_dir();
+    let other_name = get_random_name();
+    other_dir.push(&other_name);
+    let other_db = crate::open(other_dir.to_str().unwrap())?;
+    serde_json::from_str::<FullDatabaseDump>(&dump)?.restore(&other_db)?;
+    assert_eq!(Entity2::get_count(&other_db)?, 3);
+    assert_eq!(GrandChildEntity::get_count(&other_db)?, 3);
+    let e4 = ChildEntity1::get(&(String::from("id3"), 2), &other_db)?.unwrap();
+    assert_eq!(e4.get_related_count::<ChildEntity2>(&other_db)?, 1);
+    assert!(Entity3::remove(&2, &other_db).is_err());
+    tear_down(&other_name)?;
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_transaction() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let count = crate::transaction(&db, |tx| {
+        let mut e2 = tx.get::<Entity2>(&String::from("id1"))?.unwrap();
+        e2.prop2 = 4;
+        tx.save(&e2)?;
+        assert_eq!(tx.get::<Entity2>(&String::from("id1"))?.unwrap().prop2, 4);
+        assert_eq!(Entity2::get(&String::from("id1"), &db)?.unwrap().prop2, 3);
+        tx.save(&Entity4 {
+            id: 0,
+            entity_2_id: Some(e2.id.clone()),
+        })?;
+        tx.remove::<Entity4>(&0)?;
+        assert!(tx.get::<Entity4>(&0)?.is_none());
+        tx.save(&Entity4 {
+            id: 1,
+            entity_2_id: Some(e2.id),
+        })?;
+        Ok(2)
+    })?;
+    assert_eq!(count, 2);
+    assert_eq!(Entity2::get(&String::from("id1"), &db)?.unwrap().prop2, 4);
+    assert!(!Entity4::exists(&0, &db)?);
+    assert!(Entity4::exists(&1, &db)?);
+
+    let result = crate::transaction(&db, |tx| {
+        tx.save(&Entity4 {
+            id: 2,
+            entity_2_id: None,
+        })?;
+        tx.remove::<Entity3>(&2)
+    });
+    assert!(result.is_err());
+    assert!(!Entity4::exists(&2, &db)?);
+    assert!(Entity3::exists(&2, &db)?);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_try_update() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    Entity2::try_update(
+        &String::from("id1"),
+        |e| {
+            e.prop2 += 1;
+            Ok(())
+        },
+        &db,
+    )?;
+    assert_eq!(Entity2::get(&String::from("id1"), &db)?.unwrap().prop2, 4);
+    let result = Entity2::try_update(
+        &String::from("id1"),
+        |e| {
+            e.prop2 += 1;
+            Err(crate::Error::new(
+                crate::ErrorKind::IntegrityError,
+                String::from("Rejected"),
+            ))
+        },
+        &db,
+    );
+    assert!(result.is_err());
+    assert_eq!(Entity2::get(&String::from("id1"), &db)?.unwrap().prop2, 4);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_watch() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let timeout = std::time::Duration::from_secs(5);
+    let mut subscriber = Entity2::watch(&String::from("id1"), &db)?;
+    let mut prefix_subscriber = Entity2::watch_all_prefix(String::from("id"), &db)?;
+    Entity2 {
+        id: String::from("id10"),
+        prop2: 8,
+    }
+    .save(&db)?;
+    Entity2::update(&String::from("id1"), |e| e.prop2 = 4, &db)?;
+    Entity2::remove(&String::from("id1"), &db)?;
+    match subscriber.next_timeout(timeout) {
+        Some(Ok(crate::EntityEvent::Set(e))) => assert_eq!(e.prop2, 4),
+        _ => panic!("Expected a Set event"),
+    }
+    match subscriber.next_timeout(timeout) {
+        Some(Ok(crate::EntityEvent::Remove(key))) => assert_eq!(key, b"id1".to_vec()),
+        _ => panic!("Expected a Remove event"),
+    }
+    match prefix_subscriber.next_timeout(timeout) {
+        Some(Ok(crate::EntityEvent::Set(e))) => assert_eq!(e.id, "id10"),
+        _ => panic!("Expected a Set event"),
+    }
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_open_memory() -> Result<()> {
+    let db = crate::open_memory()?;
+    Entity2::register(&db)?;
+    Entity2 {
+        id: String::from("id1"),
+        prop2: 3,
+    }
+    .save(&db)?;
+    assert_eq!(Entity2::get_count(&db)?, 1);
+    assert_eq!(crate::open_memory()?.tree_names().len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_open_read_only() -> Result<()> {
+    let name = get_random_name();
+    let mut dir = std::env::temp_dir();
+    dir.push(&name);
+    assert!(crate::open_read_only(&dir).is_err());
+    {
+        // Without a flusher thread, the lock on the database is released as soon as it is dropped
+        let db = crate::Config::new()
+            .path(&dir)
+            .flush_every_ms(None)
+            .open()?;
+        register_entities(&db)?;
+        set_up_content(&db)?;
+        db.flush()?;
+    }
+    let db = crate::open_read_only(&dir)?;
+    assert_eq!(db.get::<Entity2>(&String::from("id1"))?.unwrap().prop2, 3);
+    assert!(db.exists::<Entity1>(&0)?);
+    assert_eq!(db.get_count::<Entity1>()?, 3);
+    assert_eq!(db.get_all::<Entity3>()?.len(), 3);
+    assert_eq!(db.get_with_filter(|e: &Entity2| e.prop2 > 3)?.len(), 2);
+    assert_eq!(db.get_count::<UniqueEntity>()?, 0);
+    drop(db);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_save_with_limit() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    let e1 = Entity1 {
+        id: 10,
+        prop1: String::from("Hello, World!"),
+    };
+    match e1.save_with_limit(8, &db) {
+        Err(e) => match e.kind() {
+            crate::ErrorKind::EntityTooLarge { size, limit } => {
+                assert_eq!(size, e1.try_into_ivec()?.len());
+                assert_eq!(limit, 8);
+            }
+            _ => panic!("Expected an EntityTooLarge error"),
+        },
+        Ok(_) => panic!("Expected an EntityTooLarge error"),
+    }
+    assert!(!Entity1::exists(&10, &db)?);
+    e1.save_with_limit(128, &db)?;
+    assert!(Entity1::exists(&10, &db)?);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_serialized_size() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&0, &db)?.unwrap();
+    assert_eq!(e1.estimate_serialized_size(), e1.try_into_ivec()?.len());
+    assert_eq!(
+        Entity1::get_stored_size(&0, &db)?,
+        Some(e1.estimate_serialized_size())
+    );
+    assert_eq!(Entity1::get_stored_size(&10, &db)?, None);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_get_tree_size_bytes() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    assert_eq!(Entity1::get_tree_size_bytes(&db)?, 0);
+    set_up_content(&db)?;
+    let mut expected = 0;
+    for e in Entity1::get_all(&db)? {
+        expected += (e.get_key().as_bytes().len() + e.estimate_serialized_size()) as u64;
+    }
+    assert_eq!(Entity1::get_tree_size_bytes(&db)?, expected);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_save_with_ttl() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e2 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    e2.save_with_ttl(std::time::Duration::from_millis(0), &db)?;
+    let e4 = Entity4 {
+        id: 0,
+        entity_2_id: None,
+    };
+    e4.save_with_ttl(std::time::Duration::from_secs(3600), &db)?;
+    assert!(Entity2::get(&String::from("id1"), &db)?.is_none());
+    assert!(Entity2::exists(&String::from("id1"), &db)?);
+    assert!(Entity4::get(&0, &db)?.is_some());
+    assert_eq!(crate::sweep_expired(&db)?, 1);
+    assert!(!Entity2::exists(&String::from("id1"), &db)?);
+    assert!(Entity4::exists(&0, &db)?);
+
+    e2.save_with_ttl(std::time::Duration::from_millis(0), &db)?;
+    e2.save(&db)?;
+    assert!(Entity2::get(&String::from("id1"), &db)?.is_some());
+    assert_eq!(crate::sweep_expired(&db)?, 0);
+
+    let sweeper = crate::run_expiry_sweeper(&db, std::time::Duration::from_millis(5));
+    e4.save_with_ttl(std::time::Duration::from_millis(0), &db)?;
+    for _ in 0..200 {
+        if !Entity4::exists(&0, &db)? {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    assert!(!Entity4::exists(&0, &db)?);
+    sweeper.stop()?;
+
+    e4.save_with_ttl(std::time::Duration::from_secs(3600), &db)?;
+    db.open_tree("__$ttl_entity_4")?.insert(0u32.as_bytes(), &[1, 2][..])?;
+    assert!(Entity4::get(&0, &db).is_err());
+    assert!(crate::sweep_expired(&db).is_err());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_soft_remove() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    Entity3::soft_remove(&1, &db)?;
+    assert!(Entity3::soft_remove(&10, &db).is_err());
+    assert!(Entity3::get_deleted_at(&1, &db)?.is_some());
+    assert!(Entity3::get_deleted_at(&0, &db)?.is_none());
+    assert_eq!(Entity3::get_all(&db)?.len(), 3);
+    let active = Entity3::get_active(&db)?;
+    assert_eq!(active.len(), 2);
+    assert!(active.iter().all(|e| e.id != 1));
+    Entity3::restore(&1, &db)?;
+    assert_eq!(Entity3::get_active(&db)?.len(), 3);
+
+    Entity2::soft_remove(&String::from("id1"), &db)?;
+    Entity2::remove(&String::from("id1"), &db)?;
+    Entity2 {
+        id: String::from("id1"),
+        prop2: 3,
+    }
+    .save(&db)?;
+    assert!(Entity2::get_deleted_at(&String::from("id1"), &db)?.is_none());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_versioned_save() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let key = String::from("id1");
+    assert_eq!(Entity2::get_version(&key, &db)?, None);
+    let mut e2 = Entity2::get(&key, &db)?.unwrap();
+    e2.prop2 = 4;
+    e2.versioned_save(0, &db)?;
+    assert_eq!(Entity2::get_version(&key, &db)?, Some(1));
+    e2.prop2 = 5;
+    match e2.versioned_save(0, &db) {
+        Err(e) => match e.kind() {
+            crate::ErrorKind::VersionConflict { expected, current } => {
+                assert_eq!(expected, 0);
+                assert_eq!(current, 1);
+            }
+            _ => panic!("Expected a VersionConflict error"),
+        },
+        Ok(_) => panic!("Expected a VersionConflict error"),
+    }
+    assert_eq!(Entity2::get(&key, &db)?.unwrap().prop2, 4);
+    e2.versioned_save(1, &db)?;
+    assert_eq!(Entity2::get(&key, &db)?.unwrap().prop2, 5);
+    assert_eq!(Entity2::get_version(&key, &db)?, Some(2));
+    db.open_tree("__$version_entity_2")?.insert(key.as_bytes(), &[2][..])?;
+    assert!(Entity2::get_version(&key, &db).is_err());
+    assert!(e2.versioned_save(2, &db).is_err());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_audit_log() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let key = String::from("id1");
+    let mut e2 = Entity2::get(&key, &db)?.unwrap();
+    e2.save_with_audit("alice", &db)?;
+    e2.prop2 = 4;
+    e2.save_with_audit("bob", &db)?;
+    Entity2 {
+        id: String::from("id10"),
+        prop2: 1,
+    }
+    .save_with_audit("alice", &db)?;
+    Entity2::remove_with_audit(&key, "carol", &db)?;
+    let log = Entity2::get_audit_log(&key, &db)?;
+    assert_eq!(
+        log.iter()
+            .map(|entry| (entry.actor.as_str(), entry.operation))
+            .collect::<Vec<_>>(),
+        vec![
+            ("alice", crate::AuditOperation::Save),
+            ("bob", crate::AuditOperation::Save),
+            ("carol", crate::AuditOperation::Remove)
+        ]
+    );
+    assert!(log
+        .iter()
+        .all(|entry| entry.entity_type == "entity_2" && entry.key_bytes == b"id1".to_vec()));
+    assert!(log.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+    assert_eq!(Entity2::get_audit_log(&String::from("id10"), &db)?.len(), 1);
+    tear_down(&name)?;
+    Ok(())
+}
+
+struct SetProp2(u32);
+
+impl crate::MigrationStep for SetProp2 {
+    fn version(&self) -> u32 {
+        self.0
+    }
+
+    fn run(&self, db: &sled::Db) -> Result<()> {
+        if self.0 == 99 {
+            return Err(crate::Error::new(
+                crate::ErrorKind::IntegrityError,
+                String::from("Failed migration"),
+            ));
+        }
+        Entity2::update(&String::from("id1"), |e| e.prop2 = self.0, db)
+    }
+}
+
+#[test]
+fn test_migrate() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    assert_eq!(crate::get_schema_version(&db)?, 0);
+    let steps: Vec<Box<dyn crate::MigrationStep>> =
+        vec![Box::new(SetProp2(2)), Box::new(SetProp2(1))];
+    crate::migrate(&steps, &db)?;
+    assert_eq!(crate::get_schema_version(&db)?, 2);
+    assert_eq!(Entity2::get(&String::from("id1"), &db)?.unwrap().prop2, 2);
+    let steps: Vec<Box<dyn crate::MigrationStep>> =
+        vec![Box::new(SetProp2(1)), Box::new(SetProp2(2)), Box::new(SetProp2(3))];
+    crate::migrate(&steps, &db)?;
+    assert_eq!(crate::get_schema_version(&db)?, 3);
+    assert_eq!(Entity2::get(&String::from("id1"), &db)?.unwrap().prop2, 3);
+    let steps: Vec<Box<dyn crate::MigrationStep>> =
+        vec![Box::new(SetProp2(99)), Box::new(SetProp2(4))];
+    assert!(crate::migrate(&steps, &db).is_err());
+    assert_eq!(crate::get_schema_version(&db)?, 4);
+    assert_eq!(Entity2::get(&String::from("id1"), &db)?.unwrap().prop2, 4);
+    db.open_tree("__$meta")?.insert("schema_version", &[4][..])?;
+    assert!(crate::get_schema_version(&db).is_err());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_migrate_field() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    #[cfg(not(feature = "messagepack"))]
+    assert!(Entity2V2::get_all(&db).is_err());
+    let count = Entity2V2::migrate_field(
+        |old: Entity2| Entity2V2 {
+            label: Some(format!("{}-{}", old.id, old.prop2)),
+            id: old.id,
+            prop2: old.prop2 as u64,
+        },
+        &db,
+    )?;
+    assert_eq!(count, 3);
+    let e2 = Entity2V2::get(&String::from("id1"), &db)?.unwrap();
+    assert_eq!(e2.prop2, 3);
+    assert_eq!(e2.label, Some(String::from("id1-3")));
+    assert_eq!(Entity2V2::get_all(&db)?.len(), 3);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_rename_store() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e2 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    let e4 = Entity4 {
+        id: 0,
+        entity_2_id: None,
+    };
+    e4.save(&db)?;
+    e2.create_relation(
+        &e4,
+        DeletionBehaviour::BreakLink,
+        DeletionBehaviour::BreakLink,
+        None,
+        &db,
+    )?;
+    Entity2::soft_remove(&String::from("id2"), &db)?;
+    // Expiry trees are opened on writes once any test has enabled TTLs for a store
+    let side_trees = ["__$ttl_", "__$version_", "__$audit_"];
+    let had_side_tree = |store: &str| -> Vec<bool> {
+        side_trees
+            .iter()
+            .map(|prefix| db.tree_names().iter().any(|name| name == format!("{}{}", prefix, store).as_bytes()))
+            .collect()
+    };
+    let before = had_side_tree("entity_2");
+    let steps: Vec<Box<dyn crate::MigrationStep>> =
+        vec![Box::new(crate::RenameStore::<RenamedEntity2>::new(1, "entity_2"))];
+    crate::migrate(&steps, &db)?;
+    assert_eq!(Entity2::get_count(&db)?, 0);
+    assert_eq!(RenamedEntity2::get_count(&db)?, 3);
+    assert_eq!(RenamedEntity2::get_active(&db)?.len(), 2);
+    let renamed = RenamedEntity2::get(&String::from("id1"), &db)?.unwrap();
+    assert_eq!(renamed.prop2, 3);
+    assert_eq!(renamed.get_related::<Entity4>(&db)?.len(), 1);
+    assert_eq!(e4.get_related::<RenamedEntity2>(&db)?.len(), 1);
+    assert!(e4.get_related::<Entity2>(&db)?.is_empty());
+    let stores = crate::get_all_registered_stores(&db)?;
+    assert!(stores.contains(&String::from("renamed_entity_2")));
+    assert!(!stores.contains(&String::from("entity_2")));
+    assert_eq!(had_side_tree("renamed_entity_2"), before);
+    assert_eq!(ChildEntity3::get_count(&db)?, 0);
+    assert!(crate::validate_all(&db)?.is_empty());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_backup_and_restore() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e2 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    let e4 = Entity4 {
+        id: 0,
+        entity_2_id: None,
+    };
+    e4.save(&db)?;
+    e2.create_relation(
+        &e4,
+        DeletionBehaviour::Cascade,
+        DeletionBehaviour::BreakLink,
+        None,
+        &db,
+    )?;
+    let mut backup_path = std::env::temp_dir();
+    backup_path.push(get_random_name());
+    crate::backup(&db, &backup_path)?;
+
+    let restored_name = get_random_name();
+    let mut restored_path = std::env::temp_dir();
+    restored_path.push(&restored_name);
+    let restored = crate::restore(&backup_path, &restored_path)?;
+    assert!(crate::restore(&backup_path, &restored_path).is_err());
+    assert_eq!(
+        crate::get_all_registered_stores(&restored)?,
+        crate::get_all_registered_stores(&db)?
+    );
+    assert_eq!(Entity1::get_count(&restored)?, 3);
+    assert_eq!(ChildEntity1::get_count(&restored)?, 4);
+    assert_eq!(e2.get_related::<Entity4>(&restored)?.len(), 1);
+    Entity2::remove(&String::from("id1"), &restored)?;
+    assert!(!Entity4::exists(&0, &restored)?);
+    assert!(Entity4::exists(&0, &db)?);
+    drop(restored);
+
+    let bytes = std::fs::read(&backup_path)?;
+    std::fs::write(&backup_path, &bytes[..bytes.len() / 2])?;
+    let truncated_name = get_random_name();
+    let mut truncated_path = std::env::temp_dir();
+    truncated_path.push(&truncated_name);
+    assert!(crate::restore(&backup_path, &truncated_path).is_err());
+    assert!(!truncated_path.exists());
+    assert!(!truncated_path.with_file_name(format!("{}.restoring", truncated_name)).exists());
+
+    std::fs::remove_file(&backup_path)?;
+    tear_down(&restored_name)?;
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_open_with_config() -> Result<()> {
+    let db = crate::open_with_config(
+        crate::Config::new()
+            .temporary(true)
+            .cache_capacity(1024 * 1024)
+            .flush_every_ms(None),
+    )?;
+    Entity1::register(&db)?;
+    Entity1 {
+        id: 0,
+        prop1: String::from("Hello, World!"),
+    }
+    .save(&db)?;
+    assert!(Entity1::exists(&0, &db)?);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_entity() -> Result<()> {
+    use crate::AsyncEntity;
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+    runtime.block_on(async {
+        let key = String::from("id1");
+        let mut e2 = Entity2::get_async(&key, &db).await?.unwrap();
+        e2.prop2 = 4;
+        e2.save_async(&db).await?;
+        Entity2::update_async(&key, |e| e.prop2 += 1, &db).await?;
+        assert_eq!(Entity2::get(&key, &db)?.unwrap().prop2, 5);
+        assert_eq!(Entity2::get_all_async(&db).await?.len(), 3);
+        assert_eq!(Entity2::get_count_async(&db).await?, 3);
+        assert_eq!(
+            Entity2::get_with_filter_async(|e| e.prop2 > 4, &db)
+                .await?
+                .len(),
+            3
+        );
+        Entity2::remove_async(&key, &db).await?;
+        assert!(!Entity2::exists_async(&key, &db).await?);
+        Ok::<(), crate::Error>(())
+    })?;
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_entity_store() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let store = crate::EntityStore::<Entity2>::new(&db);
+    let key = String::from("id1");
+    assert_eq!(store.get(&key)?.unwrap().prop2, 3);
+    store.update(&key, |e| e.prop2 = 4)?;
+    store.save(&Entity2 {
+        id: String::from("id10"),
+        prop2: 10,
+    })?;
+    let other = store.clone();
+    assert_eq!(other.get_count()?, 4);
+    assert_eq!(other.get_all()?.len(), 4);
+    assert_eq!(other.get_with_filter(|e| e.prop2 >= 4)?.len(), 4);
+    other.remove(&key)?;
+    assert!(!store.exists(&key)?);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[cfg(feature = "cache")]
+#[test]
+fn test_cached_entity_store() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let store = crate::EntityStore::<Entity2>::new(&db).with_cache(2);
+    let key = String::from("id1");
+    assert_eq!(store.get(&key)?.unwrap().prop2, 3);
+    Entity2::update(&key, |e| e.prop2 = 4, &db)?;
+    assert_eq!(store.get(&key)?.unwrap().prop2, 3);
+    store.clear_cache();
+    assert_eq!(store.get(&key)?.unwrap().prop2, 4);
+    store.update(&key, |e| e.prop2 = 5)?;
+    assert_eq!(store.get(&key)?.unwrap().prop2, 5);
+    store.save(&Entity2 {
+        id: key.clone(),
+        prop2: 6,
+    })?;
+    assert_eq!(store.clone().get(&key)?.unwrap().prop2, 6);
+    store.remove(&key)?;
+    assert!(store.get(&key)?.is_none());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_trace_hex() {
+    assert_eq!(crate::trace::hex(&[0, 1, 171, 255]), "0001abff");
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn test_entity_store_metrics() -> Result<()> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct Counter {
+        saves: AtomicUsize,
+        gets: AtomicUsize,
+        removes: AtomicUsize,
+        latencies: AtomicUsize,
+    }
+
+    impl crate::Metrics for Counter {
+        fn record_save(&self, entity_type: &str) {
+            assert_eq!(entity_type, "entity_2");
+            self.saves.fetch_add(1, Ordering::Relaxed);
+        }
+        fn record_get(&self, _entity_type: &str) {
+            self.gets.fetch_add(1, Ordering::Relaxed);
+        }
+        fn record_remove(&self, _entity_type: &str) {
+            self.removes.fetch_add(1, Ordering::Relaxed);
+        }
+        fn record_latency_ns(&self, _operation: &str, _entity_type: &str, _latency_ns: u64) {
+            self.latencies.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let counter = Arc::new(Counter::default());
+    let store = crate::EntityStore::<Entity2>::new(&db).with_metrics(counter.clone());
+    let key = String::from("id1");
+    store.get(&key)?;
+    store.clone().update(&key, |e| e.prop2 = 4)?;
+    store.remove(&key)?;
+    assert_eq!(counter.gets.load(Ordering::Relaxed), 1);
+    assert_eq!(counter.saves.load(Ordering::Relaxed), 1);
+    assert_eq!(counter.removes.load(Ordering::Relaxed), 1);
+    assert_eq!(counter.latencies.load(Ordering::Relaxed), 3);
+
+    let registry = prometheus::Registry::new();
+    let store = crate::EntityStore::<Entity2>::new(&db)
+        .with_metrics(Arc::new(crate::PrometheusMetrics::register(&registry)?));
+    store.get(&key)?;
+    store.get(&key)?;
+    let families = registry.gather();
+    let operations = families
+        .iter()
+        .find(|family| family.get_name() == "reindeer_operations_total")
+        .unwrap();
+    assert_eq!(operations.get_metric()[0].get_counter().get_value(), 2.0);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_structured_errors() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e = match Entity3::remove(&2, &db) {
+        Err(e) => e,
+        Ok(_) => panic!("Expected an IntegrityError error"),
+    };
+    assert!(matches!(e.kind(), crate::ErrorKind::IntegrityError));
+    match e.context() {
+        Some(crate::ErrorContext::RelationConstraintViolation {
+            source_store,
+            target_store,
+            behaviour,
+        }) => {
+            assert_eq!(source_store, "entity_3");
+            assert_eq!(target_store, "entity_1");
+            assert_eq!(*behaviour, DeletionBehaviour::Error);
+        }
+        _ => panic!("Expected a RelationConstraintViolation context"),
+    }
+    let e = match Entity2::try_from_ivec(sled::IVec::from(vec![255u8])) {
+        Err(e) => e,
+        Ok(_) => panic!("Expected a SerializationError error"),
+    };
+    assert!(matches!(e.kind(), crate::ErrorKind::SerializationError));
+    match e.context() {
+        Some(crate::ErrorContext::Serialization { entity_type }) => {
+            assert_eq!(*entity_type, "entity_2")
+        }
+        _ => panic!("Expected a Serialization context"),
+    }
+    assert!(std::error::Error::source(&e).is_some());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_corrupted_entity() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    Entity2::get_tree(&db)?.insert(String::from("id1").as_bytes(), vec![255u8])?;
+    assert!(Entity2::get(&String::from("id1"), &db).is_err());
+    assert!(Entity2::get_all(&db).is_err());
+    assert!(Entity2::get_with_filter(|_| true, &db).is_err());
+    assert!(Entity2::get(&String::from("id2"), &db)?.is_some());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_validate() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    ValidatedEntity::register(&db)?;
+    let mut entity = ValidatedEntity {
+        id: 1,
+        name: String::new(),
+    };
+    assert!(entity.save(&db).is_err());
+    assert!(entity.versioned_save(0, &db).is_err());
+    assert!(ValidatedEntity::get(&1, &db)?.is_none());
+    entity.name = String::from("valid");
+    entity.save(&db)?;
+    assert_eq!(ValidatedEntity::get(&1, &db)?.unwrap().name, "valid");
+    assert!(ValidatedEntity::update(&1, |e| e.name.clear(), &db).is_err());
+    assert!(EntityQuery::<ValidatedEntity>::new()
+        .update_all(|e| e.name.clear(), &db)
+        .is_err());
+    assert_eq!(ValidatedEntity::get(&1, &db)?.unwrap().name, "valid");
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_on_before_remove() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    ValidatedEntity::register(&db)?;
+    let mut entity = ValidatedEntity {
+        id: 1,
+        name: String::from("locked"),
+    };
+    entity.save(&db)?;
+    assert!(ValidatedEntity::remove(&1, &db).is_err());
+    assert!(ValidatedEntity::exists(&1, &db)?);
+    entity.name = String::from("unlocked");
+    entity.save(&db)?;
+    ValidatedEntity::remove(&1, &db)?;
+    assert!(!ValidatedEntity::exists(&1, &db)?);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_migrate_from() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    MigratedEntityV1::register(&db)?;
+    assert_eq!(crate::get_store_version("migrated_entity", &db)?, Some(1));
+    MigratedEntityV1 { id: 1, prop: 21 }.save(&db)?;
+    MigratedEntity::register(&db)?;
+    assert_eq!(crate::get_store_version("migrated_entity", &db)?, Some(2));
+    assert_eq!(MigratedEntity::get(&1, &db)?.unwrap().doubled, 42);
+    MigratedEntity {
+        id: 1,
+        prop: 21,
+        doubled: 0,
+    }
+    .save(&db)?;
+    MigratedEntity::register(&db)?;
+    assert_eq!(MigratedEntity::get(&1, &db)?.unwrap().doubled, 0);
+
+    // Entities saved before store versions were recorded are migrated from version 0
+    db.open_tree("__$meta")?.remove("store_version_migrated_entity")?;
+    MigratedEntity::get_tree(&db)?.insert(
+        1u32.as_bytes(),
+        MigratedEntityV1 { id: 1, prop: 5 }.try_into_ivec()?,
+    )?;
+    MigratedEntity::register(&db)?;
+    assert_eq!(MigratedEntity::get(&1, &db)?.unwrap().doubled, 10);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_generic_entity() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    GenericEntity::<Vec<String>>::register(&db)?;
+    GenericEntity {
+        id: 1,
+        value: vec![String::from("a"), String::from("b")],
+    }
+    .save(&db)?;
+    GenericEntity::<u32>::register(&db)?;
+    GenericEntity { id: 1, value: 2u32 }.save(&db)?;
+    assert_eq!(GenericEntity::<Vec<String>>::store_name(), "generic_entity_strings");
+    assert_eq!(GenericEntity::<u32>::store_name(), "generic_entity_u32");
+    assert_eq!(
+        GenericEntity::<Vec<String>>::get(&1, &db)?.unwrap().value,
+        vec![String::from("a"), String::from("b")]
+    );
+    assert_eq!(GenericEntity::<u32>::get(&1, &db)?.unwrap().value, 2);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_store_name_conflict() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    ConflictingEntity1::register(&db)?;
+    ConflictingEntity1::register(&db)?;
+    assert!(matches!(
+        ConflictingEntity2::register(&db).unwrap_err().kind(),
+        crate::ErrorKind::StoreNameConflict
+    ));
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_key_as_bytes() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    NewtypeKeyEntity::register(&db)?;
+    NewtypeKeyEntity {
+        id: NewtypeKey(7),
+        prop: String::from("seven"),
+    }
+    .save(&db)?;
+    assert_eq!(NewtypeKey(7).as_bytes(), 7u32.as_bytes());
+    assert_eq!(NewtypeKeyEntity::get(&NewtypeKey(7), &db)?.unwrap().prop, "seven");
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_unique_fields() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    UniqueEntity::register(&db)?;
+    let user = |id, email: &str, username: &str| UniqueEntity {
+        id,
+        email: String::from(email),
+        username: String::from(username),
+    };
+    user(1, "a@x.com", "a").save(&db)?;
+    user(1, "a@x.com", "a2").save(&db)?;
+    match user(2, "a@x.com", "b").save(&db) {
+        Err(e) => match e.context() {
+            Some(crate::ErrorContext::UniqueConstraintViolation { field, .. }) => {
+                assert_eq!(*field, "email")
+            }
+            _ => panic!("Expected a UniqueConstraintViolation error"),
+        },
+        Ok(_) => panic!("Expected a UniqueConstraintViolation error"),
+    }
+    assert!(user(2, "b@x.com", "a2").save(&db).is_err());
+    assert!(!UniqueEntity::exists(&2, &db)?);
+    user(2, "b@x.com", "a").save(&db)?;
+    user(1, "c@x.com", "c").save(&db)?;
+    user(3, "a@x.com", "d").save(&db)?;
+    UniqueEntity::remove(&3, &db)?;
+    user(4, "a@x.com", "d").save(&db)?;
+    UniqueEntity::update(&4, |e| e.email = String::from("e@x.com"), &db)?;
+    user(5, "a@x.com", "f").save(&db)?;
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_typed_key() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    TypedKeyEntity::register(&db)?;
+    let mut entity = TypedKeyEntity {
+        id: TypedKeyEntityId(String::from("a")),
+        prop: 1,
+    };
+    entity.save(&db)?;
+    assert_eq!(entity.get_key(), &TypedKeyEntityId(String::from("a")));
+    entity.set_key(&TypedKeyEntityId::from(String::from("b")));
+    assert_eq!(entity.id.0, "b");
+    entity.save(&db)?;
+    assert_eq!(TypedKeyEntity::get(&TypedKeyEntityId(String::from("a")), &db)?.unwrap().prop, 1);
+    assert_eq!(TypedKeyEntity::get_all(&db)?.len(), 2);
+    assert_eq!(TypedKeyEntity::get_tree(&db)?.first()?.unwrap().0, "a".as_bytes());
+    assert_eq!(serde_json::to_string(&TypedKeyEntityId(String::from("a")))?, r#""a""#);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_entity_skip() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    SkippedFieldEntity::register(&db)?;
+    let entity = SkippedFieldEntity {
+        id: 1,
+        prop: 2,
+        cache: Some(4),
+    };
+    entity.save(&db)?;
+    let mut entity = SkippedFieldEntity::get(&1, &db)?.unwrap();
+    assert_eq!(entity.prop, 2);
+    assert!(entity.cache.is_none());
+    assert!(entity.set_field_to_none("cache").is_err());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_from_bytes() -> Result<()> {
+    assert_eq!(u32::from_bytes(&7u32.as_bytes())?, 7);
+    assert_eq!(i64::from_bytes(&(-7i64).as_bytes())?, -7);
+    assert_eq!(String::from_bytes(&AsBytes::as_bytes(&String::from("id")))?, "id");
+    let key = (String::from("id3"), 2u32);
+    assert_eq!(<(String, u32)>::from_bytes(&key.as_bytes())?, key);
+    let key = ((String::from("id0"), 1u32), 0u32);
+    assert_eq!(<((String, u32), u32)>::from_bytes(&key.as_bytes())?, key);
+    assert!(u32::from_bytes(&[1, 2]).is_err());
+    assert!(<(String, String)>::from_bytes(b"ab").is_err());
+
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let mut keys = Entity2::get_typed_keys_all(&db)?;
+    keys.sort();
+    assert_eq!(keys, vec![String::from("id1"), String::from("id2"), String::from("id3")]);
+    assert_eq!(TypedKeyEntity::get_typed_keys_all(&db)?.len(), 0);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn test_chrono_keys() -> Result<()> {
+    use chrono::{DateTime, NaiveDate, Utc};
+
+    #[derive(serde_derive::Serialize, serde_derive::Deserialize, crate::Entity)]
+    #[entity(name = "event", crate = "crate")]
+    struct Event {
+        id: DateTime<Utc>,
+    }
+
+    let date = |year, month, day| -> DateTime<Utc> {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_utc()
+    };
+    let dates = [date(1950, 1, 1), date(1969, 12, 31), date(1970, 1, 2), date(2024, 6, 1)];
+    for d in &dates {
+        assert_eq!(DateTime::<Utc>::from_bytes(&d.as_bytes())?, *d);
+        assert_eq!(chrono::NaiveDateTime::from_bytes(&d.naive_utc().as_bytes())?, d.naive_utc());
+    }
+    for pair in dates.windows(2) {
+        assert!(pair[0].as_bytes() < pair[1].as_bytes());
+    }
+
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    Event::register(&db)?;
+    for d in &dates {
+        Event { id: *d }.save(&db)?;
+    }
+    let events = Event::get_in_range(date(1960, 1, 1), date(2000, 1, 1), &db)?;
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].id, dates[1]);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[cfg(feature = "semver")]
+#[test]
+fn test_semver_keys() -> Result<()> {
+    use semver::Version;
+
+    #[derive(serde_derive::Serialize, serde_derive::Deserialize, crate::Entity)]
+    #[entity(name = "release", crate = "crate")]
+    struct Release {
+        id: Version,
+    }
+
+    let versions: Vec<Version> = [
+        "0.9.0",
+        "1.0.0-alpha",
+        "1.0.0-alpha.1",
+        "1.0.0-alpha.beta",
+        "1.0.0-beta.2",
+        "1.0.0-beta.11",
+        "1.0.0-rc.1",
+        "1.0.0",
+        "1.0.0+build.5",
+        "1.2.10",
+        "2.0.0",
+    ]
+    .iter()
+    .map(|v| Version::parse(v).unwrap())
+    .collect();
+    for v in &versions {
+        assert_eq!(Version::from_bytes(&v.as_bytes())?, *v);
+    }
+    for pair in versions.windows(2) {
+        assert!(pair[0].as_bytes() < pair[1].as_bytes(), "{} < {}", pair[0], pair[1]);
+    }
+
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    Release::register(&db)?;
+    for v in &versions {
+        Release { id: v.clone() }.save(&db)?;
+    }
+    let releases = Release::get_in_range(Version::new(1, 0, 0), Version::new(2, 0, 0), &db)?;
+    assert_eq!(releases.len(), 3);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_ip_addr_keys() -> Result<()> {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    #[derive(serde_derive::Serialize, serde_derive::Deserialize, crate::Entity)]
+    #[entity(name = "peer", crate = "crate")]
+    struct Peer {
+        id: IpAddr,
+    }
+
+    let ips: Vec<IpAddr> = vec![
+        Ipv4Addr::new(10, 0, 0, 1).into(),
+        Ipv4Addr::new(192, 168, 1, 20).into(),
+        Ipv4Addr::new(192, 168, 200, 3).into(),
+        Ipv4Addr::new(192, 169, 0, 1).into(),
+        Ipv6Addr::LOCALHOST.into(),
+    ];
+    for ip in &ips {
+        assert_eq!(IpAddr::from_bytes(&ip.as_bytes())?, *ip);
+    }
+    assert!(IpAddr::from_bytes(&[4, 1, 2]).is_err());
+
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    Peer::register(&db)?;
+    for ip in &ips {
+        Peer { id: *ip }.save(&db)?;
+    }
+    let peers = Peer::get_with_prefix(&[4u8, 192, 168].as_slice(), &db)?;
+    assert_eq!(peers.len(), 2);
+    assert_eq!(peers[0].id, ips[1]);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_entity_ref() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&1, &db)?.unwrap();
+    let e2_1 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    let e2_2 = Entity2::get(&String::from("id2"), &db)?.unwrap();
+    e1.create_relation(&e2_1, DeletionBehaviour::BreakLink, DeletionBehaviour::BreakLink, None, &db)?;
+    e1.create_relation(&e2_2, DeletionBehaviour::BreakLink, DeletionBehaviour::BreakLink, None, &db)?;
+    let refs = e1.get_related_refs::<Entity2>(&db)?;
+    assert_eq!(refs.len(), 2);
+    assert!(!refs[0].is_loaded());
+    assert_eq!(refs[0].key_bytes(), String::from("id1").as_bytes());
+    assert_eq!(refs[0].load()?.unwrap().prop2, 3);
+    assert!(refs[0].is_loaded());
+    assert!(!refs[1].is_loaded());
+    assert_eq!(refs[1].as_ref().unwrap().prop2, 5);
+    // The cached value is kept even if the entity changes
+    Entity2::remove(&String::from("id1"), &db)?;
+    assert_eq!(refs[0].load()?.unwrap().prop2, 3);
+    let missing = EntityRef::<Entity2>::new(&String::from("id1"), &db);
+    assert!(missing.load()?.is_none());
+    assert!(missing.is_none());
+    let e2_3 = EntityRef::<Entity2>::new(&String::from("id3"), &db).into_inner()?;
+    assert_eq!(e2_3.unwrap().prop2, 1000);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_entity_collection() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let collection = EntityCollection::<Entity2>::new(&db)?;
+    assert_eq!(collection.entities().len(), 3);
+    assert_eq!(collection.entities()[b"id2".as_slice()].prop2, 5);
+    collection.save_and_sync(Entity2 {
+        id: String::from("id4"),
+        prop2: 4,
+    })?;
+    assert_eq!(collection.entities()[b"id4".as_slice()].prop2, 4);
+    assert_eq!(Entity2::get(&String::from("id4"), &db)?.unwrap().prop2, 4);
+    collection.remove_and_sync(&String::from("id1"))?;
+    assert!(!collection.entities().contains_key(b"id1".as_slice()));
+    assert!(!Entity2::exists(&String::from("id1"), &db)?);
+    // Changes made outside of the collection are applied by the watcher
+    Entity2::update(&String::from("id2"), |e| e.prop2 = 50, &db)?;
+    Entity2::remove(&String::from("id3"), &db)?;
+    let start = std::time::Instant::now();
+    while (collection.entities()[b"id2".as_slice()].prop2 != 50
+        || collection.entities().contains_key(b"id3".as_slice()))
+        && start.elapsed() < std::time::Duration::from_secs(5)
+    {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(collection.entities()[b"id2".as_slice()].prop2, 50);
+    assert_eq!(collection.entities().len(), 2);
+    drop(collection);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_get_all_parallel() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    for i in 0..500 {
+        Entity2 {
+            id: format!("parallel_{}", i),
+            prop2: i,
+        }
+        .save(&db)?;
+    }
+    let sequential = Entity2::get_all(&db)?;
+    let parallel = Entity2::get_all_parallel(&db)?;
+    assert_eq!(parallel.len(), 503);
+    assert!(sequential
+        .iter()
+        .zip(parallel.iter())
+        .all(|(s, p)| s.id == p.id && s.prop2 == p.prop2));
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_get_with_filter_parallel() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    for i in 0..500 {
+        Entity2 {
+            id: format!("parallel_{}", i),
+            prop2: i,
+        }
+        .save(&db)?;
+    }
+    let sequential = Entity2::get_with_filter(|e| e.prop2 % 7 == 0, &db)?;
+    let parallel = Entity2::get_with_filter_parallel(|e| e.prop2 % 7 == 0, &db)?;
+    assert_eq!(parallel.len(), 72);
+    assert!(sequential
+        .iter()
+        .zip(parallel.iter())
+        .all(|(s, p)| s.id == p.id));
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_save_batch() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&1, &db)?.unwrap();
+    let mut batch = SaveBatch::new();
+    for i in 0..100 {
+        batch.add(Entity2 {
+            id: format!("batch_{}", i),
+            prop2: i,
+        });
+    }
+    batch.add_with_relation(
+        Entity2 {
+            id: String::from("batch_related"),
+            prop2: 100,
+        },
+        e1,
+        DeletionBehaviour::BreakLink,
+        DeletionBehaviour::Cascade,
+        None,
+    );
+    assert_eq!(batch.len(), 101);
+    assert_eq!(batch.commit(&db)?, 101);
+    assert_eq!(Entity2::get_all(&db)?.len(), 104);
+    assert_eq!(Entity2::get(&String::from("batch_42"), &db)?.unwrap().prop2, 42);
+    let related = Entity2::get(&String::from("batch_related"), &db)?
+        .unwrap()
+        .get_related::<Entity1>(&db)?;
+    assert_eq!(related.len(), 1);
+    assert_eq!(*related[0].get_key(), 1);
+
+    let mut batch = SaveBatch::new();
+    batch.add(ValidatedEntity {
+        id: 1,
+        name: String::from("valid"),
+    });
+    batch.add(ValidatedEntity {
+        id: 2,
+        name: String::new(),
+    });
+    assert!(batch.commit(&db).is_err());
+    assert!(ValidatedEntity::get(&1, &db)?.is_none());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_get_tree_stats() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    let stats = Entity1::get_tree_stats(&db)?;
+    assert_eq!(stats, crate::TreeStats::default());
+    set_up_content(&db)?;
+    let stats = Entity1::get_tree_stats(&db)?;
+    assert_eq!(stats.count, 3);
+    assert_eq!(stats.total_key_bytes, 12);
+    let value_bytes = Entity1::get_tree(&db)?
+        .iter()
+        .values()
+        .map(|v| v.unwrap().len() as u64)
+        .sum::<u64>();
+    assert_eq!(stats.total_value_bytes, value_bytes);
+    assert_eq!(stats.first_key, Some(0u32.as_bytes()));
+    assert_eq!(stats.last_key, Some(2u32.as_bytes()));
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_error_source() -> Result<()> {
+    use std::error::Error;
+    let mut dest = std::env::temp_dir();
+    dest.push(get_random_name());
+    let e = crate::restore(std::path::Path::new("./does-not-exist.backup"), &dest).unwrap_err();
+    assert!(matches!(e.kind(), crate::ErrorKind::IOError));
+    let source = e.source().unwrap();
+    assert_eq!(
+        source.downcast_ref::<std::io::Error>().unwrap().kind(),
+        std::io::ErrorKind::NotFound
+    );
+    Ok(())
+}
+
+#[test]
+fn test_entity_not_found() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&1, &db)?.unwrap();
+    assert!(e1.get_single_related::<Entity2>(&db)?.is_none());
+    let e2 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    e1.create_relation(&e2, DeletionBehaviour::BreakLink, DeletionBehaviour::BreakLink, None, &db)?;
+    assert_eq!(e1.get_single_related::<Entity2>(&db)?.unwrap().prop2, 3);
+    // Removing the entity behind reindeer's back leaves a dangling relation
+    Entity2::get_tree(&db)?.remove("id1")?;
+    match e1.get_single_related::<Entity2>(&db) {
+        Err(e) => match e.context() {
+            Some(crate::ErrorContext::EntityNotFound { store, key }) => {
+                assert_eq!(*store, "entity_2");
+                assert_eq!(*key, b"id1".to_vec());
+            }
+            _ => panic!("Expected an EntityNotFound error"),
+        },
+        Ok(_) => panic!("Expected an error"),
+    }
+    assert!(matches!(
+        Entity2::soft_remove(&String::from("id1"), &db).unwrap_err().kind(),
+        crate::ErrorKind::EntityNotFound
+    ));
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_get_each_nullable() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let entities = Entity1::get_each_nullable(&[2, 7, 0], &db)?;
+    assert_eq!(entities.len(), 3);
+    assert_eq!(entities[0].as_ref().unwrap().prop1, "Hello, Steeve!");
+    assert!(entities[1].is_none());
+    assert_eq!(*entities[2].as_ref().unwrap().get_key(), 0);
+    assert!(Entity1::get_each_nullable(&[], &db)?.is_empty());
+    Entity1::get_tree(&db)?.insert(7u32.as_bytes(), vec![1u8])?;
+    assert!(Entity1::get_each_nullable(&[2, 7, 0], &db).is_err());
+    assert_eq!(Entity1::get_each(&[2, 7, 0], &db).len(), 2);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_get_all_lenient() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    Entity1::get_tree(&db)?.insert(1u32.as_bytes(), vec![1u8])?;
+    assert!(Entity1::get_all(&db).is_err());
+    let (entities, errors) = Entity1::get_all_lenient(&db)?;
+    assert_eq!(entities.len(), 2);
+    assert_eq!(*entities[1].get_key(), 2);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, 1u32.as_bytes());
+    assert!(matches!(
+        errors[0].1.kind(),
+        crate::ErrorKind::SerializationError
+    ));
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_contains_all_any() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    assert!(Entity1::contains_all(&[0, 1, 2], &db)?);
+    assert!(!Entity1::contains_all(&[0, 5, 2], &db)?);
+    assert!(Entity1::contains_all(&[], &db)?);
+    assert!(Entity1::contains_any(&[7, 2], &db)?);
+    assert!(!Entity1::contains_any(&[7, 8], &db)?);
+    assert!(!Entity1::contains_any(&[], &db)?);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_get_missing() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    assert_eq!(Entity1::get_missing(&[8, 0, 5, 2], &db)?, vec![8, 5]);
+    assert!(Entity1::get_missing(&[0, 1], &db)?.is_empty());
+    let missing = Entity2::get_missing(&[String::from("id1"), String::from("id9")], &db)?;
+    assert_eq!(missing, vec![String::from("id9")]);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_get_related_keys() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&1, &db)?.unwrap();
+    assert!(e1.get_related_keys::<Entity2>(&db)?.is_empty());
+    for id in ["id1", "id3"] {
+        let e2 = Entity2::get(&String::from(id), &db)?.unwrap();
+        e1.create_relation(&e2, DeletionBehaviour::BreakLink, DeletionBehaviour::BreakLink, None, &db)?;
+    }
+    let keys = e1.get_related_keys::<Entity2>(&db)?;
+    assert_eq!(keys, vec![b"id1".to_vec(), b"id3".to_vec()]);
+    let related = Entity2::get_each_u8(&keys[1..], &db);
+    assert_eq!(related.len(), 1);
+    assert_eq!(related[0].prop2, 1000);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_remove_all_relations_for_key() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&0, &db)?.unwrap();
+    let e2 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    let e3 = Entity3::get(&1, &db)?.unwrap();
+    e2.create_relation(&e1, DeletionBehaviour::Error, DeletionBehaviour::Error, None, &db)?;
+    e2.create_relation(&e3, DeletionBehaviour::Error, DeletionBehaviour::Error, Some("rel"), &db)?;
+    e2.create_relation_with_payload(&e3, &5u32, DeletionBehaviour::Error, DeletionBehaviour::Error, Some("payload"), &db)?;
+    assert_eq!(crate::remove_all_relations_for_key("entity_2", b"id1", &db)?, 3);
+    assert!(crate::list_all_relations_for_key("entity_2", b"id1", &db)?.is_empty());
+    assert!(e1.get_related::<Entity2>(&db)?.is_empty());
+    assert!(e3.get_related_with_payload::<Entity2, u32>(&db)?.is_empty());
+    assert!(Entity2::exists(&String::from("id1"), &db)?);
+    assert_eq!(crate::remove_all_relations_for_key("entity_2", b"id1", &db)?, 0);
+    Entity1::remove(&0, &db)?;
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_get_related_ids_only() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e2 = Entity2::get(&String::from("id2"), &db)?.unwrap();
+    for id in [2, 0] {
+        let e1 = Entity1::get(&id, &db)?.unwrap();
+        e2.create_relation(&e1, DeletionBehaviour::BreakLink, DeletionBehaviour::BreakLink, None, &db)?;
+    }
+    assert_eq!(e2.get_related_ids_only::<Entity1>(&db)?, vec![0, 2]);
+    let e1 = Entity1::get(&0, &db)?.unwrap();
+    assert_eq!(
+        e1.get_related_ids_only::<Entity2>(&db)?,
+        vec![String::from("id2")]
+    );
+    assert!(e1.get_related_ids_only::<Entity3>(&db)?.is_empty());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_register_all() -> Result<()> {
+    let db = crate::open_memory()?;
+    let descriptor = Entity1::family_descriptor();
+    assert_eq!(descriptor.tree_name, "entity_1");
+    assert_eq!(
+        descriptor.sibling_trees,
+        vec![(String::from("entity_3"), DeletionBehaviour::Cascade)]
+    );
+    assert!(descriptor.child_trees.is_empty());
+    crate::register_all(&db, &[descriptor, Entity3::family_descriptor()])?;
+    let mut stores = crate::get_all_registered_stores(&db)?;
+    stores.sort();
+    assert_eq!(stores, vec![String::from("entity_1"), String::from("entity_3")]);
+    Entity1 {
+        id: 0,
+        prop1: String::from("Hello"),
+    }
+    .save(&db)?;
+    Entity3 {
+        id: 0,
+        some_bool: true,
+    }
+    .save(&db)?;
+    Entity1::remove(&0, &db)?;
+    assert!(!Entity3::exists(&0, &db)?);
+    Ok(())
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_get_with_filter_chunked() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    for i in 0..500 {
+        Entity2 {
+            id: format!("chunked_{}", i),
+            prop2: i,
+        }
+        .save(&db)?;
+    }
+    let sequential = Entity2::get_with_filter(|e| e.prop2 % 3 == 0, &db)?;
+    for chunk_size in [0, 7, 64, 1000] {
+        let chunked = Entity2::get_with_filter_chunked(|e| e.prop2 % 3 == 0, chunk_size, &db)?;
+        assert_eq!(chunked.len(), sequential.len());
+        assert!(sequential
+            .iter()
+            .zip(chunked.iter())
+            .all(|(s, c)| s.id == c.id));
+    }
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_remove_prefixed() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    // Every entity of entity_3 has a sibling in entity_1 with an Error behaviour
+    assert!(Entity3::remove_prefixed(Vec::<u8>::new(), &db).is_err());
+    assert_eq!(Entity3::get_all(&db)?.len(), 3);
+    assert_eq!(ChildEntity1::get_all(&db)?.len(), 4);
+    Entity2::get(&String::from("id1"), &db)?.unwrap().create_relation(
+        &Entity2::get(&String::from("id2"), &db)?.unwrap(),
+        DeletionBehaviour::Error,
+        DeletionBehaviour::Error,
+        None,
+        &db,
+    )?;
+    Entity2::remove_prefixed(String::from("id"), &db)?;
+    assert!(Entity2::get_all(&db)?.is_empty());
+    assert!(ChildEntity1::get_all(&db)?.is_empty());
+    assert!(GrandChildEntity::get_all(&db)?.is_empty());
+    assert!(crate::validate_all(&db)?.is_empty());
+    ChildEntity2::remove_prefixed(2u32, &db)?;
+    assert!(ChildEntity2::get_all(&db)?.is_empty());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_filter_remove() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    Entity3 {
+        id: 5,
+        some_bool: false,
+    }
+    .save(&db)?;
+    let (removed, blocked) = Entity3::filter_remove(|e| e.id != 1, &db)?;
+    // Entities 0 and 2 of entity_3 have a sibling in entity_1 with an Error behaviour
+    assert_eq!(removed.len(), 1);
+    assert_eq!(*removed[0].get_key(), 5);
+    assert_eq!(blocked.len(), 2);
+    assert_eq!(*blocked[0].0.get_key(), 0);
+    assert!(matches!(
+        blocked[0].1.kind(),
+        crate::ErrorKind::IntegrityError
+    ));
+    assert_eq!(*blocked[1].0.get_key(), 2);
+    assert_eq!(Entity3::get_all(&db)?.len(), 3);
+    let (removed, blocked) = Entity2::filter_remove(|e| e.prop2 > 4, &db)?;
+    assert_eq!(removed.len(), 2);
+    assert!(blocked.is_empty());
+    assert_eq!(Entity2::get_all(&db)?.len(), 1);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_filter_remove_related_entities() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let mut e2 = Entity2 {
+        id: String::from("id4"),
+        prop2: 100,
+    };
+    e2.save(&db)?;
+    let mut related = e2.clone();
+    related.set_key(&String::from("id5"));
+    related.save(&db)?;
+    e2.create_relation(
+        &related,
+        DeletionBehaviour::Error,
+        DeletionBehaviour::Error,
+        None,
+        &db,
+    )?;
+    e2.set_key(&String::from("id6"));
+    e2.save(&db)?;
+    e2.create_relation(
+        &Entity1::get(&0, &db)?.unwrap(),
+        DeletionBehaviour::Error,
+        DeletionBehaviour::Error,
+        None,
+        &db,
+    )?;
+    // Entities id4 and id5 only prevent the removal of each other, while id6 is blocked by an entity of entity_1
+    let (removed, blocked) = Entity2::filter_remove(|e| e.prop2 == 100, &db)?;
+    assert_eq!(
+        removed.iter().map(|e| e.get_key().clone()).collect::<Vec<String>>(),
+        vec![String::from("id4"), String::from("id5")]
+    );
+    assert_eq!(blocked.len(), 1);
+    assert_eq!(*blocked[0].0.get_key(), "id6");
+    assert!(matches!(
+        blocked[0].1.kind(),
+        crate::ErrorKind::IntegrityError
+    ));
+    assert!(!Entity2::exists(&String::from("id4"), &db)?);
+    assert!(!Entity2::exists(&String::from("id5"), &db)?);
+    assert!(Entity2::exists(&String::from("id6"), &db)?);
+    assert_eq!(e2.get_related::<Entity1>(&db)?.len(), 1);
+    assert!(crate::validate_all(&db)?.is_empty());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_versioned_update() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let key = String::from("id1");
+    assert!(Entity2::versioned_update(&key, 0, |e| e.prop2 = 10, &db)?);
+    assert_eq!(Entity2::get_version(&key, &db)?, Some(1));
+    assert_eq!(Entity2::get(&key, &db)?.unwrap().prop2, 10);
+    // A stale version does not overwrite the entity
+    assert!(!Entity2::versioned_update(&key, 0, |e| e.prop2 = 20, &db)?);
+    assert_eq!(Entity2::get(&key, &db)?.unwrap().prop2, 10);
+    assert_eq!(Entity2::get_version(&key, &db)?, Some(1));
+    let mut e2 = Entity2::get(&key, &db)?.unwrap();
+    e2.prop2 = 30;
+    e2.versioned_save(1, &db)?;
+    assert!(Entity2::versioned_update(&key, 2, |e| e.prop2 += 1, &db)?);
+    assert_eq!(Entity2::get(&key, &db)?.unwrap().prop2, 31);
+    assert!(matches!(
+        Entity2::versioned_update(&String::from("id9"), 0, |e| e.prop2 = 1, &db)
+            .unwrap_err()
+            .kind(),
+        crate::ErrorKind::EntityNotFound
+    ));
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_atomic_save() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    ValidatedEntity::register(&db)?;
+    let count = crate::atomic_save(&db)
+        .add(&Entity1 {
+            id: 5,
+            prop1: String::from("Hello"),
+        })
+        .add(&Entity2 {
+            id: String::from("id5"),
+            prop2: 5,
+        })
+        .add(&ValidatedEntity {
+            id: 5,
+            name: String::from("valid"),
+        })
+        .commit()?;
+    assert_eq!(count, 3);
+    assert!(Entity1::exists(&5, &db)?);
+    assert!(Entity2::exists(&String::from("id5"), &db)?);
+    assert!(ValidatedEntity::exists(&5, &db)?);
+    // An invalid entity prevents all the others from being written
+    let result = crate::atomic_save(&db)
+        .add(&Entity1 {
+            id: 6,
+            prop1: String::from("Hello"),
+        })
+        .add(&ValidatedEntity {
+            id: 6,
+            name: String::new(),
+        })
+        .add(&Entity2 {
+            id: String::from("id6"),
+            prop2: 6,
+        })
+        .commit();
+    assert!(result.is_err());
+    assert!(!Entity1::exists(&6, &db)?);
+    assert!(!Entity2::exists(&String::from("id6"), &db)?);
+    assert!(!ValidatedEntity::exists(&6, &db)?);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_save_or_update() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let merge = |existing: &mut Entity2, new: &Entity2| existing.prop2 += new.prop2;
+    let new = Entity2 {
+        id: String::from("id9"),
+        prop2: 4,
+    };
+    Entity2::save_or_update(&String::from("id9"), new, merge, &db)?;
+    assert_eq!(Entity2::get(&String::from("id9"), &db)?.unwrap().prop2, 4);
+    let new = Entity2 {
+        id: String::from("id1"),
+        prop2: 4,
+    };
+    Entity2::save_or_update(&String::from("id1"), new, merge, &db)?;
+    assert_eq!(Entity2::get(&String::from("id1"), &db)?.unwrap().prop2, 7);
+    assert_eq!(Entity2::get_all(&db)?.len(), 4);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_atomic_increment_field() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let key = String::from("id1");
+    let threads = (0..4)
+        .map(|_| {
+            let db = db.clone();
+            let key = key.clone();
+            std::thread::spawn(move || -> Result<()> {
+                for _ in 0..25 {
+                    Entity2::atomic_increment_field(&key, |e, d| e.prop2 += d as u32, 2, &db)?;
+                }
+                Ok(())
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap()?;
+    }
+    assert_eq!(Entity2::get(&key, &db)?.unwrap().prop2, 203);
+    Entity2::atomic_increment_field(&key, |e, d| e.prop2 = (e.prop2 as i64 + d) as u32, -3, &db)?;
+    assert_eq!(Entity2::get(&key, &db)?.unwrap().prop2, 200);
+    assert!(matches!(
+        Entity2::atomic_increment_field(&String::from("id9"), |e, d| e.prop2 += d as u32, 1, &db)
+            .unwrap_err()
+            .kind(),
+        crate::ErrorKind::EntityNotFound
+    ));
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_touch() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    TimestampedEntity {
+        id: 1,
+        prop: String::from("content"),
+        modified_at: 0,
+    }
+    .save(&db)?;
+    TimestampedEntity::touch(&1, &db)?;
+    let touched = TimestampedEntity::get(&1, &db)?.unwrap();
+    assert!(touched.modified_at > 0);
+    assert_eq!(touched.prop, "content");
+    assert!(matches!(
+        TimestampedEntity::touch(&2, &db).unwrap_err().kind(),
+        crate::ErrorKind::EntityNotFound
+    ));
+    set_up_content(&db)?;
+    let before = Entity1::get(&0, &db)?.unwrap();
+    assert!(Entity1::touch(&0, &db).is_err());
+    assert_eq!(Entity1::get(&0, &db)?.unwrap().prop1, before.prop1);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_copy_to() -> Result<()> {
+    let source_name = get_random_name();
+    let dest_name = get_random_name();
+    let source = set_up(&source_name)?;
+    let dest = set_up(&dest_name)?;
+    set_up_content(&source)?;
+    let e1 = Entity1::get(&0, &source)?.unwrap();
+    e1.create_relation(
+        &Entity2::get(&String::from("id1"), &source)?.unwrap(),
+        DeletionBehaviour::BreakLink,
+        DeletionBehaviour::BreakLink,
+        None,
+        &source,
+    )?;
+    assert!(Entity2::copy_to(&String::from("id2"), &source, &dest)?);
+    assert!(!Entity2::copy_to(&String::from("id9"), &source, &dest)?);
+    assert_eq!(Entity2::get(&String::from("id2"), &dest)?.unwrap().prop2, 5);
+    assert_eq!(Entity2::get_count(&dest)?, 1);
+    assert_eq!(Entity1::copy_all_to(&source, &dest)?, 3);
+    assert_eq!(Entity1::get_count(&dest)?, 3);
+    assert_eq!(e1.get_related::<Entity2>(&source)?.len(), 1);
+    assert!(e1.get_related::<Entity2>(&dest)?.is_empty());
+    tear_down(&source_name)?;
+    tear_down(&dest_name)?;
+    Ok(())
+}
+
+#[test]
+fn test_get_related_sorted() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&0, &db)?.unwrap();
+    for id in ["id3", "id1", "id2"] {
+        e1.create_relation(
+            &Entity2::get(&String::from(id), &db)?.unwrap(),
+            DeletionBehaviour::BreakLink,
+            DeletionBehaviour::BreakLink,
+            Some("relation"),
+            &db,
+        )?;
+    }
+    let keys = |related: Vec<Entity2>| related.into_iter().map(|e| e.id).collect::<Vec<String>>();
+    assert_eq!(keys(e1.get_related::<Entity2>(&db)?), ["id1", "id2", "id3"]);
+    assert_eq!(keys(e1.get_related_with_name::<Entity2>("relation", &db)?), ["id1", "id2", "id3"]);
+    assert_eq!(keys(e1.get_related_with_filter(|e: &Entity2| e.prop2 > 3, &db)?), ["id2", "id3"]);
+    assert_eq!(
+        e1.get_related_ids_only::<Entity2>(&db)?,
+        ["id1", "id2", "id3"]
+    );
+    assert_eq!(e1.get_single_related::<Entity2>(&db)?.unwrap().id, "id1");
+    assert_eq!(
+        e1.get_single_related_with_name::<Entity2>("relation", &db)?
+            .unwrap()
+            .id,
+        "id1"
+    );
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_remove_relation_cascade() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let e1 = Entity1::get(&0, &db)?.unwrap();
+    let e2 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    e1.create_relation(
+        &e2,
+        DeletionBehaviour::Error,
+        DeletionBehaviour::Error,
+        None,
+        &db,
+    )?;
+    e1.remove_relation_cascade(&e2, &db)?;
+    assert!(!Entity2::exists(&String::from("id1"), &db)?);
+    assert!(e1.get_related::<Entity2>(&db)?.is_empty());
+    assert!(Entity1::exists(&0, &db)?);
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_custom_deletion_behaviour() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    let mut descriptor = Entity2::family_descriptor();
+    for (tree_name, behaviour) in &mut descriptor.child_trees {
+        if tree_name == "child_entity_1" {
+            *behaviour = DeletionBehaviour::Custom(std::sync::Arc::new(|key, db| {
+                if let Some(value) = ChildEntity1::get_tree(db)?.get(key)? {
+                    db.open_tree("archived_child_entity_1")?.insert(key, value)?;
+                }
+                ChildEntity1::remove_from_u8_array(key, db)
+            }));
+        }
+    }
+    crate::register_all(&db, &[descriptor])?;
+    // Registering the entity again keeps the custom behaviour
+    Entity2::register(&db)?;
+    Entity2::remove(&String::from("id3"), &db)?;
+    assert!(!Entity2::exists(&String::from("id3"), &db)?);
+    assert_eq!(db.open_tree("archived_child_entity_1")?.len(), 3);
+    assert_eq!(ChildEntity1::get_count(&db)?, 1);
+
+    // Other databases use the behaviours they were registered with
+    let other_name = get_random_name();
+    let other_db = set_up(&other_name)?;
+    set_up_content(&other_db)?;
+    Entity2::remove(&String::from("id3"), &other_db)?;
+    assert_eq!(other_db.open_tree("archived_child_entity_1")?.len(), 0);
+    assert_eq!(ChildEntity1::get_count(&other_db)?, 1);
+    tear_down(&other_name)?;
+    let e1 = Entity1::get(&0, &db)?.unwrap();
+    let e2 = Entity2::get(&String::from("id1"), &db)?.unwrap();
+    assert!(e1
+        .create_relation(
+            &e2,
+            DeletionBehaviour::Custom(std::sync::Arc::new(|_, _| Ok(()))),
+            DeletionBehaviour::BreakLink,
+            None,
+            &db,
+        )
+        .is_err());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_unique_fields_on_every_write() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    UniqueEntity::register(&db)?;
+    let user = |id, email: &str, username: &str| UniqueEntity {
+        id,
+        email: String::from(email),
+        username: String::from(username),
+    };
+    user(1, "a@x.com", "a").save(&db)?;
+    user(2, "b@x.com", "b").save(&db)?;
+    assert!(UniqueEntity::update(&2, |e| e.email = String::from("a@x.com"), &db).is_err());
+    assert!(user(2, "a@x.com", "b").versioned_save(0, &db).is_err());
+    assert!(
+        UniqueEntity::versioned_update(&2, 0, |e| e.username = String::from("a"), &db).is_err()
+    );
+    assert!(EntityQuery::<UniqueEntity>::new()
+        .update_all(|e| e.username = String::from("same"), &db)
+        .is_err());
+    let mut batch = SaveBatch::new();
+    batch.add(user(3, "c@x.com", "c"));
+    batch.add(user(4, "c@x.com", "d"));
+    assert!(batch.commit(&db).is_err());
+    assert!(!UniqueEntity::exists(&3, &db)?);
+    assert_eq!(UniqueEntity::get(&2, &db)?.unwrap().email, "b@x.com");
+    UniqueEntity::update(&2, |e| e.email = String::from("c@x.com"), &db)?;
+    user(3, "b@x.com", "c").save(&db)?;
+
+    UniqueEntity::get_tree(&db)?.insert(
+        5u32.as_bytes(),
+        user(5, "a@x.com", "e").try_into_ivec()?,
+    )?;
+    match UniqueEntity::register(&db) {
+        Err(e) => assert!(matches!(
+            e.kind(),
+            crate::ErrorKind::UniqueConstraintViolation
+        )),
+        Ok(_) => panic!("Expected a UniqueConstraintViolation error"),
+    }
+    UniqueEntity::get_tree(&db)?.insert(
+        5u32.as_bytes(),
+        user(5, "e@x.com", "e").try_into_ivec()?,
+    )?;
+    UniqueEntity::register(&db)?;
+    assert!(user(6, "e@x.com", "f").save(&db).is_err());
+    tear_down(&name)?;
+    Ok(())
+}
+
+#[test]
+fn test_transaction_concurrent_updates() -> Result<()> {
+    let name = get_random_name();
+    let db = set_up(&name)?;
+    set_up_content(&db)?;
+    UniqueEntity::register(&db)?;
+    let key = String::from("id1");
+    let threads = (0..4)
+        .map(|_| {
+            let db = db.clone();
+            let key = key.clone();
+            std::thread::spawn(move || -> Result<()> {
+                for _ in 0..25 {
+                    crate::transaction(&db, |tx| {
+                        let mut e2 = tx.get::<Entity2>(&key)?.unwrap();
+                        e2.prop2 += 1;
+                        tx.save(&e2)
+                    })?;
+                }
+                Ok(())
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap()?;
+    }
+    assert_eq!(Entity2::get(&key, &db)?.unwrap().prop2, 103);
+
+    let user = |id, email: &str| UniqueEntity {
+        id,
+        email: String::from(email),
+        username: format!("user{}", id),
+    };
+    user(1, "a@x.com").save(&db)?;
+    assert!(crate::atomic_save(&db)
+        .add(&user(2, "b@x.com"))
+        .add(&user(3, "a@x.com"))
+        .commit()
+        .is_err());
+    assert!(!UniqueEntity::exists(&2, &db)?);
+    crate::transaction(&db, |tx| {
+        tx.save(&user(0, "a@x.com"))?;
+        tx.remove::<UniqueEntity>(&1)
+    })?;
+    assert!(user(4, "a@x.com").save(&db).is_err());
+    tear_down(&name)?;
+    Ok(())
+}
@@ -16,7 +16,7 @@ pub struct Entity1 {
 
 #[derive(Serialize, Deserialize, Clone,Entity)]
 #[entity(name = "entity_2",version = 1,crate = "crate")]
-#[children(("child_entity_1",Cascade))]
+#[children(("child_entity_1",Cascade),("child_entity_3",SetNull(label)))]
 pub struct Entity2 {
     pub id: String,
     pub prop2: u32,
@@ -50,20 +50,225 @@ pub struct GrandChildEntity {
     id: ((String, u32), u32),
 }
 
+#[derive(Serialize, Deserialize,Entity)]
+#[entity(name = "entity_4",version = 1,crate = "crate")]
+pub struct Entity4 {
+    pub id: u32,
+    pub entity_2_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize,Entity)]
+#[entity(name = "child_entity_3",version = 1,crate = "crate")]
+pub struct ChildEntity3 {
+    pub id: (String, u32),
+    pub label: Option<String>,
+}
+
+#[derive(Serialize, Deserialize,Entity)]
+#[entity(name = "entity_2",version = 2,migrate_from = "migrate_entity_2",crate = "crate")]
+#[children(("child_entity_1",Cascade),("child_entity_3",SetNull(label)))]
+pub struct Entity2V2 {
+    pub id: String,
+    pub prop2: u64,
+    pub label: Option<String>,
+}
+
+#[derive(Serialize, Deserialize,Entity)]
+#[entity(name = "renamed_entity_2",version = 1,crate = "crate")]
+#[children(("child_entity_1",Cascade),("child_entity_3",SetNull(label)))]
+pub struct RenamedEntity2 {
+    pub id: String,
+    pub prop2: u32,
+}
+
+#[derive(Serialize, Deserialize, Entity)]
+#[entity(name = "migrated_entity", version = 1, crate = "crate")]
+pub struct MigratedEntityV1 {
+    pub id: u32,
+    pub prop: u32,
+}
+
+#[derive(Serialize, Deserialize, Entity)]
+#[entity(name = "migrated_entity", version = 2, migrate_from = "migrate_entity", crate = "crate")]
+pub struct MigratedEntity {
+    pub id: u32,
+    pub prop: u32,
+    pub doubled: u32,
+}
+
+fn migrate_entity_2(old_version: u32, db: &Db) -> Result<()> {
+    if old_version < 2 {
+        Entity2V2::migrate_field(
+            |old: Entity2| Entity2V2 {
+                id: old.id,
+                prop2: old.prop2 as u64,
+                label: None,
+            },
+            db,
+        )?;
+    }
+    Ok(())
+}
+
+fn migrate_entity(old_version: u32, db: &Db) -> Result<()> {
+    if old_version < 2 {
+        MigratedEntity::migrate_field(
+            |old: MigratedEntityV1| MigratedEntity {
+                id: old.id,
+                prop: old.prop,
+                doubled: old.prop * 2,
+            },
+            db,
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Entity)]
+#[entity(crate = "crate")]
+pub struct GenericEntity<T> {
+    pub id: u32,
+    pub value: T,
+}
+
+impl crate::GenericStoreName for GenericEntity<Vec<String>> {
+    const STORE_NAME: &'static str = "generic_entity_strings";
+}
+
+impl crate::GenericStoreName for GenericEntity<u32> {
+    const STORE_NAME: &'static str = "generic_entity_u32";
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct NewtypeKey(pub u32);
+
+fn newtype_key_bytes(key: &NewtypeKey) -> Vec<u8> {
+    key.0.to_be_bytes().to_vec()
+}
+
+#[derive(Serialize, Deserialize, Entity)]
+#[entity(name = "newtype_key_entity", key_as_bytes = "newtype_key_bytes", crate = "crate")]
+pub struct NewtypeKeyEntity {
+    pub id: NewtypeKey,
+    pub prop: String,
+}
+
+#[derive(Serialize, Deserialize, Entity)]
+#[entity(name = "unique_entity", unique = "email, username", crate = "crate")]
+pub struct UniqueEntity {
+    pub id: u32,
+    pub email: String,
+    pub username: String,
+}
+
+#[derive(Serialize, Deserialize, Entity)]
+#[entity(name = "typed_key_entity", typed_key = "String", crate = "crate")]
+pub struct TypedKeyEntity {
+    pub id: TypedKeyEntityId,
+    pub prop: u32,
+}
+
+#[derive(Serialize, Deserialize, Entity)]
+#[entity(name = "timestamped_entity", timestamp_field = "modified_at", crate = "crate")]
+pub struct TimestampedEntity {
+    pub id: u32,
+    pub prop: String,
+    pub modified_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Entity)]
+#[entity(name = "skipped_field_entity", crate = "crate")]
+pub struct SkippedFieldEntity {
+    pub id: u32,
+    pub prop: u32,
+    #[serde(skip)]
+    #[entity_skip]
+    pub cache: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Entity)]
+#[entity(name = "conflicting_entity", crate = "crate")]
+pub struct ConflictingEntity1 {
+    pub id: u32,
+}
+
+// Deriving `Entity` with the store name of `ConflictingEntity1` would not build
+#[derive(Serialize, Deserialize)]
+pub struct ConflictingEntity2 {
+    pub id: u32,
+}
+
+impl Entity for ConflictingEntity2 {
+    type Key = u32;
+    fn store_name() -> &'static str {
+        "conflicting_entity"
+    }
+    fn get_key(&self) -> &Self::Key {
+        &self.id
+    }
+    fn set_key(&mut self, key: &Self::Key) {
+        self.id = *key;
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ValidatedEntity {
+    pub id: u32,
+    pub name: String,
+}
+
+impl Entity for ValidatedEntity {
+    type Key = u32;
+    fn store_name() -> &'static str {
+        "validated_entity"
+    }
+    fn get_key(&self) -> &Self::Key {
+        &self.id
+    }
+    fn set_key(&mut self, key: &Self::Key) {
+        self.id = *key;
+    }
+    fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            return Err(crate::Error::new(
+                crate::ErrorKind::IntegrityError,
+                String::from("name cannot be empty"),
+            ));
+        }
+        Ok(())
+    }
+    fn on_before_remove(&self, _db: &Db) -> Result<()> {
+        if self.name == "locked" {
+            return Err(crate::Error::new(
+                crate::ErrorKind::IntegrityError,
+                String::from("entity is locked"),
+            ));
+        }
+        Ok(())
+    }
+}
+
 pub fn set_up(name: &str) -> Result<Db> {
     let mut dir = std::env::temp_dir();
     dir.push(name);
 
     let db = crate::open(dir.to_str().unwrap())?;
-    Entity1::register(&db)?;
-    Entity2::register(&db)?;
-    Entity3::register(&db)?;
-    ChildEntity1::register(&db)?;
-    ChildEntity2::register(&db)?;
-    GrandChildEntity::register(&db)?;
+    register_entities(&db)?;
     Ok(db)
 }
 
+pub fn register_entities(db: &Db) -> Result<()> {
+    Entity1::register(db)?;
+    Entity2::register(db)?;
+    Entity3::register(db)?;
+    ChildEntity1::register(db)?;
+    ChildEntity2::register(db)?;
+    GrandChildEntity::register(db)?;
+    Entity4::register(db)?;
+    ChildEntity3::register(db)?;
+    Ok(())
+}
+
 pub fn set_up_content(db: &Db) -> Result<()> {
     let mut e1 = Entity1 {
         id: 0,
@@ -0,0 +1,36 @@
+//! # Trace Module
+//! Instrumentation of entity operations with `tracing` spans, only compiled with the `tracing` feature.
+
+/// Enters a `tracing` span until the end of the current block, named after the operation and with
+/// `entity_type`, `key` (as hex bytes) and `target_type` fields.
+/// This expands to nothing without the `tracing` feature.
+macro_rules! trace_span {
+    ($operation:literal, $entity_type:expr) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($operation, entity_type = $entity_type).entered();
+    };
+    ($operation:literal, $entity_type:expr, $key:expr) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            $operation,
+            entity_type = $entity_type,
+            key = %crate::trace::hex(&$key)
+        )
+        .entered();
+    };
+    ($operation:literal, $entity_type:expr, $key:expr, $target_type:expr) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            $operation,
+            entity_type = $entity_type,
+            key = %crate::trace::hex(&$key),
+            target_type = $target_type
+        )
+        .entered();
+    };
+}
+
+#[cfg(feature = "tracing")]
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
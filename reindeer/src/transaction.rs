@@ -0,0 +1,263 @@
+//! # Transaction Module
+//! This module provides [`transaction`](fn.transaction.html), to write entities of several stores atomically.
+
+use std::cell::{Cell, RefCell, RefMut};
+use std::collections::{BTreeMap, HashSet};
+
+use sled::transaction::ConflictableTransactionError;
+use sled::{Db, IVec};
+
+use crate::entity::AsBytes;
+use crate::error::Result;
+use crate::relation::{EntityRelations, Relation};
+use crate::ttl::Ttl;
+use crate::write::{ApplyFn, EntityWriter, GetTreesFn};
+use crate::{Entity, Error, ErrorKind};
+
+/// Entries of a store read and written through a [`TransactionalDb`](struct.TransactionalDb.html)
+struct StoreAccess {
+    get_trees: GetTreesFn,
+    apply: ApplyFn,
+    reads: BTreeMap<Vec<u8>, Option<IVec>>,
+    writes: BTreeMap<Vec<u8>, Option<IVec>>,
+}
+
+/// Handle given to the closure of [`transaction`](fn.transaction.html), to save, remove and get entities of any store.
+///
+/// Writes are kept in memory until the closure returns, and are only visible through this handle in the meantime.
+/// Entities read through this handle are checked again when committing, so that concurrent writes are never lost.
+pub struct TransactionalDb<'a> {
+    db: &'a Db,
+    stores: RefCell<BTreeMap<&'static str, StoreAccess>>,
+}
+
+impl<'a> TransactionalDb<'a> {
+    fn new(db: &'a Db) -> Self {
+        TransactionalDb {
+            db,
+            stores: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    fn store<E: Entity>(&self) -> RefMut<'_, StoreAccess> {
+        RefMut::map(self.stores.borrow_mut(), |stores| {
+            stores
+                .entry(E::store_name())
+                .or_insert_with(|| StoreAccess {
+                    get_trees: EntityWriter::get_trees::<E>,
+                    apply: EntityWriter::apply::<E>,
+                    reads: BTreeMap::new(),
+                    writes: BTreeMap::new(),
+                })
+        })
+    }
+
+    /// Retrieves an entity instance given its key, taking the writes of the transaction into account.
+    ///
+    /// The same entity is returned every time it is read through this handle, and if it is changed concurrently before
+    /// the transaction is committed, the transaction is run again.
+    pub fn get<E: Entity>(&self, key: &E::Key) -> Result<Option<E>> {
+        let key = key.as_bytes();
+        let known = {
+            let store = self.store::<E>();
+            store
+                .writes
+                .get(&key)
+                .or_else(|| store.reads.get(&key))
+                .cloned()
+        };
+        let value = match known {
+            Some(value) => value,
+            None => {
+                let value = E::get_tree(self.db)?.get(&key)?;
+                self.store::<E>().reads.insert(key.clone(), value.clone());
+                value
+            }
+        };
+        if Ttl::is_expired(E::store_name(), &key, self.db)? {
+            return Ok(None);
+        }
+        value.map(E::try_from_ivec).transpose()
+    }
+
+    /// Saves an entity when the transaction is committed.
+    pub fn save<E: Entity>(&self, entity: &E) -> Result<()> {
+        let value = entity.try_into_ivec_with_limit(E::MAX_SIZE_BYTES)?;
+        self.write::<E>(entity.get_key().as_bytes(), Some(value));
+        Ok(())
+    }
+
+    /// Removes an entity given its key when the transaction is committed.
+    ///
+    /// ⚠ Only entities whose removal has no effect on other entities can be removed in a transaction :
+    /// if the entity has related entities, siblings or children, this will result in an error.
+    pub fn remove<E: Entity>(&self, key: &E::Key) -> Result<()> {
+        let key = key.as_bytes();
+        let mut removable_entities = EntityRelations::default();
+        Relation::can_be_deleted(
+            E::store_name(),
+            &key,
+            &mut HashSet::new(),
+            &mut removable_entities,
+            self.db,
+        )?;
+        let descriptor =
+            Relation::get_descriptor_with_key_and_tree_name(E::store_name(), &key, self.db)?;
+        if !removable_entities.related_entities.is_empty()
+            || descriptor.related_entities.values().any(|v| !v.is_empty())
+        {
+            return Err(Error::new(
+                ErrorKind::IntegrityError,
+                format!(
+                    "Entity in {} has related entities and cannot be removed in a transaction",
+                    E::store_name()
+                ),
+            ));
+        }
+        self.write::<E>(key, None);
+        Ok(())
+    }
+
+    fn write<E: Entity>(&self, key: Vec<u8>, value: Option<IVec>) {
+        self.store::<E>().writes.insert(key, value);
+    }
+
+    /// Writes everything saved or removed through this handle in a single `sled` transaction, along with the unique
+    /// indexes and expiry dates of the written entities.
+    /// Returns `false` without writing anything if an entity read through this handle was changed in the meantime.
+    fn commit(self) -> Result<bool> {
+        let stores = self.stores.into_inner();
+        let mut trees = Vec::new();
+        let mut ranges = Vec::new();
+        for access in stores.values() {
+            let start = trees.len();
+            trees.extend((access.get_trees)(self.db)?);
+            ranges.push(start..trees.len());
+        }
+        let conflict = Cell::new(false);
+        let result = EntityWriter::transaction(&trees, |trees| {
+            conflict.set(false);
+            for (access, range) in stores.values().zip(&ranges) {
+                let trees = &trees[range.clone()];
+                for (key, read) in &access.reads {
+                    if trees[0].get(key)? != *read {
+                        conflict.set(true);
+                        return Err(ConflictableTransactionError::Abort(Error::new(
+                            ErrorKind::IntegrityError,
+                            String::from(
+                                "An entity read in the transaction was changed concurrently",
+                            ),
+                        )));
+                    }
+                }
+                // Removals go first, so that saved entities can take the unique values of removed ones
+                let (removals, saves): (Vec<_>, Vec<_>) =
+                    access.writes.iter().partition(|(_, value)| value.is_none());
+                for (key, value) in removals.into_iter().chain(saves) {
+                    (access.apply)(trees, key, value.as_ref())?;
+                }
+            }
+            Ok(())
+        });
+        match result {
+            Err(_) if conflict.get() => Ok(false),
+            Err(e) => Err(e),
+            Ok(()) => Ok(true),
+        }
+    }
+}
+
+/// Runs `f` with a [`TransactionalDb`](struct.TransactionalDb.html) handle, and atomically writes everything saved or removed
+/// through it once `f` returns : either all writes are applied, or none. Unique constraints are checked and expiry dates
+/// are cleared like with [`Entity::save`](trait.Entity.html#method.save).
+///
+/// If `f` returns an error, nothing is written and the error is returned.
+///
+/// If an entity read through the handle was changed concurrently by the time the transaction is committed, nothing is
+/// written and `f` is run again with a new handle, so that read-modify-write sequences never lose updates.
+/// `f` may therefore be called several times, and should not have other side effects.
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # Account { id : 1, balance : 100 }.save(&db)?;
+/// reindeer::transaction(&db, |tx| {
+///     let mut account = tx.get::<Account>(&1)?.unwrap();
+///     account.balance -= 10;
+///     tx.save(&account)?;
+///     tx.save(&Transfer { id : 9, from : 1, amount : 10 })?;
+///     Ok(())
+/// })?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn transaction<F, R>(db: &Db, f: F) -> Result<R>
+where
+    F: Fn(&TransactionalDb) -> Result<R>,
+{
+    loop {
+        let tx = TransactionalDb::new(db);
+        let result = f(&tx)?;
+        if tx.commit()? {
+            return Ok(result);
+        }
+    }
+}
+
+/// Builder returned by [`atomic_save`](fn.atomic_save.html), collecting entities of any store to be saved atomically.
+pub struct AtomicSaveBuilder<'db> {
+    tx: TransactionalDb<'db>,
+    count: usize,
+    error: Option<Error>,
+}
+
+impl<'db> AtomicSaveBuilder<'db> {
+    /// Adds an entity to be saved when the builder is committed.
+    ///
+    /// The entity is serialized and validated right away : if this fails, the error is returned by
+    /// [`commit`](struct.AtomicSaveBuilder.html#method.commit) and nothing is written.
+    #[allow(clippy::should_implement_trait)]
+    pub fn add<E: Entity>(mut self, entity: &E) -> Self {
+        if self.error.is_none() {
+            match self.tx.save(entity) {
+                Ok(()) => self.count += 1,
+                Err(e) => self.error = Some(e),
+            }
+        }
+        self
+    }
+
+    /// Writes all the added entities atomically, and returns the number of saved entities.
+    pub fn commit(self) -> Result<usize> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        self.tx.commit()?;
+        Ok(self.count)
+    }
+}
+
+/// Saves entities of any stores in a single `sled` transaction : either all of them are written, or none.
+/// Unique constraints are checked and expiry dates are cleared like with [`Entity::save`](trait.Entity.html#method.save).
+///
+/// This is a shorthand for a [`transaction`](fn.transaction.html) that only saves entities.
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # let account = Account { id : 1, balance : 90 };
+/// # let transfer = Transfer { id : 9, from : 1, amount : 10 };
+/// reindeer::atomic_save(&db)
+///     .add(&account)
+///     .add(&transfer)
+///     .commit()?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn atomic_save(db: &Db) -> AtomicSaveBuilder<'_> {
+    AtomicSaveBuilder {
+        tx: TransactionalDb::new(db),
+        count: 0,
+        error: None,
+    }
+}
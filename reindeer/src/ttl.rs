@@ -0,0 +1,223 @@
+//! # TTL Module
+//! This module keeps track of the expiry dates of entities saved with
+//! [`Entity::save_with_ttl`](trait.Entity.html#method.save_with_ttl), and removes them once expired.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{OnceLock, PoisonError, RwLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use sled::{Db, Tree};
+
+use crate::error::Result;
+use crate::{Entity, Error, ErrorKind, FromBytes};
+
+pub(crate) const TTL_TREE_PREFIX: &str = "__$ttl_";
+
+type RemoveFn = fn(&[u8], &Db) -> Result<()>;
+
+fn expiry_registry() -> &'static RwLock<HashMap<String, RemoveFn>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<String, RemoveFn>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn decode_expiry(value: &[u8]) -> Result<u64> {
+    u64::from_bytes(value)
+}
+
+pub struct Ttl;
+
+impl Ttl {
+    fn tree_name(store: &str) -> String {
+        format!("{}{}", TTL_TREE_PREFIX, store)
+    }
+
+    fn get_tree(store: &str, db: &Db) -> Result<Tree> {
+        Ok(db.open_tree(Ttl::tree_name(store))?)
+    }
+
+    fn is_enabled(store: &str) -> bool {
+        expiry_registry()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(store)
+    }
+
+    pub fn enable<E: Entity>() {
+        expiry_registry()
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(String::from(E::store_name()), E::remove_from_u8_array);
+    }
+
+    pub fn register<E: Entity>(db: &Db) {
+        let tree_name = Ttl::tree_name(E::store_name());
+        if db
+            .tree_names()
+            .iter()
+            .any(|name| name == tree_name.as_bytes())
+        {
+            Ttl::enable::<E>();
+        }
+    }
+
+    /// Opens the TTL tree of `store` if expiry dates are enabled for it.
+    pub fn get_enabled_tree(store: &str, db: &Db) -> Result<Option<Tree>> {
+        if !Ttl::is_enabled(store) {
+            return Ok(None);
+        }
+        Ok(Some(Ttl::get_tree(store, db)?))
+    }
+
+    pub fn set_expiry(store: &str, key: &[u8], ttl: Duration, db: &Db) -> Result<()> {
+        let expiry = now_millis().saturating_add(ttl.as_millis() as u64);
+        Ttl::get_tree(store, db)?.insert(key, &expiry.to_be_bytes())?;
+        Ok(())
+    }
+
+    pub fn is_expired(store: &str, key: &[u8], db: &Db) -> Result<bool> {
+        if !Ttl::is_enabled(store) {
+            return Ok(false);
+        }
+        match Ttl::get_tree(store, db)?.get(key)? {
+            Some(value) => Ok(decode_expiry(&value)? <= now_millis()),
+            None => Ok(false),
+        }
+    }
+}
+
+/// Removes every expired entity saved with [`Entity::save_with_ttl`](trait.Entity.html#method.save_with_ttl),
+/// and returns the number of removed entities.
+///
+/// Expired entities are removed like with [`Entity::remove`](trait.Entity.html#method.remove), so relation constraints
+/// are enforced : an expired entity that cannot be removed is kept, and will be tried again on the next sweep. With the
+/// `tracing` feature, the reason why it could not be removed is logged as a warning.
+///
+/// Only stores registered with [`Entity::register`](trait.Entity.html#method.register) are swept.
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// let removed = reindeer::sweep_expired(&db)?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn sweep_expired(db: &Db) -> Result<usize> {
+    let now = now_millis();
+    let mut count = 0;
+    for tree_name in db.tree_names() {
+        let store = match std::str::from_utf8(&tree_name)
+            .ok()
+            .and_then(|name| name.strip_prefix(TTL_TREE_PREFIX))
+        {
+            Some(store) => store,
+            None => continue,
+        };
+        let remove = expiry_registry()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(store)
+            .copied();
+        let remove = match remove {
+            Some(remove) => remove,
+            None => continue,
+        };
+        let tree = db.open_tree(&tree_name)?;
+        for elem in tree.iter() {
+            let (key, value) = elem?;
+            if decode_expiry(&value)? > now {
+                continue;
+            }
+            match remove(&key, db) {
+                Ok(()) => count += 1,
+                #[cfg(feature = "tracing")]
+                Err(e) => tracing::warn!(
+                    entity_type = store,
+                    key = %crate::trace::hex(&key),
+                    error = %e,
+                    "could not remove expired entity"
+                ),
+                #[cfg(not(feature = "tracing"))]
+                Err(_) => {}
+            }
+        }
+    }
+    Ok(count)
+}
+
+/// Handle on the thread spawned by [`run_expiry_sweeper`](fn.run_expiry_sweeper.html).
+///
+/// The thread is stopped when this handle is dropped, or with [`stop`](struct.ExpirySweeper.html#method.stop).
+pub struct ExpirySweeper {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl ExpirySweeper {
+    /// Stops the thread, waits for the current sweep to finish, and returns the error that stopped the thread
+    /// earlier, if any.
+    pub fn stop(mut self) -> Result<()> {
+        self.stop.take();
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(Error::new(
+                ErrorKind::IntegrityError,
+                String::from("The expiry sweeper thread panicked"),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ExpirySweeper {
+    fn drop(&mut self) {
+        self.stop.take();
+    }
+}
+
+/// Spawns a thread that calls [`sweep_expired`](fn.sweep_expired.html) every `interval`, until the returned
+/// [`ExpirySweeper`](struct.ExpirySweeper.html) is dropped or stopped.
+///
+/// If a sweep fails, the thread stops, and the error is returned by [`ExpirySweeper::stop`](struct.ExpirySweeper.html#method.stop).
+///
+/// ⚠ The thread keeps a handle on the database, which is therefore not closed before the thread is stopped.
+///
+/// ### Exemple
+/// ```rust
+/// # mod setup { include!("../doctest_setup.rs"); } use setup::*;
+/// # let db = doctest_db()?;
+/// # use std::time::Duration;
+/// let sweeper = reindeer::run_expiry_sweeper(&db, Duration::from_secs(60));
+/// // ...
+/// sweeper.stop()?;
+/// # Ok::<(), reindeer::Error>(())
+/// ```
+pub fn run_expiry_sweeper(db: &Db, interval: Duration) -> ExpirySweeper {
+    let db = db.clone();
+    let (stop, stopped) = mpsc::channel::<()>();
+    let thread = std::thread::spawn(move || loop {
+        match stopped.recv_timeout(interval) {
+            Err(RecvTimeoutError::Timeout) => {
+                let result = sweep_expired(&db);
+                #[cfg(feature = "tracing")]
+                if let Err(e) = &result {
+                    tracing::error!(error = %e, "expiry sweep failed");
+                }
+                result?;
+            }
+            _ => return Ok(()),
+        }
+    });
+    ExpirySweeper {
+        stop: Some(stop),
+        thread: Some(thread),
+    }
+}
@@ -0,0 +1,97 @@
+//! # Unique Module
+//! This module enforces the unique field constraints declared with `#[entity(unique = "field")]`, using an index
+//! tree per unique field that maps each value to the key of the entity holding it.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use sled::{Batch, Db, Tree};
+
+use crate::entity::Entity;
+use crate::error::{ErrorContext, Result};
+use crate::{Error, ErrorKind};
+
+pub(crate) const UNIQUE_TREE_PREFIX: &str = "__$unique_";
+
+/// Serializes the value of a unique field to the bytes it is indexed with.
+#[doc(hidden)]
+pub fn unique_value<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    Ok(bincode::serialize(value)?)
+}
+
+pub struct Unique;
+
+impl Unique {
+    pub fn get_tree(store: &str, field: &str, db: &Db) -> Result<Tree> {
+        Ok(db.open_tree(format!("{}{}_{}", UNIQUE_TREE_PREFIX, store, field))?)
+    }
+
+    /// Rebuilds the unique indexes of `E` from the entities of its store, so that entities written without them
+    /// (imports of whole databases, older versions...) are indexed too.
+    /// Fails without changing the indexes if two entities have the same value for one of the unique fields.
+    pub fn register<E: Entity>(db: &Db) -> Result<()> {
+        if E::get_unique_fields().is_empty() {
+            return Ok(());
+        }
+        let mut entities = Vec::new();
+        for elem in E::get_tree(db)?.iter() {
+            let (key, value) = elem?;
+            entities.push((key, E::try_from_ivec(value)?));
+        }
+        let indexes = Unique::build_indexes(&entities)?;
+        Unique::write_indexes::<E>(indexes, db)
+    }
+
+    /// Computes the unique indexes of the given `(key, entity)` pairs : for each unique field of `E`, the key of the
+    /// entity holding each value.
+    /// Fails if two entities have the same value for one of the unique fields.
+    pub fn build_indexes<K: AsRef<[u8]>, E: Entity>(
+        entities: &[(K, E)],
+    ) -> Result<Vec<BTreeMap<Vec<u8>, Vec<u8>>>> {
+        let fields = E::get_unique_fields();
+        let mut indexes = vec![BTreeMap::new(); fields.len()];
+        for (key, entity) in entities {
+            for (field, value) in entity.get_unique_values()? {
+                let index = match fields.iter().position(|f| *f == field) {
+                    Some(index) => &mut indexes[index],
+                    None => continue,
+                };
+                if index.contains_key(&value) {
+                    return Err(Error::new(
+                        ErrorKind::UniqueConstraintViolation,
+                        format!(
+                            "Several entities of {} have the same {}",
+                            E::store_name(),
+                            field
+                        ),
+                    )
+                    .with_context(ErrorContext::UniqueConstraintViolation { field, value }));
+                }
+                index.insert(value, key.as_ref().to_vec());
+            }
+        }
+        Ok(indexes)
+    }
+
+    /// Replaces the unique indexes of `E` with `indexes`, as returned by [`Unique::build_indexes`].
+    pub fn write_indexes<E: Entity>(
+        indexes: Vec<BTreeMap<Vec<u8>, Vec<u8>>>,
+        db: &Db,
+    ) -> Result<()> {
+        for (field, index) in E::get_unique_fields().into_iter().zip(indexes) {
+            let tree = Unique::get_tree(E::store_name(), field, db)?;
+            let mut batch = Batch::default();
+            for elem in tree.iter() {
+                let (value, _) = elem?;
+                if !index.contains_key(value.as_ref()) {
+                    batch.remove(value);
+                }
+            }
+            for (value, key) in index {
+                batch.insert(value, key);
+            }
+            tree.apply_batch(batch)?;
+        }
+        Ok(())
+    }
+}
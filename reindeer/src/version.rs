@@ -0,0 +1,107 @@
+//! # Version Module
+//! This module keeps track of the versions of entities saved with
+//! [`Entity::versioned_save`](trait.Entity.html#method.versioned_save).
+
+use sled::transaction::ConflictableTransactionError;
+use sled::{Db, IVec, Tree};
+
+use crate::error::{entity_not_found, Result};
+use crate::write::EntityWriter;
+use crate::{Entity, Error, ErrorKind, FromBytes};
+
+pub(crate) const VERSION_TREE_PREFIX: &str = "__$version_";
+
+fn decode_version(value: &[u8]) -> Result<u64> {
+    u64::from_bytes(value)
+}
+
+pub struct Version;
+
+impl Version {
+    fn get_tree(store: &str, db: &Db) -> Result<Tree> {
+        Ok(db.open_tree(format!("{}{}", VERSION_TREE_PREFIX, store))?)
+    }
+
+    pub fn get(store: &str, key: &[u8], db: &Db) -> Result<Option<u64>> {
+        Version::get_tree(store, db)?
+            .get(key)?
+            .map(|value| decode_version(&value))
+            .transpose()
+    }
+
+    fn conflict(store: &str, expected_version: u64, current: u64) -> Error {
+        Error::new(
+            ErrorKind::VersionConflict {
+                expected: expected_version,
+                current,
+            },
+            format!(
+                "Expected version {} but found version {} in {}",
+                expected_version, current, store
+            ),
+        )
+    }
+
+    /// Opens the trees written by a versioned write of `E` : the trees written along with an entity of `E`,
+    /// followed by its version tree.
+    fn get_trees<E: Entity>(db: &Db) -> Result<Vec<Tree>> {
+        let mut trees = EntityWriter::get_trees::<E>(db)?;
+        trees.push(Version::get_tree(E::store_name(), db)?);
+        Ok(trees)
+    }
+
+    pub fn save<E: Entity>(key: &[u8], value: IVec, expected_version: u64, db: &Db) -> Result<()> {
+        EntityWriter::transaction(&Version::get_trees::<E>(db)?, |trees| {
+            let (versions, trees) = trees
+                .split_last()
+                .expect("the version tree is always opened");
+            let current = versions
+                .get(key)?
+                .map(|value| decode_version(&value))
+                .transpose()
+                .map_err(ConflictableTransactionError::Abort)?
+                .unwrap_or(0);
+            if current != expected_version {
+                return Err(ConflictableTransactionError::Abort(Version::conflict(
+                    E::store_name(),
+                    expected_version,
+                    current,
+                )));
+            }
+            EntityWriter::apply::<E>(trees, key, Some(&value))?;
+            versions.insert(key, &(current + 1).to_be_bytes())?;
+            Ok(())
+        })
+    }
+
+    /// Replaces the value of `key` with the result of `f` if its version is `expected_version`, and increments its version.
+    /// Returns `false` if the version did not match, in which case nothing is written.
+    pub fn update<E: Entity, F: Fn(IVec) -> Result<IVec>>(
+        key: &[u8],
+        expected_version: u64,
+        f: F,
+        db: &Db,
+    ) -> Result<bool> {
+        EntityWriter::transaction(&Version::get_trees::<E>(db)?, |trees| {
+            let (versions, trees) = trees
+                .split_last()
+                .expect("the version tree is always opened");
+            let current = versions
+                .get(key)?
+                .map(|value| decode_version(&value))
+                .transpose()
+                .map_err(ConflictableTransactionError::Abort)?
+                .unwrap_or(0);
+            if current != expected_version {
+                return Ok(false);
+            }
+            let value = trees[0].get(key)?.ok_or_else(|| {
+                ConflictableTransactionError::Abort(entity_not_found(E::store_name(), key))
+            })?;
+            let value = f(value).map_err(ConflictableTransactionError::Abort)?;
+            EntityWriter::apply::<E>(trees, key, Some(&value))?;
+            versions.insert(key, &(current + 1).to_be_bytes())?;
+            Ok(true)
+        })
+    }
+}
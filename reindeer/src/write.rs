@@ -0,0 +1,402 @@
+//! # Write Module
+//! This module writes entities along with the side trees that depend on their value : the indexes of their unique
+//! fields, which must point to the entity holding each value, and their expiry date, which every write clears.
+//! All the methods writing entities go through it, and removals go through [`Removal`], which also removes the relations
+//! of removed entities and the entities removed along with them.
+
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
+
+use sled::transaction::{
+    ConflictableTransactionError, ConflictableTransactionResult, TransactionError,
+    TransactionalTree,
+};
+use sled::{Batch, Db, IVec, Transactional, Tree};
+
+use crate::entity::Entity;
+use crate::error::{entity_not_found, ErrorContext, Result};
+use crate::relation::{DeletionBehaviour, EntityRelations, Relation};
+use crate::sibling_link::SiblingLink;
+use crate::soft_delete::SoftDelete;
+use crate::ttl::Ttl;
+use crate::unique::Unique;
+use crate::{Error, ErrorKind};
+
+pub(crate) type GetTreesFn = fn(&Db) -> Result<Vec<Tree>>;
+pub(crate) type ApplyFn =
+    fn(&[TransactionalTree], &[u8], Option<&IVec>) -> ConflictableTransactionResult<(), Error>;
+type HookFn = fn(&[u8], &Db) -> Result<()>;
+
+pub struct EntityWriter;
+
+impl EntityWriter {
+    /// Opens the trees written along with an entity of `E`, in the order expected by [`EntityWriter::apply`] : the store of
+    /// `E`, one index tree per unique field, and its TTL tree if expiry dates are enabled for this store.
+    pub fn get_trees<E: Entity>(db: &Db) -> Result<Vec<Tree>> {
+        let mut trees = vec![E::get_tree(db)?];
+        for field in E::get_unique_fields() {
+            trees.push(Unique::get_tree(E::store_name(), field, db)?);
+        }
+        if let Some(ttl) = Ttl::get_enabled_tree(E::store_name(), db)? {
+            trees.push(ttl);
+        }
+        Ok(trees)
+    }
+
+    /// Writes `value`, a serialized entity of `E`, with key `key`, or removes this key if `value` is `None`,
+    /// within a transaction over the trees returned by [`EntityWriter::get_trees`].
+    ///
+    /// The write is aborted if another entity holds the same value for one of the unique fields of `E`.
+    /// Index entries of a replaced value that cannot be deserialized are left behind : as they do not match the
+    /// stored entity anymore, they are ignored and overwritten by later writes.
+    pub fn apply<E: Entity>(
+        trees: &[TransactionalTree],
+        key: &[u8],
+        value: Option<&IVec>,
+    ) -> ConflictableTransactionResult<(), Error> {
+        let fields = E::get_unique_fields();
+        let store = &trees[0];
+        let (indexes, ttl) = trees[1..].split_at(fields.len());
+        let index_of = |field: &str| fields.iter().position(|f| *f == field).map(|i| &indexes[i]);
+        let values = match value {
+            Some(value) if !fields.is_empty() => E::try_from_ivec(value.clone())
+                .and_then(|entity| entity.get_unique_values())
+                .map_err(ConflictableTransactionError::Abort)?,
+            _ => Vec::new(),
+        };
+        for (field, field_value) in &values {
+            let index = match index_of(field) {
+                Some(index) => index,
+                None => continue,
+            };
+            if let Some(owner) = index.get(field_value)? {
+                if owner != key {
+                    let still_owned = match store.get(&owner)? {
+                        Some(stored) => E::try_from_ivec(stored)
+                            .and_then(|other| other.get_unique_values())
+                            .map(|other| other.contains(&(field, field_value.clone())))
+                            .unwrap_or(true),
+                        None => false,
+                    };
+                    if still_owned {
+                        return Err(ConflictableTransactionError::Abort(
+                            Error::new(
+                                ErrorKind::UniqueConstraintViolation,
+                                format!(
+                                    "Another entity of {} has the same {}",
+                                    E::store_name(),
+                                    field
+                                ),
+                            )
+                            .with_context(
+                                ErrorContext::UniqueConstraintViolation {
+                                    field,
+                                    value: field_value.clone(),
+                                },
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+        let old = match value {
+            Some(value) => store.insert(key, value.clone())?,
+            None => store.remove(key)?,
+        };
+        let old_values = old
+            .filter(|_| !fields.is_empty())
+            .and_then(|old| E::try_from_ivec(old).ok())
+            .and_then(|old| old.get_unique_values().ok());
+        if let Some(old_values) = old_values {
+            for (field, old_value) in &old_values {
+                if let Some(index) = index_of(field) {
+                    if index.get(old_value)?.as_deref() == Some(key) {
+                        index.remove(old_value.as_slice())?;
+                    }
+                }
+            }
+        }
+        for (field, field_value) in &values {
+            if let Some(index) = index_of(field) {
+                index.insert(field_value.as_slice(), key)?;
+            }
+        }
+        if let Some(ttl) = ttl.first() {
+            ttl.remove(key)?;
+        }
+        Ok(())
+    }
+
+    /// Writes serialized entities of `E` given as `(key, value)` pairs : either all of them are written, or none.
+    pub fn save<E: Entity>(entries: &[(Vec<u8>, IVec)], db: &Db) -> Result<()> {
+        let trees = EntityWriter::get_trees::<E>(db)?;
+        if trees.len() == 1 {
+            let mut batch = Batch::default();
+            for (key, value) in entries {
+                batch.insert(key.as_slice(), value.clone());
+            }
+            return Ok(trees[0].apply_batch(batch)?);
+        }
+        EntityWriter::transaction(&trees, |trees| {
+            for (key, value) in entries {
+                EntityWriter::apply::<E>(trees, key, Some(value))?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Atomically replaces the entity of key `key` with the result of `f`, and returns `false` if it does not exist.
+    /// If `f` fails, or if the updated entity cannot be written, the entity is left unchanged and the error is returned.
+    ///
+    /// `f` may be called several times if the entity is changed concurrently.
+    pub fn update<E: Entity, F: Fn(&mut E) -> Result<()>>(
+        key: &[u8],
+        f: F,
+        db: &Db,
+    ) -> Result<bool> {
+        let trees = EntityWriter::get_trees::<E>(db)?;
+        EntityWriter::transaction(&trees, |trees| {
+            let current = match trees[0].get(key)? {
+                Some(current) => current,
+                None => return Ok(false),
+            };
+            let value = E::try_from_ivec(current)
+                .and_then(|mut entity| {
+                    f(&mut entity)?;
+                    entity.try_into_ivec_with_limit(E::MAX_SIZE_BYTES)
+                })
+                .map_err(ConflictableTransactionError::Abort)?;
+            EntityWriter::apply::<E>(trees, key, Some(&value))?;
+            Ok(true)
+        })
+    }
+
+    /// Runs `f` in a transaction over `trees`, returning errors it aborts with as they are.
+    pub fn transaction<R, F>(trees: &[Tree], f: F) -> Result<R>
+    where
+        F: Fn(&Vec<TransactionalTree>) -> ConflictableTransactionResult<R, Error>,
+    {
+        match trees.transaction(f) {
+            Ok(result) => Ok(result),
+            Err(TransactionError::Abort(e)) => Err(e),
+            Err(TransactionError::Storage(e)) => Err(e.into()),
+        }
+    }
+}
+
+/// Trees opened for a transaction, that can be found by name.
+#[derive(Default)]
+pub struct TreeSet {
+    pub trees: Vec<Tree>,
+    indexes: HashMap<String, usize>,
+}
+
+impl TreeSet {
+    /// Opens the tree `name` if it is not in the set yet, and returns its index in the set.
+    pub fn open(&mut self, name: &str, db: &Db) -> Result<usize> {
+        if let Some(index) = self.indexes.get(name) {
+            return Ok(*index);
+        }
+        self.trees.push(db.open_tree(name)?);
+        self.indexes
+            .insert(String::from(name), self.trees.len() - 1);
+        Ok(self.trees.len() - 1)
+    }
+}
+
+/// Entities of a store removed with a [`Removal`]
+struct RemovedStore {
+    get_trees: GetTreesFn,
+    apply: ApplyFn,
+    on_before_remove: HookFn,
+    pre_remove_hook: HookFn,
+    keys: Vec<Vec<u8>>,
+}
+
+/// Entities removed together.
+///
+/// Relation constraints are checked for all of them at once, so that relations between them never prevent their
+/// removal. They are then removed in a single transaction, along with their unique index entries and expiry dates,
+/// the entities removed with them (`DeletionBehaviour::Cascade`), and the relations of all removed entities.
+///
+/// `SetNull` and `Custom` deletion behaviours are applied to related entities before this transaction :
+/// if it fails, they are not reverted.
+#[derive(Default)]
+pub struct Removal {
+    stores: BTreeMap<&'static str, RemovedStore>,
+}
+
+impl Removal {
+    /// Adds the entity of `E` with key `key` to the removed entities.
+    pub fn add<E: Entity>(&mut self, key: &[u8]) {
+        let store = self
+            .stores
+            .entry(E::store_name())
+            .or_insert_with(|| RemovedStore {
+                get_trees: EntityWriter::get_trees::<E>,
+                apply: EntityWriter::apply::<E>,
+                on_before_remove: on_before_remove::<E>,
+                pre_remove_hook: pre_remove_hook::<E>,
+                keys: Vec::new(),
+            });
+        if !store.keys.iter().any(|k| k == key) {
+            store.keys.push(key.to_vec());
+        }
+    }
+
+    fn roots(&self) -> impl Iterator<Item = (&'static str, &Vec<u8>)> {
+        self.stores
+            .iter()
+            .flat_map(|(name, store)| store.keys.iter().map(move |key| (*name, key)))
+    }
+
+    /// Checks relation constraints of all removed entities, and returns the other entities to be removed, updated or
+    /// notified along with them.
+    fn check(&self, db: &Db) -> Result<EntityRelations> {
+        let mut removed = self
+            .roots()
+            .map(|(name, key)| (String::from(name), key.clone()))
+            .collect::<HashSet<(String, Vec<u8>)>>();
+        let roots = removed.clone();
+        let mut affected = EntityRelations::default();
+        for (name, key) in self.roots() {
+            Relation::can_be_deleted(name, key, &mut removed, &mut affected, db)?;
+        }
+        for (name, related) in affected.related_entities.iter_mut() {
+            related.retain(|rd| !roots.contains(&(name.clone(), rd.key.clone())));
+        }
+        Ok(affected)
+    }
+
+    /// Removes all the entities, or none of them if any cannot be removed.
+    pub fn commit(self, db: &Db) -> Result<()> {
+        if self.stores.is_empty() {
+            return Ok(());
+        }
+        for (name, key) in self.roots() {
+            (self.stores[name].on_before_remove)(key, db)?;
+        }
+        let mut affected = self.check(db)?;
+        for (name, key) in self.roots() {
+            (self.stores[name].pre_remove_hook)(key, db)?;
+        }
+        let mut updated = HashSet::new();
+        let cascaded = loop {
+            let cascaded = affected
+                .related_entities
+                .iter()
+                .flat_map(|(name, related)| related.iter().map(move |rd| (name, rd)))
+                .filter(|(_, rd)| rd.deletion_behaviour == DeletionBehaviour::Cascade)
+                .map(|(name, rd)| (name.clone(), rd.key.clone()))
+                .collect::<HashSet<(String, Vec<u8>)>>();
+            for (name, related) in &affected.related_entities {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    target_type = name.as_str(),
+                    count = related.len(),
+                    "updating related entities"
+                );
+                for rd in related {
+                    let entry = (name.clone(), rd.key.clone());
+                    if cascaded.contains(&entry) || updated.contains(&entry) {
+                        continue;
+                    }
+                    match &rd.deletion_behaviour {
+                        DeletionBehaviour::SetNull(field) => {
+                            Relation::set_null(name, &rd.key, field, db)?
+                        }
+                        DeletionBehaviour::Custom(f) => f(&rd.key, db)?,
+                        _ => continue,
+                    }
+                    updated.insert(entry);
+                }
+            }
+            if self.try_commit(&cascaded, db)? {
+                break cascaded;
+            }
+            affected = self.check(db)?;
+        };
+        for (name, key) in self.roots() {
+            SoftDelete::clear(name, key, db)?;
+            SiblingLink::clear(name, key, db)?;
+        }
+        for (name, key) in &cascaded {
+            SiblingLink::clear(name, key, db)?;
+        }
+        Ok(())
+    }
+
+    /// Removes all the entities along with the `cascaded` ones and their relations in a single transaction, and
+    /// returns `false` without removing anything if relations of a removed entity were changed since they were read.
+    fn try_commit(&self, cascaded: &HashSet<(String, Vec<u8>)>, db: &Db) -> Result<bool> {
+        let mut trees = TreeSet::default();
+        let mut ranges: BTreeMap<&str, Range<usize>> = BTreeMap::new();
+        for (name, store) in &self.stores {
+            let start = trees.trees.len();
+            trees.trees.extend((store.get_trees)(db)?);
+            ranges.insert(name, start..trees.trees.len());
+        }
+        let mut removed = self
+            .roots()
+            .map(|(name, key)| (String::from(name), key.clone()))
+            .collect::<Vec<(String, Vec<u8>)>>();
+        let mut typed = self.roots().collect::<Vec<(&'static str, &Vec<u8>)>>();
+        let mut untyped = Vec::new();
+        for (name, key) in cascaded {
+            match self.stores.get_key_value(name.as_str()) {
+                Some((name, _)) => typed.push((name, key)),
+                None => untyped.push((trees.open(name, db)?, key)),
+            }
+            removed.push((name.clone(), key.clone()));
+        }
+        let removed_set = removed
+            .iter()
+            .cloned()
+            .collect::<HashSet<(String, Vec<u8>)>>();
+        let relations = Relation::prepare_removal(&removed, &removed_set, &mut trees, db)?;
+        let stale = Cell::new(false);
+        let result = EntityWriter::transaction(&trees.trees, |tx| {
+            stale.set(false);
+            for (name, key) in &typed {
+                (self.stores[name].apply)(&tx[ranges[name].clone()], key, None)?;
+            }
+            for (index, key) in &untyped {
+                tx[*index].remove(key.as_slice())?;
+            }
+            if !relations.apply(tx)? {
+                stale.set(true);
+                return Err(ConflictableTransactionError::Abort(Error::new(
+                    ErrorKind::IntegrityError,
+                    String::from("Relations of a removed entity were changed concurrently"),
+                )));
+            }
+            Ok(())
+        });
+        match result {
+            Err(_) if stale.get() => Ok(false),
+            Err(e) => Err(e),
+            Ok(()) => Ok(true),
+        }
+    }
+}
+
+fn on_before_remove<E: Entity>(key: &[u8], db: &Db) -> Result<()> {
+    let entity = E::get_tree(db)?
+        .get(key)?
+        .and_then(|value| E::try_from_ivec(value).ok());
+    match entity {
+        Some(entity) => entity.on_before_remove(db),
+        None => Ok(()),
+    }
+}
+
+fn pre_remove_hook<E: Entity>(key: &[u8], db: &Db) -> Result<()> {
+    if !E::use_pre_remove_hook() {
+        return Ok(());
+    }
+    E::pre_remove_hook(
+        &E::get_from_u8_array(key, db)?.ok_or_else(|| entity_not_found(E::store_name(), key))?,
+        db,
+    )
+}